@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE login_attempts (
+    key_type TEXT NOT NULL,
+    key_value TEXT NOT NULL,
+    PRIMARY KEY (key_type, key_value),
+    failed_count INT NOT NULL,
+    last_failed_at timestamptz NOT NULL,
+    locked_until timestamptz NULL
+);
\ No newline at end of file
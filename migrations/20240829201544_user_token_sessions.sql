@@ -0,0 +1,15 @@
+-- Add migration script here
+ALTER TABLE user_tokens
+    ADD COLUMN user_agent TEXT NULL,
+    ADD COLUMN ip TEXT NULL,
+    ADD COLUMN device_label TEXT NULL,
+    ADD COLUMN last_used_at timestamptz NULL,
+    ADD COLUMN expires_at timestamptz NULL;
+
+UPDATE user_tokens SET
+    last_used_at = created_at,
+    expires_at = created_at + interval '7 weeks';
+
+ALTER TABLE user_tokens
+    ALTER COLUMN last_used_at SET NOT NULL,
+    ALTER COLUMN expires_at SET NOT NULL;
\ No newline at end of file
@@ -0,0 +1,23 @@
+-- Add migration script here
+ALTER TABLE user_tokens
+    ADD COLUMN family_id uuid NULL,
+    ADD COLUMN parent_id uuid NULL,
+    ADD COLUMN rotated_at timestamptz NULL;
+
+UPDATE user_tokens SET family_id = id;
+
+ALTER TABLE user_tokens ALTER COLUMN family_id SET NOT NULL;
+
+CREATE INDEX user_tokens_family_id_idx ON user_tokens (family_id);
+
+CREATE TABLE security_incidents (
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    kind TEXT NOT NULL,
+    user_id uuid NULL,
+    family_id uuid NULL,
+    ip TEXT NULL,
+    user_agent TEXT NULL,
+    created_at timestamptz NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
\ No newline at end of file
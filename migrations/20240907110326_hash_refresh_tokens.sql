@@ -0,0 +1,6 @@
+-- Add migration script here
+UPDATE user_tokens SET refresh_token = encode(sha256(convert_to(refresh_token, 'UTF8')), 'hex');
+
+ALTER TABLE user_tokens RENAME COLUMN refresh_token TO token_hash;
+
+CREATE UNIQUE INDEX user_tokens_token_hash_idx ON user_tokens (token_hash);
\ No newline at end of file
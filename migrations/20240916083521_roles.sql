@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE roles (
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    name TEXT NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL
+);
+
+CREATE TABLE user_roles (
+    user_id uuid NOT NULL,
+    role_id uuid NOT NULL,
+    PRIMARY KEY (user_id, role_id),
+    granted_at timestamptz NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+);
+
+INSERT INTO roles (id, name, created_at) VALUES (gen_random_uuid(), 'admin', now());
\ No newline at end of file
@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE api_keys (
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    user_id uuid NOT NULL,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    last_used_at timestamptz NULL,
+    revoked_at timestamptz NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
\ No newline at end of file
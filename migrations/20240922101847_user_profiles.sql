@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN display_name TEXT NULL;
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- Add migration script here
+UPDATE users SET username = normalize(username, NFKC) WHERE username IS NOT NFKC NORMALIZED;
+
+-- Usernames that only differ by case were allowed before. The oldest account keeps its name
+-- and the others are renamed to a prefix of it plus part of their id so the index below can be
+-- created. The result stays within the 12 characters a username may have.
+UPDATE users u SET username = left(u.username, 5) || '_' || left(u.id::text, 6)
+FROM (
+    SELECT id, row_number() OVER (PARTITION BY lower(username) ORDER BY created_at, id) AS rank
+    FROM users
+) d
+WHERE u.id = d.id AND d.rank > 1;
+
+CREATE UNIQUE INDEX users_username_lower_idx ON users (lower(username));
\ No newline at end of file
@@ -0,0 +1,16 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN email TEXT NULL;
+
+CREATE UNIQUE INDEX users_email_lower_idx ON users (lower(email));
+
+CREATE TABLE email_changes (
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    new_email TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX email_changes_user_id_idx ON email_changes (user_id);
+CREATE INDEX email_changes_expires_at_idx ON email_changes (expires_at);
\ No newline at end of file
@@ -0,0 +1,26 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN disabled_at timestamptz NULL;
+
+-- Kept when either user is deleted, so the trail outlives the accounts it mentions.
+CREATE TABLE admin_audit_log (
+    id UUID NOT NULL PRIMARY KEY,
+    admin_id UUID NULL,
+    action TEXT NOT NULL,
+    target_user_id UUID NULL,
+    details TEXT NULL,
+    created_at timestamptz NOT NULL
+);
+
+CREATE INDEX admin_audit_log_created_at_idx ON admin_audit_log (created_at);
+CREATE INDEX admin_audit_log_target_user_id_idx ON admin_audit_log (target_user_id);
+
+CREATE TABLE password_resets (
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX password_resets_user_id_idx ON password_resets (user_id);
+CREATE INDEX password_resets_expires_at_idx ON password_resets (expires_at);
\ No newline at end of file
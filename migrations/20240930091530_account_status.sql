@@ -0,0 +1,9 @@
+-- Add migration script here
+ALTER TABLE users
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'disabled', 'banned')),
+    ADD COLUMN status_reason TEXT NULL,
+    ADD COLUMN status_until timestamptz NULL;
+
+UPDATE users SET status = 'disabled' WHERE disabled_at IS NOT NULL;
+
+ALTER TABLE users DROP COLUMN disabled_at;
\ No newline at end of file
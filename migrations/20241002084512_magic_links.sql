@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE magic_links (
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    nonce_hash TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX magic_links_user_id_idx ON magic_links (user_id);
+CREATE INDEX magic_links_expires_at_idx ON magic_links (expires_at);
\ No newline at end of file
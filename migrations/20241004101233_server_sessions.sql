@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE server_sessions (
+    id_hash TEXT NOT NULL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX server_sessions_user_id_idx ON server_sessions (user_id);
+CREATE INDEX server_sessions_expires_at_idx ON server_sessions (expires_at);
\ No newline at end of file
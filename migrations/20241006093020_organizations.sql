@@ -0,0 +1,35 @@
+-- Add migration script here
+CREATE TABLE organizations (
+    id UUID NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+CREATE TABLE memberships (
+    organization_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'admin', 'member')),
+    created_at timestamptz NOT NULL,
+    PRIMARY KEY (organization_id, user_id),
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX memberships_user_id_idx ON memberships (user_id);
+
+-- Todos predate organizations. Each user who has some gets a personal organization, reusing
+-- their id, so no todo is lost.
+INSERT INTO organizations (id, name, created_at)
+SELECT u.id, u.username, now() FROM users u
+WHERE EXISTS (SELECT 1 FROM todos t WHERE t.owner_id = u.id);
+
+INSERT INTO memberships (organization_id, user_id, role, created_at)
+SELECT o.id, o.id, 'owner', now() FROM organizations o;
+
+ALTER TABLE todos ADD COLUMN organization_id UUID NULL;
+UPDATE todos SET organization_id = owner_id;
+ALTER TABLE todos ALTER COLUMN organization_id SET NOT NULL;
+ALTER TABLE todos
+    ADD FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE;
+
+CREATE INDEX todos_organization_id_idx ON todos (organization_id, created_at);
\ No newline at end of file
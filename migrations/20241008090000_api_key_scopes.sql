@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Keys created without scopes used to get every scope known at request time, so each new
+-- scope was silently granted to them. Pin them to the scopes that existed before organizations
+-- and todos were added.
+UPDATE api_keys SET scopes = ARRAY[
+    'account:read', 'account:write',
+    'api_keys:read', 'api_keys:write',
+    'roles:read', 'roles:write',
+    'sessions:read', 'sessions:write',
+    'users:read', 'users:write'
+]
+WHERE scopes IS NULL;
+
+ALTER TABLE api_keys ALTER COLUMN scopes SET NOT NULL;
\ No newline at end of file
@@ -0,0 +1,15 @@
+-- Add migration script here
+ALTER TABLE server_sessions
+    ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid(),
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip TEXT,
+    ADD COLUMN device_label TEXT,
+    ADD COLUMN last_used_at timestamptz;
+
+UPDATE server_sessions SET last_used_at = created_at;
+
+ALTER TABLE server_sessions
+    ALTER COLUMN id DROP DEFAULT,
+    ALTER COLUMN last_used_at SET NOT NULL;
+
+CREATE UNIQUE INDEX server_sessions_id_idx ON server_sessions (id);
\ No newline at end of file
@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE organization_invitations (
+    id UUID NOT NULL PRIMARY KEY,
+    organization_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'admin', 'member')),
+    invited_by UUID NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    UNIQUE (organization_id, user_id),
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX organization_invitations_user_id_idx ON organization_invitations (user_id);
+CREATE INDEX organization_invitations_expires_at_idx ON organization_invitations (expires_at);
\ No newline at end of file
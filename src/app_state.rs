@@ -1,8 +1,20 @@
-use crate::{configurations::JwtSettings, db::DbPool};
-use crate::utils::password_hasher::ServerPwdHasher;
+use crate::{configurations::{JwtSettings, LoginThrottleSettings, SessionSettings}, db::DbPool, features::auth::domain::PasswordPolicy, maintenance::MaintenanceMetrics};
+use crate::utils::{account_status_cache::AccountStatusCache, email_client::EmailClient, jwks::JwtKeys, password_hasher::ServerPwdHasher, session_store::SessionStore, token_denylist::TokenDenylist};
+use std::sync::Arc;
 
 pub struct AppState {
     pub pool : DbPool,
     pub jwt_settings : JwtSettings,
-    pub pwd_hasher : ServerPwdHasher
+    pub jwt_keys : JwtKeys,
+    pub session_settings : SessionSettings,
+    pub session_store : Arc<dyn SessionStore>,
+    pub token_denylist : TokenDenylist,
+    pub account_status_cache : AccountStatusCache,
+    pub login_throttle : LoginThrottleSettings,
+    pub password_policy : PasswordPolicy,
+    pub pwd_hasher : ServerPwdHasher,
+    pub email_client : EmailClient,
+    pub maintenance_metrics : Arc<MaintenanceMetrics>,
+    /// Base of links sent to users, e.g. email confirmations.
+    pub client_url : String
 }
\ No newline at end of file
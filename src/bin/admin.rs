@@ -0,0 +1,51 @@
+use std::net::IpAddr;
+
+use test_rs::{
+    configurations::get_config,
+    db::DbPool,
+    features::auth::{
+        domain::AttemptKey,
+        repository::{
+            clear_failed_logins, get_user_by_username, grant_role_to_user, revoke_role_from_user,
+        },
+    },
+    startup::get_db_pool,
+};
+
+const USAGE: &str = "usage: admin <unlock-user <username> | unlock-ip <ip> \
+                     | grant-role <username> <role> | revoke-role <username> <role>>";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config = get_config()
+        .expect("failed to parse configurations.");
+
+    let db = DbPool { pool: get_db_pool(&config.database) };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["unlock-user", username] => {
+            clear_failed_logins(&AttemptKey::username(username), &db).await?;
+            println!("unlocked user {}", username);
+        }
+        ["unlock-ip", ip] => {
+            let ip: IpAddr = ip.parse()?;
+            clear_failed_logins(&AttemptKey::Ip(ip), &db).await?;
+            println!("unlocked ip {}", ip);
+        }
+        ["grant-role", username, role] => {
+            let user = get_user_by_username(username, &db).await?;
+            grant_role_to_user(user.id, role, &db).await?;
+            println!("granted {} to {}", role, username);
+        }
+        ["revoke-role", username, role] => {
+            let user = get_user_by_username(username, &db).await?;
+            revoke_role_from_user(user.id, role, &db).await?;
+            println!("revoked {} from {}", role, username);
+        }
+        _ => anyhow::bail!(USAGE),
+    }
+
+    Ok(())
+}
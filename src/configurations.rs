@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
@@ -7,6 +9,13 @@ pub struct Settings {
     pub app: AppSettings,
     pub database: DatabaseSettings,
     pub jwt: JwtSettings,
+    pub login_throttle: LoginThrottleSettings,
+    pub rate_limit: RateLimitSettings,
+    pub maintenance: MaintenanceSettings,
+    pub password_policy: PasswordPolicySettings,
+    pub password_hashing: PasswordHashingSettings,
+    pub email_client: EmailClientSettings,
+    pub session: SessionSettings,
 }
 
 #[derive(Deserialize, Clone)]
@@ -30,8 +39,347 @@ pub struct DatabaseSettings {
 pub struct JwtSettings {
     pub issuer: String,
     pub audience: String,
-    pub access_token_secret: Secret<String>,
+    pub signing_key_id: String,
+    pub keys: Vec<JwtKeySettings>,
     pub refresh_token_secret: Secret<String>,
+    pub access_token_ttl_seconds: i64,
+    pub refresh_token_ttl_seconds: i64,
+    pub refresh_cookie: RefreshCookieSettings,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RefreshCookieSettings {
+    pub name: String,
+    pub domain: Option<String>,
+    pub path: String,
+    pub same_site: CookieSameSite,
+    pub secure: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<&CookieSameSite> for cookie::SameSite {
+    fn from(value: &CookieSameSite) -> Self {
+        match value {
+            CookieSameSite::Strict => cookie::SameSite::Strict,
+            CookieSameSite::Lax => cookie::SameSite::Lax,
+            CookieSameSite::None => cookie::SameSite::None,
+        }
+    }
+}
+
+/// How browsers authenticate: bearer JWTs with a refresh cookie, or an opaque session cookie
+/// resolved on the server. API keys work in either mode.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum SessionMode {
+    Jwt,
+    Server,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum SessionStoreKind {
+    Postgres,
+    /// Sessions are lost on restart and are not shared between instances.
+    Memory,
+}
+
+/// Only used in `Server` mode. The cookie takes its other attributes from `jwt.refresh_cookie`.
+#[derive(Deserialize, Clone)]
+pub struct SessionSettings {
+    pub mode: SessionMode,
+    pub store: SessionStoreKind,
+    pub cookie_name: String,
+    pub ttl_seconds: i64,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct JwtKeySettings {
+    pub kid: String,
+    pub algorithm: JwtAlgorithm,
+    pub private_key_path: Option<String>,
+    pub public_key_path: String,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum JwtAlgorithm {
+    RS256,
+    EdDSA,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct LoginThrottleSettings {
+    pub max_attempts: u32,
+    pub attempt_window_seconds: i64,
+    pub base_lockout_seconds: i64,
+    pub max_lockout_seconds: i64,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RateLimitSettings {
+    pub default: RateLimitPolicy,
+    pub auth: RateLimitPolicy,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MaintenanceSettings {
+    pub interval_seconds: u64,
+    pub batch_size: i64,
+    pub security_incident_retention_days: i64,
+}
+
+/// Browsers cap cookie lifetimes at 400 days, so a longer refresh token would outlive its cookie.
+const MAX_REFRESH_TOKEN_TTL_SECONDS: i64 = 400 * 24 * 60 * 60;
+
+/// Keys under this directory are committed for local development and publicly known.
+const DEV_KEY_DIR: &str = "configurations/keys/";
+const DEV_KEY_ID_PREFIX: &str = "dev-";
+
+impl JwtKeySettings {
+    fn is_dev_key(&self) -> bool {
+        let is_dev_path = |path: &str| path.trim_start_matches("./").starts_with(DEV_KEY_DIR);
+
+        self.kid.starts_with(DEV_KEY_ID_PREFIX)
+            || is_dev_path(&self.public_key_path)
+            || self.private_key_path.as_deref().is_some_and(is_dev_path)
+    }
+}
+
+impl JwtSettings {
+    pub fn validate(&self, environment: &Environment) -> Result<(), String> {
+        if *environment == Environment::Production && self.keys.iter().any(JwtKeySettings::is_dev_key) {
+            return Err("jwt.keys must not use the development keys in production.".into());
+        }
+
+        if self.access_token_ttl_seconds <= 0 {
+            return Err("jwt.access_token_ttl_seconds must be positive.".into());
+        }
+
+        if self.refresh_token_ttl_seconds <= self.access_token_ttl_seconds {
+            return Err(
+                "jwt.refresh_token_ttl_seconds must be longer than the access token ttl.".into(),
+            );
+        }
+
+        if self.refresh_token_ttl_seconds > MAX_REFRESH_TOKEN_TTL_SECONDS {
+            return Err(format!(
+                "jwt.refresh_token_ttl_seconds must not exceed {} seconds.",
+                MAX_REFRESH_TOKEN_TTL_SECONDS
+            ));
+        }
+
+        self.refresh_cookie.validate()
+    }
+}
+
+impl MaintenanceSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_seconds == 0 {
+            return Err("maintenance.interval_seconds must be positive.".into());
+        }
+
+        if self.batch_size < 1 {
+            return Err("maintenance.batch_size must be at least 1.".into());
+        }
+
+        if self.security_incident_retention_days < 1 {
+            return Err("maintenance.security_incident_retention_days must be positive.".into());
+        }
+
+        Ok(())
+    }
+}
+
+impl RateLimitSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.default
+            .validate("rate_limit.default")
+            .and_then(|_| self.auth.validate("rate_limit.auth"))
+    }
+}
+
+impl RateLimitPolicy {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        if self.capacity < 1 {
+            return Err(format!("{}.capacity must be at least 1.", name));
+        }
+
+        if !(self.refill_per_second > 0.0 && self.refill_per_second.is_finite()) {
+            return Err(format!("{}.refill_per_second must be positive.", name));
+        }
+
+        Ok(())
+    }
+}
+
+fn is_cookie_name(name: &str) -> bool {
+    let is_token_char = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+
+    !name.is_empty() && name.chars().all(is_token_char)
+}
+
+impl RefreshCookieSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_cookie_name(&self.name) {
+            return Err(format!("{} is not a valid cookie name.", self.name));
+        }
+
+        if !self.path.starts_with('/') {
+            return Err("jwt.refresh_cookie.path must start with '/'.".into());
+        }
+
+        if self.domain.as_deref().is_some_and(str::is_empty) {
+            return Err("jwt.refresh_cookie.domain must not be empty.".into());
+        }
+
+        if self.same_site == CookieSameSite::None && !self.secure {
+            return Err("SameSite=None cookies must be secure.".into());
+        }
+
+        if self.name.starts_with("__Secure-") && !self.secure {
+            return Err("__Secure- cookies must be secure.".into());
+        }
+
+        if self.name.starts_with("__Host-")
+            && (!self.secure || self.path != "/" || self.domain.is_some())
+        {
+            return Err("__Host- cookies must be secure, use path '/' and have no domain.".into());
+        }
+
+        Ok(())
+    }
+}
+
+impl SessionSettings {
+    pub fn validate(&self, refresh_cookie: &RefreshCookieSettings) -> Result<(), String> {
+        if !is_cookie_name(&self.cookie_name) {
+            return Err(format!("{} is not a valid cookie name.", self.cookie_name));
+        }
+
+        if self.cookie_name == refresh_cookie.name {
+            return Err("session.cookie_name must differ from the refresh cookie name.".into());
+        }
+
+        if self.ttl_seconds <= 0 || self.ttl_seconds > MAX_REFRESH_TOKEN_TTL_SECONDS {
+            return Err(format!(
+                "session.ttl_seconds must be between 1 and {} seconds.",
+                MAX_REFRESH_TOKEN_TTL_SECONDS
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PasswordPolicySettings {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// 0 (anything goes) to 4 (only very strong passwords).
+    pub min_strength: u8,
+    pub breached_passwords_path: String,
+}
+
+/// A Postmark-compatible HTTP API used for transactional email.
+#[derive(Deserialize, Clone)]
+pub struct EmailClientSettings {
+    pub base_url: String,
+    pub sender_email: String,
+    pub authorization_token: Secret<String>,
+    pub timeout_milliseconds: u64,
+}
+
+/// Argon2id cost for new hashes. Raising it upgrades existing hashes as users log in.
+#[derive(Deserialize, Clone)]
+pub struct PasswordHashingSettings {
+    pub memory_cost_kib: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+    /// Pepper used for new hashes. Hashes made without a pepper still verify when unset.
+    pub current_pepper_id: Option<String>,
+    /// Secrets mixed into every hash through Argon2's secret input, by id. The id is stored in
+    /// the hash as its `keyid`, so retired peppers must stay listed until their hashes have been
+    /// upgraded. A map so each pepper can be set from the environment, e.g.
+    /// `APP_PASSWORD_HASHING__PEPPERS__PROD1`.
+    #[serde(default)]
+    pub peppers: BTreeMap<String, Secret<String>>,
+}
+
+/// Committed in `local.yaml` and therefore publicly known.
+const DEV_PEPPER_SECRET: &str = "dev-pepper-change-me";
+
+/// Argon2 stores at most 8 bytes of key id.
+const MAX_PEPPER_ID_LENGTH: usize = 8;
+
+impl PasswordHashingSettings {
+    pub fn validate(&self, environment: &Environment) -> Result<(), String> {
+        for (id, secret) in &self.peppers {
+            if id.is_empty()
+                || id.len() > MAX_PEPPER_ID_LENGTH
+                || !id.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return Err(format!("{} is not a valid pepper id.", id));
+            }
+
+            if secret.expose_secret().is_empty() {
+                return Err(format!("pepper {} has an empty secret.", id));
+            }
+
+            if *environment == Environment::Production && secret.expose_secret() == DEV_PEPPER_SECRET {
+                return Err(format!("pepper {} must not use the development secret in production.", id));
+            }
+        }
+
+        match &self.current_pepper_id {
+            Some(id) if !self.peppers.contains_key(id) => {
+                Err(format!("current pepper {} is not configured.", id))
+            }
+            None if *environment == Environment::Production => {
+                Err("password_hashing.current_pepper_id must be set in production.".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Upper bound on any password accepted by the API, so hashing cannot be used for DoS.
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+impl PasswordPolicySettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_length == 0 || self.min_length > self.max_length {
+            return Err(
+                "password_policy.min_length must be positive and at most max_length.".into(),
+            );
+        }
+
+        if self.max_length > MAX_PASSWORD_LENGTH {
+            return Err(format!(
+                "password_policy.max_length must not exceed {}.",
+                MAX_PASSWORD_LENGTH
+            ));
+        }
+
+        if self.min_strength > 4 {
+            return Err("password_policy.min_strength must be between 0 and 4.".into());
+        }
+
+        Ok(())
+    }
 }
 
 impl DatabaseSettings {
@@ -56,7 +404,8 @@ impl DatabaseSettings {
     }
 }
 
-enum Environment {
+#[derive(PartialEq)]
+pub enum Environment {
     Local,
     Production 
 }
@@ -106,10 +455,175 @@ pub fn get_config() -> Result<Settings, config::ConfigError> {
         )
         .add_source(
             config::Environment::with_prefix("APP")
-            .prefix("_")
+            .prefix_separator("_")
             .separator("__")
         )
         .build()?;
 
-    config.try_deserialize()
-}
\ No newline at end of file
+    let settings: Settings = config.try_deserialize()?;
+
+    settings
+        .jwt
+        .validate(&env)
+        .and_then(|_| settings.password_policy.validate())
+        .and_then(|_| settings.password_hashing.validate(&env))
+        .and_then(|_| settings.session.validate(&settings.jwt.refresh_cookie))
+        .and_then(|_| settings.rate_limit.validate())
+        .and_then(|_| settings.maintenance.validate())
+        .map_err(config::ConfigError::Message)?;
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use secrecy::Secret;
+
+    use super::{
+        CookieSameSite, Environment, JwtAlgorithm, JwtKeySettings, JwtSettings,
+        MaintenanceSettings, PasswordHashingSettings, RateLimitPolicy, RateLimitSettings, RefreshCookieSettings, SessionMode,
+        SessionSettings, SessionStoreKind,
+    };
+
+    fn test_cookie() -> RefreshCookieSettings {
+        RefreshCookieSettings {
+            name: "rt".into(),
+            domain: None,
+            path: "/".into(),
+            same_site: CookieSameSite::Lax,
+            secure: true,
+        }
+    }
+
+    #[test]
+    fn a_valid_cookie_is_accepted() {
+        assert_ok!(test_cookie().validate());
+    }
+
+    #[test]
+    fn invalid_cookie_names_are_rejected() {
+        for name in ["", "refresh token", "rt;", "rt="] {
+            let cookie = RefreshCookieSettings {
+                name: name.into(),
+                ..test_cookie()
+            };
+
+            assert_err!(cookie.validate());
+        }
+    }
+
+    #[test]
+    fn same_site_none_requires_a_secure_cookie() {
+        let cookie = RefreshCookieSettings {
+            same_site: CookieSameSite::None,
+            secure: false,
+            ..test_cookie()
+        };
+
+        assert_err!(cookie.validate());
+    }
+
+    #[test]
+    fn host_prefixed_cookies_cannot_set_a_domain() {
+        let cookie = RefreshCookieSettings {
+            name: "__Host-rt".into(),
+            domain: Some("example.com".into()),
+            ..test_cookie()
+        };
+
+        assert_err!(cookie.validate());
+    }
+
+    #[test]
+    fn the_session_cookie_must_not_shadow_the_refresh_cookie() {
+        let session = SessionSettings {
+            mode: SessionMode::Server,
+            store: SessionStoreKind::Memory,
+            cookie_name: "rt".into(),
+            ttl_seconds: 3600,
+        };
+
+        assert_err!(session.validate(&test_cookie()));
+        assert_ok!(SessionSettings { cookie_name: "sid".into(), ..session }.validate(&test_cookie()));
+    }
+
+    #[test]
+    fn rate_limits_need_a_capacity_and_a_refill_rate() {
+        let policy = RateLimitPolicy { capacity: 10, refill_per_second: 1.0 };
+        let settings = |auth: RateLimitPolicy| RateLimitSettings { default: policy, auth };
+
+        assert_ok!(settings(policy).validate());
+        assert_err!(settings(RateLimitPolicy { capacity: 0, ..policy }).validate());
+        assert_err!(settings(RateLimitPolicy { refill_per_second: 0.0, ..policy }).validate());
+        assert_err!(settings(RateLimitPolicy { refill_per_second: f64::NAN, ..policy }).validate());
+    }
+
+    #[test]
+    fn maintenance_needs_an_interval_and_a_batch_size() {
+        let settings = MaintenanceSettings {
+            interval_seconds: 300,
+            batch_size: 1000,
+            security_incident_retention_days: 90,
+        };
+
+        assert_ok!(settings.validate());
+        assert_err!(MaintenanceSettings { interval_seconds: 0, ..settings.clone() }.validate());
+        assert_err!(MaintenanceSettings { batch_size: 0, ..settings.clone() }.validate());
+        assert_err!(MaintenanceSettings { security_incident_retention_days: 0, ..settings }.validate());
+    }
+
+    fn test_jwt(kid: &str, private_key_path: &str) -> JwtSettings {
+        JwtSettings {
+            issuer: "test".into(),
+            audience: "test".into(),
+            signing_key_id: kid.into(),
+            keys: vec![JwtKeySettings {
+                kid: kid.into(),
+                algorithm: JwtAlgorithm::EdDSA,
+                private_key_path: Some(private_key_path.into()),
+                public_key_path: private_key_path.replace(".pem", ".pub.pem"),
+            }],
+            refresh_token_secret: Secret::new("test".into()),
+            access_token_ttl_seconds: 60,
+            refresh_token_ttl_seconds: 3600,
+            refresh_cookie: test_cookie(),
+        }
+    }
+
+    #[test]
+    fn development_signing_keys_are_rejected_in_production() {
+        let dev = test_jwt("dev-signing", "configurations/keys/dev-signing.pem");
+        let renamed = test_jwt("signing-1", "./configurations/keys/dev-signing.pem");
+        let mounted = test_jwt("signing-1", "/run/secrets/jwt/signing-1.pem");
+
+        assert_ok!(dev.validate(&Environment::Local));
+        assert_err!(dev.validate(&Environment::Production));
+        assert_err!(renamed.validate(&Environment::Production));
+        assert_ok!(mounted.validate(&Environment::Production));
+    }
+
+    fn test_hashing(current_pepper_id: Option<&str>, secret: &str) -> PasswordHashingSettings {
+        PasswordHashingSettings {
+            memory_cost_kib: 1024,
+            time_cost: 1,
+            parallelism: 1,
+            current_pepper_id: current_pepper_id.map(Into::into),
+            peppers: [("p1".to_string(), Secret::new(secret.to_string()))].into(),
+        }
+    }
+
+    #[test]
+    fn production_needs_a_pepper_that_is_not_the_development_one() {
+        let dev = test_hashing(Some("p1"), "dev-pepper-change-me");
+        let unpeppered = test_hashing(None, "a-real-secret");
+        let configured = test_hashing(Some("p1"), "a-real-secret");
+
+        assert_ok!(dev.validate(&Environment::Local));
+        assert_err!(dev.validate(&Environment::Production));
+        assert_err!(unpeppered.validate(&Environment::Production));
+        assert_ok!(configured.validate(&Environment::Production));
+        assert_err!(test_hashing(Some("p2"), "a-real-secret").validate(&Environment::Local));
+    }
+}
@@ -13,6 +13,8 @@ pub trait DbContext {
     async fn execute_query<'a>(&self, query : Query<'a, Postgres, PgArguments>) -> Result<(), AppError>;
     async fn fetch_optional<T>(&self, query : QueryAs<'static, Postgres, T, PgArguments>) -> Result<Option<T>, AppError>
         where T : for<'a> FromRow<'a, PgRow> + Send + Sync + Unpin + 'static;
+    async fn fetch_all<T>(&self, query : QueryAs<'static, Postgres, T, PgArguments>) -> Result<Vec<T>, AppError>
+        where T : for<'a> FromRow<'a, PgRow> + Send + Sync + Unpin + 'static;
 }
 
 #[cfg_attr(test, automock)]
@@ -55,6 +57,15 @@ impl DbContext for DbPool {
 
         Ok(result)
     }
+
+    async fn fetch_all<T>(&self, query: QueryAs<'static, Postgres, T, PgArguments>) -> Result<Vec<T>, AppError>
+    where
+        T: for<'a> FromRow<'a, PgRow> + Send + Sync + Unpin + 'static
+    {
+        let result = query.fetch_all(&self.pool).await?;
+
+        Ok(result)
+    }
 }
 
 #[async_trait]
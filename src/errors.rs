@@ -1,4 +1,11 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use std::collections::BTreeMap;
+
+use axum::{
+    http::{header::{RETRY_AFTER, WWW_AUTHENTICATE}, StatusCode},
+    response::{AppendHeaders, IntoResponse},
+    Json,
+};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use validator::ValidationErrors;
 
@@ -9,13 +16,40 @@ pub enum AppError {
     #[error("{0}")]
     UnauthorizedError(String),
     #[error("{0}")]
+    ForbiddenError(String),
+    #[error("Account is {status}.")]
+    AccountInactiveError {
+        status: String,
+        reason: Option<String>,
+        until: Option<DateTime<Utc>>
+    },
+    #[error("Missing required scopes: {}", .0.join(" "))]
+    InsufficientScopeError(Vec<String>),
+    #[error("{0}")]
+    ConflictError(String),
+    #[error("{0}")]
     UnexpectedError(String),
     #[error("{0}")]
+    LockedError(String, i64),
+    #[error("{0}")]
+    TooManyRequestsError(String, i64),
+    #[error("{0}")]
     ValidationError(#[from] ValidationErrors),
     #[error("{0}")]
     DbError(#[from] sqlx::Error)
 }
 
+impl AppError {
+    pub fn is_unique_violation(&self) -> bool {
+        match self {
+            AppError::DbError(e) => e
+                .as_database_error()
+                .is_some_and(|e| e.is_unique_violation()),
+            _ => false
+        }
+    }
+}
+
 impl std::fmt::Debug for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self)
@@ -30,6 +64,27 @@ pub struct AppErrorDetails {
     details : String
 }
 
+/// Lists the failed validation codes per field, e.g. `{"password": ["password_too_short"]}`.
+#[derive(Serialize)]
+pub struct ValidationErrorDetails {
+    error_code : u16,
+    error_type : String,
+    title : String,
+    fields : BTreeMap<String, Vec<String>>
+}
+
+/// Tells a signed out user why their account cannot be used and until when.
+#[derive(Serialize)]
+pub struct AccountInactiveErrorDetails {
+    error_code : u16,
+    error_type : String,
+    title : String,
+    details : String,
+    status : String,
+    reason : Option<String>,
+    until : Option<DateTime<Utc>>
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         match self {
@@ -43,7 +98,85 @@ impl IntoResponse for AppError {
                     details : e.to_string()
                 })
             ).into_response(),
-            AppError::ValidationError(_) => (StatusCode::BAD_REQUEST).into_response(),
+            AppError::ForbiddenError(e) => (
+                StatusCode::FORBIDDEN,
+                Json(AppErrorDetails {
+                    error_code : StatusCode::FORBIDDEN.as_u16(),
+                    error_type: "ForbiddenError".into(),
+                    title : "Forbidden".into(),
+                    details : e.to_string()
+                })
+            ).into_response(),
+            AppError::ConflictError(e) => (
+                StatusCode::CONFLICT,
+                Json(AppErrorDetails {
+                    error_code : StatusCode::CONFLICT.as_u16(),
+                    error_type: "ConflictError".into(),
+                    title : "Conflict".into(),
+                    details : e.to_string()
+                })
+            ).into_response(),
+            AppError::AccountInactiveError { ref status, ref reason, until } => (
+                StatusCode::FORBIDDEN,
+                Json(AccountInactiveErrorDetails {
+                    error_code : StatusCode::FORBIDDEN.as_u16(),
+                    error_type: "AccountInactiveError".into(),
+                    title : "Forbidden".into(),
+                    details : self.to_string(),
+                    status : status.to_string(),
+                    reason : reason.clone(),
+                    until
+                })
+            ).into_response(),
+            AppError::InsufficientScopeError(ref scopes) => (
+                StatusCode::FORBIDDEN,
+                AppendHeaders([(
+                    WWW_AUTHENTICATE,
+                    format!(r#"Bearer error="insufficient_scope", scope="{}""#, scopes.join(" ")),
+                )]),
+                Json(AppErrorDetails {
+                    error_code : StatusCode::FORBIDDEN.as_u16(),
+                    error_type: "InsufficientScopeError".into(),
+                    title : "Forbidden".into(),
+                    details : self.to_string()
+                })
+            ).into_response(),
+            AppError::LockedError(e, retry_after) => (
+                StatusCode::LOCKED,
+                AppendHeaders([(RETRY_AFTER, retry_after.to_string())]),
+                Json(AppErrorDetails {
+                    error_code : StatusCode::LOCKED.as_u16(),
+                    error_type: "LockedError".into(),
+                    title : "Locked".into(),
+                    details : e.to_string()
+                })
+            ).into_response(),
+            AppError::TooManyRequestsError(e, retry_after) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                AppendHeaders([(RETRY_AFTER, retry_after.to_string())]),
+                Json(AppErrorDetails {
+                    error_code : StatusCode::TOO_MANY_REQUESTS.as_u16(),
+                    error_type: "TooManyRequestsError".into(),
+                    title : "Too Many Requests".into(),
+                    details : e.to_string()
+                })
+            ).into_response(),
+            AppError::ValidationError(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(ValidationErrorDetails {
+                    error_code : StatusCode::BAD_REQUEST.as_u16(),
+                    error_type: "ValidationError".into(),
+                    title : "Bad Request".into(),
+                    fields : e
+                        .field_errors()
+                        .into_iter()
+                        .map(|(field, errors)| (
+                            field.to_string(),
+                            errors.iter().map(|e| e.code.to_string()).collect()
+                        ))
+                        .collect()
+                })
+            ).into_response(),
             AppError::UnexpectedError(e) => (
                 StatusCode::BAD_REQUEST,
                 Json(AppErrorDetails {
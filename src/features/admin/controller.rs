@@ -0,0 +1,450 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    configurations::SessionMode,
+    db::{DbContext, TxContext},
+    errors::AppError,
+    features::{
+        auth::{
+            controller::SessionResponse,
+            domain::{
+                generate_password_reset_token, AccountState, AccountStatus,
+                PASSWORD_RESET_TTL_MINUTES,
+            },
+            repository::{
+                add_password_reset, delete_all_refresh_token_by_user_id_tx, get_account_state,
+                get_roles_by_user_id,
+                get_sessions_by_user_id, grant_role_to_user_tx, revoke_role_from_user_tx,
+            },
+        },
+        users::repository::get_user_profile_by_id,
+    },
+    utils::{
+        jwt::generate_impersonation_jwt,
+        roles::{Admin, RequireRole, Role},
+        scopes::{RolesRead, RolesWrite, UsersRead, UsersWrite, ALL_SCOPES},
+    },
+};
+
+use super::{
+    domain::{StatusChange, UserSearch},
+    models::{StatusChangeData, UserSearchQuery},
+    repository::{add_audit_entry, add_audit_entry_tx, count_users, search_users, set_user_status},
+};
+
+pub fn admin_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/users", get(get_users))
+        .route(
+            "/users/:user_id/sessions",
+            get(get_user_sessions).delete(revoke_user_sessions),
+        )
+        .route("/users/:user_id/disable", post(disable_user))
+        .route("/users/:user_id/enable", post(enable_user))
+        .route("/users/:user_id/status", put(change_user_status))
+        .route("/users/:user_id/password-reset", post(request_password_reset))
+        .route("/users/:user_id/impersonate", post(impersonate_user))
+        .route("/users/:user_id/roles", get(get_user_roles))
+        .route(
+            "/users/:user_id/roles/:role",
+            put(grant_role).delete(revoke_role),
+        )
+        .route("/maintenance", get(get_maintenance_metrics))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserRolesResponse {
+    pub user_id: Uuid,
+    pub roles: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminUserResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub status: AccountStatus,
+    pub status_reason: Option<String>,
+    pub status_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImpersonationResponse {
+    pub user_id: Uuid,
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Rows each maintenance job purged, and how often it failed, since the server started.
+#[derive(Serialize, Deserialize)]
+pub struct MaintenanceResponse {
+    pub rows_purged: BTreeMap<String, u64>,
+    pub failures: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserPageResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+#[tracing::instrument(name = "Searching Users", skip(app_state, admin, query))]
+async fn get_users(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<UserSearchQuery>,
+    admin: RequireRole<Admin, UsersRead>,
+) -> Result<Response, AppError> {
+    let search: UserSearch = query.try_into()?;
+
+    let users = search_users(&search, &app_state.pool)
+        .await?
+        .into_iter()
+        .map(|u| AdminUserResponse {
+            id: u.id,
+            username: u.username,
+            email: u.email,
+            display_name: u.display_name,
+            created_at: u.created_at,
+            status: AccountStatus::parse(&u.status),
+            status_reason: u.status_reason,
+            status_until: u.status_until,
+        })
+        .collect::<Vec<_>>();
+    let total = count_users(&search, &app_state.pool).await?;
+
+    add_audit_entry(admin.user.id, "users.searched", None, search.q.as_deref(), &app_state.pool)
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(UserPageResponse {
+            users,
+            page: search.page,
+            per_page: search.per_page,
+            total,
+        }),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(name = "Listing sessions of User", skip(app_state, admin))]
+async fn get_user_sessions(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersRead>,
+) -> Result<Response, AppError> {
+    let sessions = match app_state.session_settings.mode {
+        SessionMode::Jwt => get_sessions_by_user_id(user_id, &app_state.pool).await?,
+        SessionMode::Server => app_state.session_store.list_user_sessions(user_id).await?,
+    };
+
+    let sessions = sessions
+        .into_iter()
+        .map(|s| SessionResponse {
+            current: false,
+            id: s.id,
+            user_agent: s.user_agent,
+            ip: s.ip,
+            device_label: s.device_label,
+            created_at: s.created_at,
+            last_used_at: s.last_used_at,
+            expires_at: s.expires_at,
+        })
+        .collect::<Vec<_>>();
+
+    add_audit_entry(admin.user.id, "user.sessions_viewed", Some(user_id), None, &app_state.pool)
+        .await?;
+
+    Ok((StatusCode::OK, Json(sessions)).into_response())
+}
+
+/// Signs the user out everywhere. Access tokens already issued stay valid until they expire.
+#[tracing::instrument(name = "Revoking sessions of User", skip(app_state, admin))]
+async fn revoke_user_sessions(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersWrite>,
+) -> Result<Response, AppError> {
+    let mut tx = app_state.pool.get_transaction().await?;
+    delete_all_refresh_token_by_user_id_tx(user_id, &mut tx).await?;
+    add_audit_entry_tx(admin.user.id, "user.sessions_revoked", Some(user_id), None, &mut tx)
+        .await?;
+    tx.execute_transaction().await?;
+
+    app_state.session_store.delete_user_sessions(user_id, None).await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Disabling User", skip(app_state, admin))]
+async fn disable_user(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersWrite>,
+) -> Result<Response, AppError> {
+    let state = AccountState {
+        status: AccountStatus::Disabled,
+        reason: None,
+        until: None,
+    };
+
+    apply_user_status(&app_state, admin.user.id, user_id, &state, "user.disabled").await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Enabling User", skip(app_state, admin))]
+async fn enable_user(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersWrite>,
+) -> Result<Response, AppError> {
+    let state = AccountState::active();
+
+    apply_user_status(&app_state, admin.user.id, user_id, &state, "user.enabled").await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Changing User status", skip(app_state, admin, data))]
+async fn change_user_status(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersWrite>,
+    Json(data): Json<StatusChangeData>,
+) -> Result<Response, AppError> {
+    let change: StatusChange = data.try_into()?;
+    let state: AccountState = change.into();
+
+    apply_user_status(&app_state, admin.user.id, user_id, &state, "user.status_changed").await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+/// Restricting an account also ends its sessions, and the cached status is dropped so access
+/// tokens already issued stop working on their next request.
+async fn apply_user_status(
+    app_state: &AppState,
+    admin_id: Uuid,
+    user_id: Uuid,
+    state: &AccountState,
+    action: &str,
+) -> Result<(), AppError> {
+    // Otherwise the last administrator could lock everyone out.
+    if user_id == admin_id {
+        return Err(AppError::ForbiddenError(
+            "Administrators cannot change the status of their own account.".into(),
+        ));
+    }
+
+    let details = match &state.reason {
+        Some(reason) => format!("{}: {}", state.status.as_str(), reason),
+        None => state.status.as_str().to_string(),
+    };
+
+    let mut tx = app_state.pool.get_transaction().await?;
+    set_user_status(user_id, state, &mut tx).await?;
+    if state.status != AccountStatus::Active {
+        delete_all_refresh_token_by_user_id_tx(user_id, &mut tx).await?;
+    }
+    add_audit_entry_tx(admin_id, action, Some(user_id), Some(&details), &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    // Only after the commit, or a request in between could cache the old status again.
+    app_state.account_status_cache.invalidate(user_id)?;
+    if state.status != AccountStatus::Active {
+        app_state.session_store.delete_user_sessions(user_id, None).await?;
+    }
+
+    Ok(())
+}
+
+/// Emails the user a link to choose a new password. The current password keeps working until
+/// the reset is completed.
+#[tracing::instrument(name = "Requesting password reset", skip(app_state, admin))]
+async fn request_password_reset(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersWrite>,
+) -> Result<Response, AppError> {
+    let user = get_user_profile_by_id(user_id, &app_state.pool).await?;
+
+    let email = match user.email {
+        Some(data) => data,
+        None => {
+            return Err(AppError::ConflictError(
+                "User has no email address to send a reset link to.".into(),
+            ))
+        }
+    };
+
+    let token = generate_password_reset_token();
+    let expires_at = Utc::now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES);
+
+    let mut tx = app_state.pool.get_transaction().await?;
+    add_password_reset(&token, user_id, expires_at, &mut tx).await?;
+    add_audit_entry_tx(
+        admin.user.id,
+        "user.password_reset_requested",
+        Some(user_id),
+        None,
+        &mut tx,
+    )
+    .await?;
+    tx.execute_transaction().await?;
+
+    let link = format!(
+        "{}/reset-password?token={}",
+        app_state.client_url.trim_end_matches('/'),
+        token
+    );
+    app_state
+        .email_client
+        .send_email(
+            &email,
+            "Reset your password",
+            &format!("Open <a href=\"{}\">this link</a> to choose a new password.", link),
+            &format!("Open {} to choose a new password.", link),
+        )
+        .await?;
+
+    Ok((StatusCode::ACCEPTED).into_response())
+}
+
+/// Issues a short-lived access token for the user with the administrator in its `act` claim.
+/// Other administrators cannot be impersonated, so this never grants more than `admin` has.
+/// Only available in `SessionMode::Jwt`.
+#[tracing::instrument(name = "Impersonating User", skip(app_state, admin))]
+async fn impersonate_user(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    admin: RequireRole<Admin, UsersWrite>,
+) -> Result<Response, AppError> {
+    // Server sessions are only read from the cookie, so the issued token would be ignored.
+    if app_state.session_settings.mode == SessionMode::Server {
+        return Err(AppError::ForbiddenError(
+            "Impersonation is not available with server-side sessions.".into(),
+        ));
+    }
+
+    if user_id == admin.user.id {
+        return Err(AppError::ForbiddenError(
+            "Administrators cannot impersonate themselves.".into(),
+        ));
+    }
+
+    match get_account_state(user_id, &app_state.pool).await? {
+        Some(state) => state.ensure_active()?,
+        None => return Err(AppError::NotFoundError("User was not found".into())),
+    }
+
+    let roles = get_roles_by_user_id(user_id, &app_state.pool).await?;
+    if roles.iter().any(|r| r == Admin::NAME) {
+        return Err(AppError::ForbiddenError(
+            "Administrators cannot be impersonated.".into(),
+        ));
+    }
+
+    let (access_token, expires_at) = generate_impersonation_jwt(
+        user_id,
+        admin.user.id,
+        &roles,
+        ALL_SCOPES,
+        &app_state.jwt_settings,
+        &app_state.jwt_keys,
+    )
+    .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+    add_audit_entry(
+        admin.user.id,
+        "user.impersonation_started",
+        Some(user_id),
+        None,
+        &app_state.pool,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ImpersonationResponse {
+            user_id,
+            access_token,
+            expires_at,
+        }),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(name = "Listing User roles", skip(app_state, _admin))]
+async fn get_user_roles(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    _admin: RequireRole<Admin, RolesRead>,
+) -> Result<Response, AppError> {
+    let roles = get_roles_by_user_id(user_id, &app_state.pool).await?;
+
+    Ok((StatusCode::OK, Json(UserRolesResponse { user_id, roles })).into_response())
+}
+
+#[tracing::instrument(name = "Granting role", skip(app_state, admin))]
+async fn grant_role(
+    State(app_state): State<Arc<AppState>>,
+    Path((user_id, role)): Path<(Uuid, String)>,
+    admin: RequireRole<Admin, RolesWrite>,
+) -> Result<Response, AppError> {
+    let mut tx = app_state.pool.get_transaction().await?;
+    grant_role_to_user_tx(user_id, &role, &mut tx).await?;
+    add_audit_entry_tx(admin.user.id, "role.granted", Some(user_id), Some(&role), &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    tracing::info!(admin_id = %admin.user.id, %user_id, role, "Role granted.");
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Revoking role", skip(app_state, admin))]
+async fn revoke_role(
+    State(app_state): State<Arc<AppState>>,
+    Path((user_id, role)): Path<(Uuid, String)>,
+    admin: RequireRole<Admin, RolesWrite>,
+) -> Result<Response, AppError> {
+    let mut tx = app_state.pool.get_transaction().await?;
+    revoke_role_from_user_tx(user_id, &role, &mut tx).await?;
+    add_audit_entry_tx(admin.user.id, "role.revoked", Some(user_id), Some(&role), &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    tracing::info!(admin_id = %admin.user.id, %user_id, role, "Role revoked.");
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Fetching maintenance metrics", skip(app_state, _admin))]
+async fn get_maintenance_metrics(
+    State(app_state): State<Arc<AppState>>,
+    _admin: RequireRole<Admin, UsersRead>,
+) -> Result<Response, AppError> {
+    Ok((
+        StatusCode::OK,
+        Json(MaintenanceResponse {
+            rows_purged: app_state.maintenance_metrics.snapshot(),
+            failures: app_state.maintenance_metrics.failure_snapshot(),
+        }),
+    )
+        .into_response())
+}
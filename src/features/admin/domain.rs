@@ -0,0 +1,173 @@
+use chrono::Utc;
+use validator::{Validate, ValidationError};
+
+use crate::{
+    errors::AppError,
+    features::auth::domain::{AccountState, AccountStatus},
+};
+
+use super::models::{StatusChangeData, UserSearchQuery};
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+const MAX_SEARCH_LENGTH: u64 = 64;
+const MAX_REASON_LENGTH: u64 = 256;
+
+#[derive(Validate)]
+pub struct UserSearch {
+    #[validate(length(max = "MAX_SEARCH_LENGTH", code = "invalid_query"))]
+    pub q : Option<String>,
+    #[validate(range(min = 1, code = "invalid_page"))]
+    pub page : i64,
+    #[validate(range(min = 1, max = "MAX_PAGE_SIZE", code = "invalid_page_size"))]
+    pub per_page : i64
+}
+
+impl UserSearch {
+    /// A case-insensitive `LIKE` pattern matching usernames or emails containing `q`.
+    pub fn pattern(&self) -> Option<String> {
+        self.q.as_ref().map(|q| format!("%{}%", escape_like(q)))
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+fn escape_like (v : &str) -> String {
+    v.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+impl TryFrom<UserSearchQuery> for UserSearch {
+    type Error = AppError;
+
+    fn try_from(value: UserSearchQuery) -> Result<Self, Self::Error> {
+        let UserSearchQuery { q, page, per_page } = value;
+
+        let search = UserSearch {
+            q : q.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+            page : page.unwrap_or(1),
+            per_page : per_page.unwrap_or(DEFAULT_PAGE_SIZE)
+        };
+
+        search.validate()?;
+
+        Ok(search)
+    }
+}
+
+/// A status set by an administrator. Only restrictions carry a reason or an end date, and an
+/// end date must lie in the future.
+#[derive(Validate)]
+#[validate(schema(function = "validate_status_change", skip_on_field_errors = false))]
+pub struct StatusChange {
+    pub status : AccountStatus,
+    #[validate(length(min = 1, max = "MAX_REASON_LENGTH", code = "invalid_reason"))]
+    pub reason : Option<String>,
+    pub until : Option<chrono::DateTime<Utc>>
+}
+
+fn validate_status_change (change : &StatusChange) -> Result<(), ValidationError> {
+    if change.status == AccountStatus::Active && (change.reason.is_some() || change.until.is_some()) {
+        return Err(ValidationError::new("invalid_status"));
+    }
+
+    match change.until {
+        Some(until) if until <= Utc::now() => Err(ValidationError::new("invalid_until")),
+        _ => Ok(())
+    }
+}
+
+impl TryFrom<StatusChangeData> for StatusChange {
+    type Error = AppError;
+
+    fn try_from(value: StatusChangeData) -> Result<Self, Self::Error> {
+        let StatusChangeData { status, reason, until } = value;
+
+        let change = StatusChange {
+            status,
+            reason : reason.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+            until
+        };
+
+        change.validate()?;
+
+        Ok(change)
+    }
+}
+
+impl From<StatusChange> for AccountState {
+    fn from(value: StatusChange) -> Self {
+        AccountState { status : value.status, reason : value.reason, until : value.until }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_err;
+
+    use chrono::{Duration, Utc};
+
+    use crate::features::{
+        admin::models::{StatusChangeData, UserSearchQuery},
+        auth::domain::AccountStatus,
+    };
+
+    use super::{StatusChange, UserSearch};
+
+    fn query(q : Option<&str>, page : Option<i64>, per_page : Option<i64>) -> UserSearchQuery {
+        UserSearchQuery { q : q.map(String::from), page, per_page }
+    }
+
+    #[test]
+    fn defaults_to_the_first_page() {
+        let search = UserSearch::try_from(query(Some("  "), None, None)).unwrap();
+
+        assert_eq!(None, search.q);
+        assert_eq!(0, search.offset());
+        assert_eq!(20, search.per_page);
+    }
+
+    #[test]
+    fn like_wildcards_in_the_query_are_escaped() {
+        let search = UserSearch::try_from(query(Some("a_b%"), Some(3), Some(10))).unwrap();
+
+        assert_eq!(Some("%a\\_b\\%%".to_string()), search.pattern());
+        assert_eq!(20, search.offset());
+    }
+
+    #[test]
+    fn out_of_range_pages_are_rejected() {
+        assert_err!(UserSearch::try_from(query(None, Some(0), None)).map(|_| ()));
+        assert_err!(UserSearch::try_from(query(None, None, Some(101))).map(|_| ()));
+    }
+
+    #[test]
+    fn a_restriction_may_carry_a_reason_and_a_future_end() {
+        let change = StatusChange::try_from(StatusChangeData {
+            status : AccountStatus::Banned,
+            reason : Some(" spam ".into()),
+            until : Some(Utc::now() + Duration::days(7))
+        }).unwrap();
+
+        assert_eq!(Some("spam".to_string()), change.reason);
+    }
+
+    #[test]
+    fn an_inconsistent_status_change_is_rejected() {
+        let cases = [
+            (AccountStatus::Active, Some("spam"), None),
+            (AccountStatus::Active, None, Some(Duration::days(1))),
+            (AccountStatus::Banned, None, Some(Duration::days(-1))),
+            (AccountStatus::Disabled, Some(&*"x".repeat(257)), None),
+        ];
+
+        for (status, reason, until) in cases {
+            assert_err!(StatusChange::try_from(StatusChangeData {
+                status,
+                reason : reason.map(String::from),
+                until : until.map(|d| Utc::now() + d)
+            }).map(|_| ()));
+        }
+    }
+}
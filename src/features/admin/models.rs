@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::features::auth::domain::AccountStatus;
+
+#[derive(Deserialize)]
+pub struct UserSearchQuery {
+    pub q : Option<String>,
+    pub page : Option<i64>,
+    pub per_page : Option<i64>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct AdminUserData {
+    pub id : Uuid,
+    pub username : String,
+    pub email : Option<String>,
+    pub display_name : Option<String>,
+    pub created_at : DateTime<Utc>,
+    pub status : String,
+    pub status_reason : Option<String>,
+    pub status_until : Option<DateTime<Utc>>
+}
+
+#[derive(Deserialize)]
+pub struct StatusChangeData {
+    pub status : AccountStatus,
+    pub reason : Option<String>,
+    pub until : Option<DateTime<Utc>>
+}
\ No newline at end of file
@@ -0,0 +1,129 @@
+use uuid::{NoContext, Timestamp, Uuid};
+
+use crate::db::{DbContext, TxContext};
+use crate::errors::AppError;
+use crate::features::auth::domain::AccountState;
+
+use super::domain::UserSearch;
+use super::models::AdminUserData;
+
+#[tracing::instrument(name = "Searching Users", skip(search, db))]
+pub async fn search_users(
+    search: &UserSearch,
+    db: &impl DbContext,
+) -> Result<Vec<AdminUserData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT
+                id, username, email, display_name, created_at,
+                status, status_reason, status_until
+            FROM users
+            WHERE $1::text IS NULL OR username ILIKE $1 OR email ILIKE $1
+            ORDER BY created_at, id
+            LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(search.pattern())
+    .bind(search.per_page)
+    .bind(search.offset());
+
+    let result = db.fetch_all::<AdminUserData>(query).await?;
+
+    Ok(result)
+}
+
+#[tracing::instrument(name = "Counting Users", skip(search, db))]
+pub async fn count_users(search: &UserSearch, db: &impl DbContext) -> Result<i64, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT count(*) FROM users
+            WHERE $1::text IS NULL OR username ILIKE $1 OR email ILIKE $1
+        "#,
+    )
+    .bind(search.pattern());
+
+    let result = db.fetch_optional::<(i64,)>(query).await?;
+
+    Ok(result.map(|(total,)| total).unwrap_or(0))
+}
+
+#[tracing::instrument(name = "Setting User status", skip(user_id, state, tx))]
+pub async fn set_user_status(
+    user_id: Uuid,
+    state: &AccountState,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query(
+        r#"
+            UPDATE users
+            SET status = $2, status_reason = $3, status_until = $4
+            WHERE id = $1
+            RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(state.status.as_str())
+    .bind(state.reason.clone())
+    .bind(state.until);
+
+    match tx.fetch_optional(query).await? {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFoundError("User was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Adding audit entry", skip(admin_id, target_user_id, details, db))]
+pub async fn add_audit_entry(
+    admin_id: Uuid,
+    action: &str,
+    target_user_id: Option<Uuid>,
+    details: Option<&str>,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let id = Uuid::new_v7(Timestamp::now(NoContext));
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO admin_audit_log (id, admin_id, action, target_user_id, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, now())
+        "#,
+        id,
+        admin_id,
+        action,
+        target_user_id,
+        details
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Records the entry in the same transaction as the action it describes, so neither is kept
+/// without the other.
+#[tracing::instrument(name = "Adding audit entry", skip(admin_id, target_user_id, details, tx))]
+pub async fn add_audit_entry_tx(
+    admin_id: Uuid,
+    action: &str,
+    target_user_id: Option<Uuid>,
+    details: Option<&str>,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let id = Uuid::new_v7(Timestamp::now(NoContext));
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO admin_audit_log (id, admin_id, action, target_user_id, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, now())
+        "#,
+        id,
+        admin_id,
+        action,
+        target_user_id,
+        details
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::AppError,
+    utils::scopes::{ApiKeysRead, ApiKeysWrite, RequireScopes},
+};
+
+use super::{
+    domain::{generate_api_key, NewApiKey},
+    models::CreateApiKeyFormData,
+    repository::{create_api_key, get_api_keys_by_user_id, revoke_api_key},
+};
+
+pub fn api_key_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_api_keys).post(add_api_key))
+        .route("/:id", delete(remove_api_key))
+}
+
+/// Returned once on creation. `key` is not stored and cannot be shown again.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub key: String,
+    pub prefix: String,
+    pub scopes: Option<Vec<String>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Creating API key", skip(app_state, user, input))]
+async fn add_api_key(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<ApiKeysWrite>,
+    Json(input): Json<CreateApiKeyFormData>,
+) -> Result<Response, AppError> {
+    // A leaked key must not be able to mint new keys that outlive its own revocation.
+    if user.api_key_id.is_some() {
+        return Err(AppError::ForbiddenError(
+            "API keys cannot be used to create API keys.".into(),
+        ));
+    }
+
+    user.reject_impersonation()?;
+
+    let input: NewApiKey = input.try_into()?;
+    let (key, prefix) = generate_api_key();
+
+    let (id, expires_at) = create_api_key(&key, &prefix, user.id, &input, &app_state.pool).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            id,
+            name: input.name,
+            key,
+            prefix,
+            scopes: input.scopes,
+            expires_at,
+        }),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(name = "Listing API keys", skip(app_state, user))]
+async fn get_api_keys(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<ApiKeysRead>,
+) -> Result<Response, AppError> {
+    let api_keys = get_api_keys_by_user_id(user.id, &app_state.pool)
+        .await?
+        .into_iter()
+        .map(|k| ApiKeyResponse {
+            id: k.id,
+            name: k.name,
+            prefix: k.prefix,
+            scopes: k.scopes,
+            created_at: k.created_at,
+            expires_at: k.expires_at,
+            last_used_at: k.last_used_at,
+            revoked_at: k.revoked_at,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(api_keys)).into_response())
+}
+
+#[tracing::instrument(name = "Revoking API key", skip(app_state, user))]
+async fn remove_api_key(
+    State(app_state): State<Arc<AppState>>,
+    Path(api_key_id): Path<Uuid>,
+    RequireScopes { user, .. }: RequireScopes<ApiKeysWrite>,
+) -> Result<Response, AppError> {
+    user.reject_impersonation()?;
+
+    revoke_api_key(api_key_id, user.id, &app_state.pool).await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
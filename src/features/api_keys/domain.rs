@@ -0,0 +1,110 @@
+use validator::{Validate, ValidationError};
+
+use crate::{
+    errors::AppError,
+    utils::{randomizer::generate_random_string, scopes::{is_known_scope, ALL_SCOPES}},
+};
+
+use super::models::CreateApiKeyFormData;
+
+/// Every key starts with this so leaked keys are easy to spot in logs and secret scanners.
+pub const API_KEY_PREFIX: &str = "trs_";
+
+const API_KEY_LENGTH: usize = 40;
+const DISPLAYED_PREFIX_LENGTH: usize = 12;
+const MAX_SCOPES: usize = 32;
+
+#[derive(Validate)]
+pub struct NewApiKey {
+    #[validate(length(min = 1, max = 64))]
+    pub name : String,
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days : i64,
+    #[validate(custom(function = "parse_scopes"))]
+    pub scopes : Option<Vec<String>>
+}
+
+fn parse_scopes(v : &[String]) -> Result<(), ValidationError> {
+    if v.len() > MAX_SCOPES || !v.iter().all(|s| is_known_scope(s)) {
+        return Err(ValidationError::new("invalid_scopes").with_message(std::borrow::Cow::Borrowed("Invalid Scopes")))
+    }
+
+    Ok(())
+}
+
+impl TryFrom<CreateApiKeyFormData> for NewApiKey {
+    type Error = AppError;
+
+    fn try_from(value: CreateApiKeyFormData) -> Result<Self, Self::Error> {
+        let CreateApiKeyFormData { name, expires_in_days, scopes } = value;
+
+        // Unrestricted keys get the scopes known today. Scopes added later must be granted
+        // explicitly with a new key.
+        let scopes = scopes.unwrap_or_else(|| ALL_SCOPES.iter().map(|s| s.to_string()).collect());
+
+        let new_api_key = NewApiKey { name : name.trim().to_string(), expires_in_days, scopes : Some(scopes) };
+
+        new_api_key.validate()?;
+
+        Ok(new_api_key)
+    }
+}
+
+/// A freshly generated key and the part of it that is safe to show again later.
+pub fn generate_api_key() -> (String, String) {
+    let key = format!("{}{}", API_KEY_PREFIX, generate_random_string(API_KEY_LENGTH));
+    let prefix = key[..DISPLAYED_PREFIX_LENGTH].to_string();
+
+    (key, prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::{features::api_keys::models::CreateApiKeyFormData, utils::scopes::ALL_SCOPES};
+
+    use super::{generate_api_key, NewApiKey, API_KEY_PREFIX};
+
+    fn form(expires_in_days : i64, scopes : Option<Vec<&str>>) -> CreateApiKeyFormData {
+        CreateApiKeyFormData {
+            name : "ci".into(),
+            expires_in_days,
+            scopes : scopes.map(|s| s.into_iter().map(String::from).collect())
+        }
+    }
+
+    #[test]
+    fn a_valid_api_key_is_accepted() {
+        assert_ok!(NewApiKey::try_from(form(30, Some(vec!["todos:read"]))).map(|_| ()));
+    }
+
+    #[test]
+    fn an_unrestricted_key_stores_every_current_scope() {
+        let api_key = NewApiKey::try_from(form(30, None)).unwrap();
+
+        assert_eq!(Some(ALL_SCOPES.len()), api_key.scopes.map(|s| s.len()));
+    }
+
+    #[test]
+    fn an_out_of_range_expiry_is_rejected() {
+        for days in [0, 366] {
+            assert_err!(NewApiKey::try_from(form(days, None)).map(|_| ()));
+        }
+    }
+
+    #[test]
+    fn unknown_scopes_are_rejected() {
+        assert_err!(NewApiKey::try_from(form(30, Some(vec!["todos write"]))).map(|_| ()));
+        assert_err!(NewApiKey::try_from(form(30, Some(vec!["todos:delete"]))).map(|_| ()));
+        assert_err!(NewApiKey::try_from(form(30, Some(vec![""]))).map(|_| ()));
+    }
+
+    #[test]
+    fn generated_keys_are_prefixed() {
+        let (key, prefix) = generate_api_key();
+
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert!(key.starts_with(&prefix));
+    }
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyFormData {
+    pub name : String,
+    pub expires_in_days : i64,
+    pub scopes : Option<Vec<String>>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct ApiKeyData {
+    pub id : Uuid,
+    pub user_id : Uuid,
+    pub scopes : Option<Vec<String>>,
+    pub expires_at : DateTime<Utc>,
+    pub revoked_at : Option<DateTime<Utc>>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct ApiKeySummaryData {
+    pub id : Uuid,
+    pub name : String,
+    pub prefix : String,
+    pub scopes : Option<Vec<String>>,
+    pub created_at : DateTime<Utc>,
+    pub expires_at : DateTime<Utc>,
+    pub last_used_at : Option<DateTime<Utc>>,
+    pub revoked_at : Option<DateTime<Utc>>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct ApiKeyIdData {
+    pub id : Uuid
+}
\ No newline at end of file
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::{NoContext, Timestamp, Uuid};
+
+use crate::db::DbContext;
+use crate::errors::AppError;
+use crate::utils::token_hash::hash_token;
+
+use super::domain::NewApiKey;
+use super::models::{ApiKeyData, ApiKeyIdData, ApiKeySummaryData};
+
+#[tracing::instrument(name = "Creating API key", skip(key, user_id, new_api_key, db))]
+pub async fn create_api_key(
+    key: &str,
+    prefix: &str,
+    user_id: Uuid,
+    new_api_key: &NewApiKey,
+    db: &impl DbContext,
+) -> Result<(Uuid, DateTime<Utc>), AppError> {
+    let id = Uuid::new_v7(Timestamp::now(NoContext));
+    let expires_at = Utc::now() + Duration::days(new_api_key.expires_in_days);
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO api_keys (id, user_id, name, prefix, key_hash, scopes, created_at, expires_at)
+            VALUES
+            ($1, $2, $3, $4, $5, $6, now(), $7)
+        "#,
+        id,
+        user_id,
+        new_api_key.name,
+        prefix,
+        hash_token(key),
+        new_api_key.scopes.as_deref(),
+        expires_at
+    );
+
+    db.execute_query(query).await?;
+
+    Ok((id, expires_at))
+}
+
+#[tracing::instrument(name = "Fetching API key by key", skip(key, db))]
+pub async fn get_api_key_by_key(
+    key: &str,
+    db: &impl DbContext,
+) -> Result<Option<ApiKeyData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id, user_id, scopes, expires_at, revoked_at FROM api_keys WHERE key_hash = $1
+        "#,
+    )
+    .bind(hash_token(key));
+
+    let result = db.fetch_optional::<ApiKeyData>(query).await?;
+
+    Ok(result)
+}
+
+/// Records that a key was used. Writes are skipped while the last one is under a minute old
+/// so busy clients don't turn every request into an UPDATE.
+#[tracing::instrument(name = "Touching API key", skip(api_key_id, db))]
+pub async fn touch_api_key(api_key_id: Uuid, db: &impl DbContext) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            UPDATE api_keys SET last_used_at = now()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < now() - interval '1 minute')
+        "#,
+        api_key_id
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetching API keys by user id", skip(user_id, db))]
+pub async fn get_api_keys_by_user_id(
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Vec<ApiKeySummaryData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id, name, prefix, scopes, created_at, expires_at, last_used_at, revoked_at
+            FROM api_keys WHERE user_id = $1
+            ORDER BY created_at DESC
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_all::<ApiKeySummaryData>(query).await?;
+
+    Ok(result)
+}
+
+#[tracing::instrument(name = "Revoking API key", skip(api_key_id, user_id, db))]
+pub async fn revoke_api_key(
+    api_key_id: Uuid,
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Uuid, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            UPDATE api_keys SET revoked_at = COALESCE(revoked_at, now())
+            WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(user_id);
+
+    match db.fetch_all::<ApiKeyIdData>(query).await?.pop() {
+        Some(data) => Ok(data.id),
+        None => Err(AppError::NotFoundError("API key was not found".into())),
+    }
+}
@@ -1,114 +1,306 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::StatusCode,
     response::{AppendHeaders, IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
 use axum_extra::TypedHeader;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use cookie::{time::Duration, Cookie};
+use headers::{authorization::Bearer, Authorization};
 use reqwest::header::SET_COOKIE;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
     app_state::AppState,
-    configurations::JwtSettings,
+    configurations::{JwtSettings, SessionMode},
     errors::AppError,
-    utils::jwt::{decode_jwt, generate_jwt},
+    features::users::{controller::send_email_confirmation, repository::update_user_password},
+    utils::{
+        client_ip::ClientIp,
+        csrf::{csrf_cookie, generate_csrf_token},
+        session_store::session_cookie,
+        jwt::{decode_jwt, generate_jwt},
+        scopes::{RequireScopes, SessionsRead, SessionsWrite, ALL_SCOPES},
+    },
 };
 
 use super::{
-    models::{LoginFormData, RegisterFormData},
+    domain::{
+        generate_magic_link_nonce, generate_magic_link_token, AttemptKey, Credentials,
+        LoginCredentials, MagicLinkRequest, MAGIC_LINK_NONCE_COOKIE, MAGIC_LINK_TTL_MINUTES,
+    },
+    models::{
+        ConsumeMagicLinkFormData, LoginFormData, MagicLinkFormData, PasswordResetFormData,
+        RegisterFormData, SessionMetadata, UserTokenData,
+    },
     repository::{
-        add_refresh_token_by_user_id, create_user, delete_all_refresh_token_by_user_id,
-        delete_refresh_token_by_token, get_user_by_id, get_user_tokens_by_token,
-        validate_credentials,
+        add_magic_link, add_refresh_token_by_user_id, add_security_incident, clear_failed_logins, create_user,
+        delete_all_refresh_token_by_user_id, delete_other_sessions_by_user_id,
+        delete_password_reset, delete_refresh_token_family, delete_session_by_id,
+        get_account_state, get_locked_until, get_password_reset, get_roles_by_user_id,
+        get_sessions_by_user_id, get_user_id_by_email, get_user_tokens_by_token, get_username_by_email,
+        record_failed_login, rotate_refresh_token, take_magic_link, validate_credentials,
     },
 };
 
+const MAX_DEVICE_LABEL_LENGTH: usize = 64;
+
 pub fn auth_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/login", post(login_user))
         .route("/register", post(register_user))
-        .route("/refresh", get(refresh_user_token))
+        .route("/csrf", get(issue_csrf_token))
+        .route("/refresh", post(refresh_user_token))
         .route("/logout", post(logout_user))
+        .route("/sessions", get(get_sessions).delete(revoke_other_sessions))
+        .route("/sessions/:id", delete(revoke_session))
+        .route("/password-reset", post(reset_password))
+        .route("/magic-link", post(request_magic_link))
+        .route("/magic-link/consume", post(consume_magic_link))
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct AuthResponse {
-    id: Uuid,
-    access_token: String,
+    pub id: Uuid,
+    pub access_token: String,
+}
+
+/// Returned instead of `AuthResponse` in `SessionMode::Server`, where the cookie is all a client
+/// needs.
+#[derive(Serialize, Deserialize)]
+pub struct SessionLoginResponse {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CsrfResponse {
+    pub csrf_token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub device_label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub current: bool,
 }
 
-#[tracing::instrument(name = "Logging User In", skip(app_state, cookie, input))]
+#[tracing::instrument(name = "Logging User In", skip(app_state, user_agent, cookie, input))]
 async fn login_user(
     State(app_state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
     TypedHeader(cookie): TypedHeader<headers::Cookie>,
-    Json(input): Json<LoginFormData>,
+    Json(mut input): Json<LoginFormData>,
 ) -> Result<Response, AppError> {
-    let input = input.try_into()?;
+    let metadata = session_metadata(ip, user_agent, input.device_label.take());
+    let input: LoginCredentials = input.try_into()?;
 
-    let id = validate_credentials(&input, &app_state.pool, &app_state.pwd_hasher).await?;
+    let attempt_keys = [account_attempt_key(&input, &app_state).await?, AttemptKey::Ip(ip)];
 
-    let (at, rt) = generate_auth_tokens(id, &app_state.jwt_settings)?;
+    for key in &attempt_keys {
+        if let Some(locked_until) = get_locked_until(key, &app_state.pool).await? {
+            let retry_after = (locked_until - Utc::now()).num_seconds().max(1);
 
-    let a = if let Some(data) = cookie.get("rt") {
-        let token_data = get_user_tokens_by_token(data, &app_state.pool).await?;
+            return Err(AppError::LockedError(
+                "Too many failed login attempts.".into(),
+                retry_after,
+            ));
+        }
+    }
+
+    let id = match validate_credentials(&input, &app_state.pool, &app_state.pwd_hasher).await {
+        Ok(id) => id,
+        Err(AppError::UnauthorizedError(e)) => {
+            for key in &attempt_keys {
+                record_failed_login(key, &app_state.login_throttle, &app_state.pool).await?;
+            }
+
+            return Err(AppError::UnauthorizedError(e));
+        }
+        Err(e) => return Err(e),
+    };
+
+    clear_failed_logins(&attempt_keys[0], &app_state.pool).await?;
 
-        if token_data.is_none() {
-            delete_all_refresh_token_by_user_id(id, &app_state.pool).await?;
+    start_session(id, Some(&cookie), &metadata, &app_state).await
+}
+
+/// Failed attempts count per account, so guesses made with the email and with the username
+/// share a lockout. Identifiers that match no account are keyed as given.
+async fn account_attempt_key(
+    input: &LoginCredentials,
+    app_state: &AppState,
+) -> Result<AttemptKey, AppError> {
+    if input.is_email() {
+        if let Some(username) = get_username_by_email(&input.identifier, &app_state.pool).await? {
+            return Ok(AttemptKey::username(&username));
         }
+    }
 
-        data
-    } else {
-        ""
+    Ok(AttemptKey::username(&input.identifier))
+}
+
+/// Emails a single-use login link. The response is the same whether or not the address belongs
+/// to an account, so it cannot be used to find out who has one.
+#[tracing::instrument(name = "Requesting magic link", skip(app_state, input))]
+async fn request_magic_link(
+    State(app_state): State<Arc<AppState>>,
+    Json(input): Json<MagicLinkFormData>,
+) -> Result<Response, AppError> {
+    let input: MagicLinkRequest = input.try_into()?;
+    let nonce = generate_magic_link_nonce();
+
+    // Looking the account up and sending happen in the background, so neither the response time
+    // nor a failed send tells whether the email belongs to an account.
+    let task_state = app_state.clone();
+    let task_nonce = nonce.clone();
+    tokio::spawn(async move {
+        if let Err(e) = send_magic_link(&input.email, &task_nonce, &task_state).await {
+            tracing::error!(error = %e, "Failed to send the magic link.");
+        }
+    });
+
+    let cookie = magic_link_nonce_cookie(
+        nonce,
+        Duration::minutes(MAGIC_LINK_TTL_MINUTES),
+        &app_state.jwt_settings,
+    );
+
+    Ok((StatusCode::ACCEPTED, AppendHeaders([(SET_COOKIE, cookie.to_string())])).into_response())
+}
+
+async fn send_magic_link(email: &str, nonce: &str, app_state: &AppState) -> Result<(), AppError> {
+    let user_id = match get_user_id_by_email(email, &app_state.pool).await? {
+        Some(data) => data,
+        None => return Ok(()),
     };
-    delete_refresh_token_by_token(a, &app_state.pool).await?;
 
-    add_refresh_token_by_user_id(rt.value(), id, &app_state.pool).await?;
+    let token = generate_magic_link_token();
+    let expires_at = Utc::now() + ChronoDuration::minutes(MAGIC_LINK_TTL_MINUTES);
+
+    add_magic_link(&token, nonce, user_id, expires_at, &app_state.pool).await?;
+
+    let link = format!(
+        "{}/magic-link?token={}",
+        app_state.client_url.trim_end_matches('/'),
+        token
+    );
+    app_state
+        .email_client
+        .send_email(
+            email,
+            "Your login link",
+            &format!("Open <a href=\"{}\">this link</a> to log in.", link),
+            &format!("Open {} to log in.", link),
+        )
+        .await?;
 
-    Ok((
-        StatusCode::OK,
-        AppendHeaders([(SET_COOKIE, rt.to_string())]),
-        Json(AuthResponse {
-            id,
-            access_token: at,
-        }),
-    )
-        .into_response())
+    Ok(())
+}
+
+/// Completes a magic link login. The link only works in the browser that requested it, which
+/// holds the matching nonce cookie.
+#[tracing::instrument(name = "Consuming magic link", skip(app_state, user_agent, cookie, input))]
+async fn consume_magic_link(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    cookie: Option<TypedHeader<headers::Cookie>>,
+    Json(mut input): Json<ConsumeMagicLinkFormData>,
+) -> Result<Response, AppError> {
+    let metadata = session_metadata(ip, user_agent, input.device_label.take());
+    let cookie = cookie.map(|TypedHeader(c)| c);
+
+    let nonce = cookie
+        .as_ref()
+        .and_then(|c| c.get(MAGIC_LINK_NONCE_COOKIE))
+        .ok_or(AppError::UnauthorizedError(
+            "Magic link was requested from another browser.".into(),
+        ))?;
+
+    // A wrong nonce leaves the link in place for the browser that requested it.
+    let link = take_magic_link(&input.token, nonce, &app_state.pool).await?;
+
+    match get_account_state(link.user_id, &app_state.pool).await? {
+        Some(state) => state.ensure_active()?,
+        None => return Err(AppError::UnauthorizedError("Invalid or expired token".into())),
+    }
+
+    let response = start_session(link.user_id, cookie.as_ref(), &metadata, &app_state).await?;
+    let nonce_cookie = magic_link_nonce_cookie(String::new(), Duration::ZERO, &app_state.jwt_settings);
+
+    Ok((AppendHeaders([(SET_COOKIE, nonce_cookie.to_string())]), response).into_response())
 }
 
-#[tracing::instrument(name = "Registering User", skip(app_state, input))]
+#[tracing::instrument(name = "Registering User", skip(app_state, user_agent, input))]
 async fn register_user(
     State(app_state): State<Arc<AppState>>,
-    Json(input): Json<RegisterFormData>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Json(mut input): Json<RegisterFormData>,
 ) -> Result<Response, AppError> {
-    let input = input.try_into()?;
+    let metadata = session_metadata(ip, user_agent, input.device_label.take());
+    let input: Credentials = input.try_into()?;
 
-    let id = create_user(&input, &app_state.pool, &app_state.pwd_hasher).await?;
+    app_state.password_policy.check(&input)?;
+
+    if let Some(email) = &input.email {
+        if get_user_id_by_email(email, &app_state.pool).await?.is_some() {
+            return Err(AppError::ConflictError("Email is already taken.".into()));
+        }
+    }
+
+    let id = match create_user(&input, &app_state.pool, &app_state.pwd_hasher).await {
+        Err(e) if e.is_unique_violation() => {
+            return Err(AppError::ConflictError("Username is already taken.".into()))
+        }
+        result => result?,
+    };
+
+    // The address only lands on the account once its owner opens the confirmation link.
+    if let Some(email) = &input.email {
+        if let Err(e) = send_email_confirmation(id, email, &app_state).await {
+            tracing::warn!(error = %e, user_id = %id, "Failed to send the email confirmation.");
+        }
+    }
+
+    start_session(id, None, &metadata, &app_state).await
+}
 
-    let (at, rt) = generate_auth_tokens(id, &app_state.jwt_settings)?;
+/// Hands out the token `csrf_protect` expects on cookie-authenticated requests. CORS keeps other sites from
+/// reading it.
+#[tracing::instrument(name = "Issuing CSRF token", skip(app_state))]
+async fn issue_csrf_token(State(app_state): State<Arc<AppState>>) -> Result<Response, AppError> {
+    let csrf_token = generate_csrf_token();
+    let cookie = csrf_cookie(csrf_token.clone(), &app_state.jwt_settings);
 
     Ok((
         StatusCode::OK,
-        AppendHeaders([(SET_COOKIE, rt.to_string())]),
-        Json(AuthResponse {
-            id,
-            access_token: at,
-        }),
+        AppendHeaders([(SET_COOKIE, cookie.to_string())]),
+        Json(CsrfResponse { csrf_token }),
     )
         .into_response())
 }
 
-#[tracing::instrument(name = "Refreshing User token", skip(app_state, cookie))]
+#[tracing::instrument(name = "Refreshing User token", skip(app_state, user_agent, cookie))]
 async fn refresh_user_token(
     State(app_state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
     TypedHeader(cookie): TypedHeader<headers::Cookie>,
 ) -> Result<Response, AppError> {
-    let rt = match cookie.get("rt") {
+    let rt = match cookie.get(&app_state.jwt_settings.refresh_cookie.name) {
         Some(data) => data,
         None => {
             return Err(AppError::UnauthorizedError(
@@ -117,26 +309,30 @@ async fn refresh_user_token(
         }
     };
 
+    let metadata = session_metadata(ip, user_agent, None);
+
     let user_token_data = match get_user_tokens_by_token(rt, &app_state.pool).await? {
         Some(data) => data,
         None => {
-            let token_data = decode_jwt(rt, &app_state.jwt_settings, true)
-                .map_err(|e| AppError::UnauthorizedError(e.to_string()))?;
-
-            let user = get_user_by_id(token_data.claims.id, &app_state.pool).await?;
-
-            delete_all_refresh_token_by_user_id(user.id, &app_state.pool).await?;
             return Err(AppError::UnauthorizedError(
-                "Refresh token reuse found.".into(),
-            ));
+                "Refresh token is no longer valid.".into(),
+            ))
         }
     };
 
-    let token_data = match decode_jwt(rt, &app_state.jwt_settings, true) {
+    if user_token_data.rotated_at.is_some() {
+        revoke_reused_token_family(&user_token_data, &metadata, &app_state).await?;
+
+        return Err(AppError::UnauthorizedError(
+            "Refresh token reuse found.".into(),
+        ));
+    }
+
+    let token_data = match decode_jwt(rt, &app_state.jwt_settings, &app_state.jwt_keys, true) {
         Ok(data) => data,
         Err(e) => {
             if *e.kind() == jsonwebtoken::errors::ErrorKind::ExpiredSignature {
-                delete_refresh_token_by_token(rt, &app_state.pool).await?;
+                delete_refresh_token_family(user_token_data.family_id, &app_state.pool).await?;
             }
             return Err(AppError::UnauthorizedError(e.to_string()));
         }
@@ -146,39 +342,95 @@ async fn refresh_user_token(
         return Err(AppError::UnauthorizedError("Invalid jwt token".into()));
     }
 
-    let user = get_user_by_id(token_data.claims.id, &app_state.pool).await?;
+    let user_id = token_data.claims.id;
 
-    delete_refresh_token_by_token(rt, &app_state.pool).await?;
+    match get_account_state(user_id, &app_state.pool).await? {
+        Some(state) => state.ensure_active()?,
+        None => return Err(AppError::NotFoundError("User was not found".into())),
+    }
+
+    let (at, rt, rt_expires_at) = generate_auth_tokens(user_id, &app_state).await?;
+
+    let is_rotated = rotate_refresh_token(
+        &user_token_data,
+        rt.value(),
+        rt_expires_at,
+        &metadata,
+        &app_state.pool,
+    )
+    .await?;
 
-    let (at, rt) = generate_auth_tokens(user.id, &app_state.jwt_settings)?;
+    // Another request rotated this token first, so it was presented twice.
+    if !is_rotated {
+        revoke_reused_token_family(&user_token_data, &metadata, &app_state).await?;
 
-    add_refresh_token_by_user_id(rt.value(), token_data.claims.id, &app_state.pool).await?;
+        return Err(AppError::UnauthorizedError(
+            "Refresh token reuse found.".into(),
+        ));
+    }
 
     Ok((
         StatusCode::OK,
         AppendHeaders([(SET_COOKIE, rt.to_string())]),
         Json(AuthResponse {
-            id: user.id,
+            id: user_id,
             access_token: at,
         }),
     )
         .into_response())
 }
 
-#[tracing::instrument(name = "Logging User out", skip(app_state, cookie))]
+#[tracing::instrument(name = "Logging User out", skip(app_state, bearer, cookie))]
 async fn logout_user(
     State(app_state): State<Arc<AppState>>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
     TypedHeader(cookie): TypedHeader<headers::Cookie>,
 ) -> Result<Response, AppError> {
-    let empty_rt = cookie::Cookie::build(("rt", ""))
-        .path("/")
-        .secure(true)
-        .http_only(true)
-        .same_site(cookie::SameSite::Lax)
-        .max_age(Duration::ZERO)
-        .build();
+    // The access token would otherwise stay usable until it expires.
+    if let Some(TypedHeader(Authorization(bearer))) = bearer {
+        if let Ok(token_data) =
+            decode_jwt(bearer.token(), &app_state.jwt_settings, &app_state.jwt_keys, false)
+        {
+            if let Some(expires_at) = DateTime::from_timestamp(token_data.claims.exp as i64, 0) {
+                app_state
+                    .token_denylist
+                    .revoke(token_data.claims.jti, expires_at, &app_state.pool)
+                    .await?;
+            }
+        }
+    }
+
+    if app_state.session_settings.mode == SessionMode::Server {
+        let settings = &app_state.session_settings;
+
+        if let Some(session_id) = cookie.get(&settings.cookie_name) {
+            app_state.session_store.delete(session_id).await?;
+        }
+
+        let empty_session =
+            session_cookie(String::new(), Duration::ZERO, settings, &app_state.jwt_settings);
+
+        return Ok((
+            StatusCode::OK,
+            AppendHeaders([(SET_COOKIE, empty_session.to_string())]),
+        )
+            .into_response());
+    }
+
+    let empty_rt = refresh_token_cookie(String::new(), Duration::ZERO, &app_state.jwt_settings);
+
+    let rt = match cookie.get(&app_state.jwt_settings.refresh_cookie.name) {
+        Some(data) => data,
+        None => {
+            return Ok((
+                StatusCode::OK,
+                AppendHeaders([(SET_COOKIE, empty_rt.to_string())]),
+            )
+                .into_response())
+        }
+    };
 
-    let rt = match cookie.get("rt") {
+    let user_token_data = match get_user_tokens_by_token(rt, &app_state.pool).await? {
         Some(data) => data,
         None => {
             return Ok((
@@ -189,42 +441,317 @@ async fn logout_user(
         }
     };
 
-    if get_user_tokens_by_token(rt, &app_state.pool)
+    delete_refresh_token_family(user_token_data.family_id, &app_state.pool).await?;
+
+    Ok((
+        StatusCode::OK,
+        AppendHeaders([(SET_COOKIE, empty_rt.to_string())]),
+    )
+        .into_response())
+}
+
+/// Completes a reset started by an administrator. Every session is signed out, since whoever
+/// held them may have known the old password.
+#[tracing::instrument(name = "Resetting User password", skip(app_state, input))]
+async fn reset_password(
+    State(app_state): State<Arc<AppState>>,
+    Json(input): Json<PasswordResetFormData>,
+) -> Result<Response, AppError> {
+    let reset = get_password_reset(&input.token, &app_state.pool).await?;
+
+    let credentials = Credentials {
+        username: reset.username,
+        password: input.password,
+        email: None,
+    };
+    credentials.validate()?;
+    app_state.password_policy.check(&credentials)?;
+
+    delete_password_reset(&input.token, &app_state.pool).await?;
+
+    update_user_password(
+        reset.user_id,
+        &credentials.password,
+        &app_state.pool,
+        &app_state.pwd_hasher,
+    )
+    .await?;
+    delete_all_refresh_token_by_user_id(reset.user_id, &app_state.pool).await?;
+    app_state.session_store.delete_user_sessions(reset.user_id, None).await?;
+    clear_failed_logins(&AttemptKey::username(&credentials.username), &app_state.pool).await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Listing User sessions", skip(app_state, cookie, user))]
+async fn get_sessions(
+    State(app_state): State<Arc<AppState>>,
+    cookie: Option<TypedHeader<headers::Cookie>>,
+    RequireScopes { user, .. }: RequireScopes<SessionsRead>,
+) -> Result<Response, AppError> {
+    let (sessions, current_session_id) = match app_state.session_settings.mode {
+        SessionMode::Jwt => (
+            get_sessions_by_user_id(user.id, &app_state.pool).await?,
+            get_current_session_id(cookie, user.id, &app_state).await?,
+        ),
+        SessionMode::Server => {
+            let current = match current_server_session(cookie.as_ref(), &app_state) {
+                Some(data) => app_state.session_store.get(data).await?.map(|s| s.id),
+                None => None,
+            };
+
+            (app_state.session_store.list_user_sessions(user.id).await?, current)
+        }
+    };
+
+    let sessions = sessions
+        .into_iter()
+        .map(|s| SessionResponse {
+            current: Some(s.id) == current_session_id,
+            id: s.id,
+            user_agent: s.user_agent,
+            ip: s.ip,
+            device_label: s.device_label,
+            created_at: s.created_at,
+            last_used_at: s.last_used_at,
+            expires_at: s.expires_at,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(sessions)).into_response())
+}
+
+#[tracing::instrument(name = "Revoking User session", skip(app_state, user))]
+async fn revoke_session(
+    State(app_state): State<Arc<AppState>>,
+    Path(session_id): Path<Uuid>,
+    RequireScopes { user, .. }: RequireScopes<SessionsWrite>,
+) -> Result<Response, AppError> {
+    user.reject_impersonation()?;
+
+    match app_state.session_settings.mode {
+        SessionMode::Jwt => {
+            delete_session_by_id(session_id, user.id, &app_state.pool).await?;
+        }
+        SessionMode::Server => {
+            app_state.session_store.delete_user_session(user.id, session_id).await?;
+        }
+    }
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Revoking other User sessions", skip(app_state, cookie, user))]
+async fn revoke_other_sessions(
+    State(app_state): State<Arc<AppState>>,
+    cookie: Option<TypedHeader<headers::Cookie>>,
+    RequireScopes { user, .. }: RequireScopes<SessionsWrite>,
+) -> Result<Response, AppError> {
+    user.reject_impersonation()?;
+
+    match app_state.session_settings.mode {
+        SessionMode::Jwt => {
+            let current_session_id = get_current_session_id(cookie, user.id, &app_state).await?;
+
+            delete_other_sessions_by_user_id(user.id, current_session_id, &app_state.pool).await?;
+        }
+        SessionMode::Server => {
+            let current = current_server_session(cookie.as_ref(), &app_state);
+
+            app_state.session_store.delete_user_sessions(user.id, current).await?;
+        }
+    }
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+/// The session cookie the request was made with in `SessionMode::Server`.
+pub fn current_server_session<'a>(
+    cookie: Option<&'a TypedHeader<headers::Cookie>>,
+    app_state: &AppState,
+) -> Option<&'a str> {
+    cookie.and_then(|TypedHeader(c)| c.get(&app_state.session_settings.cookie_name))
+}
+
+pub async fn get_current_session_id(
+    cookie: Option<TypedHeader<headers::Cookie>>,
+    user_id: Uuid,
+    app_state: &AppState,
+) -> Result<Option<Uuid>, AppError> {
+    let cookie_name = &app_state.jwt_settings.refresh_cookie.name;
+    let rt = match cookie.as_ref().and_then(|TypedHeader(c)| c.get(cookie_name)) {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+
+    let session_id = get_user_tokens_by_token(rt, &app_state.pool)
         .await?
-        .is_none()
-    {
+        .filter(|data| data.user_id == user_id && data.rotated_at.is_none())
+        .map(|data| data.family_id);
+
+    Ok(session_id)
+}
+
+async fn revoke_reused_token_family(
+    user_token_data: &UserTokenData,
+    metadata: &SessionMetadata,
+    app_state: &AppState,
+) -> Result<(), AppError> {
+    tracing::warn!(
+        user_id = %user_token_data.user_id,
+        family_id = %user_token_data.family_id,
+        "Refresh token reuse detected, revoking token family."
+    );
+
+    delete_refresh_token_family(user_token_data.family_id, &app_state.pool).await?;
+
+    add_security_incident(
+        "refresh_token_reuse",
+        user_token_data.user_id,
+        Some(user_token_data.family_id),
+        metadata,
+        &app_state.pool,
+    )
+    .await
+}
+
+fn session_metadata(
+    ip: std::net::IpAddr,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    device_label: Option<String>,
+) -> SessionMetadata {
+    SessionMetadata {
+        user_agent: user_agent.map(|TypedHeader(ua)| ua.to_string()),
+        ip: ip.to_string(),
+        device_label: device_label
+            .map(|label| label.trim().chars().take(MAX_DEVICE_LABEL_LENGTH).collect())
+            .filter(|label: &String| !label.is_empty()),
+    }
+}
+
+/// Signs the user in and builds the response for the configured `SessionMode`: an access token
+/// with a refresh cookie, or only a session cookie. Logging in again from the same browser
+/// replaces the session it already holds.
+async fn start_session(
+    user_id: Uuid,
+    cookie: Option<&headers::Cookie>,
+    metadata: &SessionMetadata,
+    app_state: &AppState,
+) -> Result<Response, AppError> {
+    if app_state.session_settings.mode == SessionMode::Server {
+        let settings = &app_state.session_settings;
+
+        if let Some(data) = cookie.and_then(|c| c.get(&settings.cookie_name)) {
+            app_state.session_store.delete(data).await?;
+        }
+
+        let expires_at = Utc::now() + ChronoDuration::seconds(settings.ttl_seconds);
+        let session_id = app_state
+            .session_store
+            .create(user_id, metadata, expires_at)
+            .await?;
+        let session_cookie = session_cookie(
+            session_id,
+            Duration::seconds(settings.ttl_seconds),
+            settings,
+            &app_state.jwt_settings,
+        );
+
         return Ok((
             StatusCode::OK,
-            AppendHeaders([(SET_COOKIE, empty_rt.to_string())]),
+            AppendHeaders([(SET_COOKIE, session_cookie.to_string())]),
+            Json(SessionLoginResponse { id: user_id }),
         )
             .into_response());
     }
 
-    delete_refresh_token_by_token(rt, &app_state.pool).await?;
+    let (at, rt, rt_expires_at) = generate_auth_tokens(user_id, app_state).await?;
+
+    if let Some(data) = cookie.and_then(|c| c.get(&app_state.jwt_settings.refresh_cookie.name)) {
+        if let Some(token_data) = get_user_tokens_by_token(data, &app_state.pool).await? {
+            delete_refresh_token_family(token_data.family_id, &app_state.pool).await?;
+        }
+    }
+
+    add_refresh_token_by_user_id(rt.value(), user_id, rt_expires_at, metadata, &app_state.pool)
+        .await?;
 
     Ok((
         StatusCode::OK,
-        AppendHeaders([(SET_COOKIE, empty_rt.to_string())]),
+        AppendHeaders([(SET_COOKIE, rt.to_string())]),
+        Json(AuthResponse {
+            id: user_id,
+            access_token: at,
+        }),
     )
         .into_response())
 }
 
-pub fn generate_auth_tokens(
+/// Roles are read when tokens are issued, so grants and revocations reach a user's access
+/// token on their next login or refresh.
+pub async fn generate_auth_tokens(
     user_id: Uuid,
-    jwt_settings: &JwtSettings,
-) -> Result<(String, Cookie), AppError> {
-    let at = generate_jwt(user_id, jwt_settings, false)
-        .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
-    let rt = generate_jwt(user_id, jwt_settings, true)
-        .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
-
-    let cookie = cookie::CookieBuilder::new("rt", rt)
+    app_state: &AppState,
+) -> Result<(String, Cookie<'static>, DateTime<Utc>), AppError> {
+    let roles = get_roles_by_user_id(user_id, &app_state.pool).await?;
+
+    let (at, _) = generate_jwt(
+        user_id,
+        &roles,
+        ALL_SCOPES,
+        &app_state.jwt_settings,
+        &app_state.jwt_keys,
+        false,
+    )
+    .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+    let (rt, rt_expires_at) = generate_jwt(
+        user_id,
+        &[],
+        &[],
+        &app_state.jwt_settings,
+        &app_state.jwt_keys,
+        true,
+    )
+    .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+    let max_age = Duration::seconds((rt_expires_at - Utc::now()).num_seconds().max(0));
+    let cookie = refresh_token_cookie(rt, max_age, &app_state.jwt_settings);
+
+    Ok((at, cookie, rt_expires_at))
+}
+
+/// The cookie lifetime is always derived from the refresh token expiry so the two never drift.
+pub fn refresh_token_cookie(value: String, max_age: Duration, jwt_settings: &JwtSettings) -> Cookie<'static> {
+    let settings = &jwt_settings.refresh_cookie;
+
+    let mut cookie = cookie::CookieBuilder::new(settings.name.to_string(), value)
+        .http_only(true)
+        .max_age(max_age)
+        .path(settings.path.to_string())
+        .same_site((&settings.same_site).into())
+        .secure(settings.secure);
+
+    if let Some(domain) = &settings.domain {
+        cookie = cookie.domain(domain.to_string());
+    }
+
+    cookie.build()
+}
+
+/// Shares the refresh cookie's attributes, but is only ever sent to the magic link routes.
+fn magic_link_nonce_cookie(value: String, max_age: Duration, jwt_settings: &JwtSettings) -> Cookie<'static> {
+    let settings = &jwt_settings.refresh_cookie;
+
+    let mut cookie = cookie::CookieBuilder::new(MAGIC_LINK_NONCE_COOKIE, value)
         .http_only(true)
-        .max_age(Duration::days(7))
-        .path("/")
-        .same_site(cookie::SameSite::Lax)
-        .secure(true)
-        .build();
+        .max_age(max_age)
+        .path("/api/auth/magic-link")
+        .same_site((&settings.same_site).into())
+        .secure(settings.secure);
+
+    if let Some(domain) = &settings.domain {
+        cookie = cookie.domain(domain.to_string());
+    }
 
-    Ok((at, cookie))
+    cookie.build()
 }
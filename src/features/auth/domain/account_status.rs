@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppError;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatus {
+    Active,
+    Disabled,
+    Banned,
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Disabled => "disabled",
+            AccountStatus::Banned => "banned",
+        }
+    }
+
+    /// Unknown values are treated as disabled so a bad row can never grant access.
+    pub fn parse(v: &str) -> Self {
+        match v {
+            "active" => AccountStatus::Active,
+            "banned" => AccountStatus::Banned,
+            _ => AccountStatus::Disabled,
+        }
+    }
+}
+
+/// The `status` columns of a user. A status with an `until` in the past has lapsed, and the
+/// account counts as active again without anyone having to clear it.
+#[derive(Clone)]
+pub struct AccountState {
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl AccountState {
+    pub fn active() -> Self {
+        Self {
+            status: AccountStatus::Active,
+            reason: None,
+            until: None,
+        }
+    }
+
+    pub fn effective_status(&self) -> AccountStatus {
+        match self.until {
+            Some(until) if until <= Utc::now() => AccountStatus::Active,
+            _ => self.status,
+        }
+    }
+
+    pub fn ensure_active(&self) -> Result<(), AppError> {
+        match self.effective_status() {
+            AccountStatus::Active => Ok(()),
+            status => Err(AppError::AccountInactiveError {
+                status: status.as_str().into(),
+                reason: self.reason.clone(),
+                until: self.until,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use claims::{assert_err, assert_ok};
+
+    use super::{AccountState, AccountStatus};
+
+    fn state(status: AccountStatus, until: Option<i64>) -> AccountState {
+        AccountState {
+            status,
+            reason: None,
+            until: until.map(|minutes| Utc::now() + Duration::minutes(minutes)),
+        }
+    }
+
+    #[test]
+    fn only_active_accounts_pass() {
+        assert_ok!(state(AccountStatus::Active, None).ensure_active());
+        assert_err!(state(AccountStatus::Disabled, None).ensure_active());
+        assert_err!(state(AccountStatus::Banned, Some(10)).ensure_active());
+    }
+
+    #[test]
+    fn a_lapsed_status_counts_as_active() {
+        assert_eq!(AccountStatus::Active, state(AccountStatus::Banned, Some(-1)).effective_status());
+    }
+
+    #[test]
+    fn unknown_statuses_are_treated_as_disabled() {
+        assert_eq!(AccountStatus::Disabled, AccountStatus::parse("suspended"));
+    }
+}
@@ -1,40 +1,128 @@
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
-use validator::{Validate, ValidationError};
+use validator::{Validate, ValidateEmail, ValidationError};
+use crate::configurations::MAX_PASSWORD_LENGTH;
 use crate::errors::AppError;
 use crate::features::auth::models::{LoginFormData, RegisterFormData};
 
+/// The longest address SMTP can deliver to.
+pub const MAX_EMAIL_LENGTH: u64 = 254;
+
 #[derive(Validate)]
 pub struct Credentials {
     #[validate(custom(function = "parse_username"))]
     pub username : String,
     #[validate(custom(function = "parse_password"))]
+    pub password : String,
+    #[validate(email(code = "invalid_email"), length(max = "MAX_EMAIL_LENGTH", code = "invalid_email"))]
+    pub email : Option<String>
+}
+
+/// What a user signs in with. `identifier` is an email address when it contains `@` and a
+/// username otherwise, which is unambiguous because usernames cannot contain `@`.
+#[derive(Validate)]
+pub struct LoginCredentials {
+    #[validate(custom(function = "parse_identifier"))]
+    pub identifier : String,
+    #[validate(custom(function = "parse_password"))]
     pub password : String
 }
 
+impl LoginCredentials {
+    pub fn is_email(&self) -> bool {
+        looks_like_email(&self.identifier)
+    }
+}
+
 fn parse_username (v : &str) -> Result<(), ValidationError>{ 
     let is_empty = v.trim().is_empty();
 
     let is_too_long = v.graphemes(true).count() > 12;
 
-    if is_empty || is_too_long {
+    if is_empty || is_too_long || looks_like_email(v) {
         return Err(ValidationError::new("invalid_username").with_message(std::borrow::Cow::Borrowed("Invalid Username")))
     }
 
     Ok(())
 }
 
+fn parse_identifier (v : &str) -> Result<(), ValidationError>{ 
+    if !looks_like_email(v) {
+        return parse_username(v)
+    }
+
+    if v.len() as u64 > MAX_EMAIL_LENGTH || !v.validate_email() {
+        return Err(ValidationError::new("invalid_email").with_message(std::borrow::Cow::Borrowed("Invalid Email")))
+    }
+
+    Ok(())
+}
+
+fn looks_like_email (v : &str) -> bool {
+    v.contains('@')
+}
+
+/// Folds compatibility characters, e.g. fullwidth letters, so visually identical usernames
+/// collide in the case-insensitive unique index on `users`.
+pub fn normalize_username (v : &str) -> String {
+    v.nfkc().collect()
+}
+
+/// Email addresses keep their case as entered and are compared case-insensitively.
+pub fn normalize_email (v : &str) -> String {
+    v.trim().to_string()
+}
+
+/// Only bounds the input; the rules for new passwords live in `PasswordPolicy`.
 fn parse_password (v : &str) -> Result<(), ValidationError>{ 
     let is_empty = v.trim().is_empty();
 
-    let is_too_long = v.graphemes(true).count() > 12;
+    let is_too_long = v.graphemes(true).count() > MAX_PASSWORD_LENGTH;
 
     if is_empty || is_too_long {
-        return Err(ValidationError::new("invalid_username").with_message(std::borrow::Cow::Borrowed("Invalid Username")))
+        return Err(ValidationError::new("invalid_password").with_message(std::borrow::Cow::Borrowed("Invalid Password")))
     }
 
     Ok(())
 }
 
+impl TryFrom<LoginFormData> for LoginCredentials {
+    type Error = AppError;
+
+    fn try_from(value: LoginFormData) -> Result<Self, Self::Error> {
+        let LoginFormData {username, password, ..} = value;
+
+        let identifier = match looks_like_email(&username) {
+            true => normalize_email(&username),
+            false => normalize_username(&username)
+        };
+
+        let credentials = LoginCredentials {identifier, password};
+
+        credentials.validate()?;
+
+        Ok(credentials)
+    }
+}
+
+impl TryFrom<RegisterFormData> for Credentials {
+    type Error = AppError;
+
+    fn try_from(value: RegisterFormData) -> Result<Self, Self::Error> {
+        let RegisterFormData{username, password, email, ..} = value;
+
+        let credentials = Credentials {
+            username : normalize_username(&username),
+            password,
+            email : email.map(|v| normalize_email(&v))
+        };
+
+        credentials.validate()?;
+
+        Ok(credentials)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use claims::{assert_err, assert_ok};
@@ -43,12 +131,13 @@ mod tests {
 
     use crate::utils::randomizer::generate_random_string;
 
-    use super::Credentials;
+    use super::{normalize_username, Credentials, LoginCredentials};
  
     fn generate_test_user() -> Credentials {
         Credentials {
             username : (1..12).fake(),
-            password : (1..12).fake()
+            password : (1..12).fake(),
+            email : None
         }
     }
     #[test]
@@ -83,7 +172,7 @@ mod tests {
     #[test]
     fn a_long_username_is_rejected() {
         let mut credentials = generate_test_user();
-        let test_username = vec![generate_random_string(14), "انفسكم".repeat(12).into()];
+        let test_username = [generate_random_string(14), "انفسكم".repeat(12)];
 
         for v in test_username.iter() {
             credentials.username = v.to_string();
@@ -94,10 +183,20 @@ mod tests {
     }
 
 
+    #[test]
+    fn a_long_passphrase_is_accepted() {
+        let mut credentials = generate_test_user();
+        credentials.password = "violet kettle marching under rain".into();
+
+        let result = credentials.validate();
+
+        assert_ok!(result);
+    }
+
     #[test]
     fn a_long_password_is_rejected() {
         let mut credentials = generate_test_user();
-        let test_password= vec![generate_random_string(14), "انفسكم".repeat(12).into()];
+        let test_password = [generate_random_string(1025), "انفسكم".repeat(200)];
 
         for v in test_password.iter() {
             credentials.password = v.to_string();
@@ -106,32 +205,43 @@ mod tests {
             assert_err!(result);
         }
     }
-}
 
-impl TryFrom<LoginFormData> for Credentials {
-    type Error = AppError;
+    #[test]
+    fn a_username_is_normalized_to_nfkc() {
+        assert_eq!("tester", normalize_username("ｔｅｓｔｅｒ"));
+        assert_eq!("caf\u{e9}", normalize_username("cafe\u{301}"));
+    }
 
-    fn try_from(value: LoginFormData) -> Result<Self, Self::Error> {
-        let LoginFormData {username, password} = value;
+    #[test]
+    fn an_invalid_email_is_rejected() {
+        let mut credentials = generate_test_user();
 
-        let credentials = Credentials {username, password};
+        for v in ["not-an-email", "a@", &format!("{}@example.com", "a".repeat(250))] {
+            credentials.email = Some(v.to_string());
 
-        credentials.validate()?;
+            assert_err!(credentials.validate());
+        }
 
-        Ok(credentials)
+        credentials.email = Some("ada@example.com".into());
+        assert_ok!(credentials.validate());
     }
-}
 
-impl TryFrom<RegisterFormData> for Credentials {
-    type Error = AppError;
-
-    fn try_from(value: RegisterFormData) -> Result<Self, Self::Error> {
-        let RegisterFormData{username, password} = value;
-
-        let credentials = Credentials {username, password};
+    #[test]
+    fn a_username_cannot_look_like_an_email() {
+        let mut credentials = generate_test_user();
+        credentials.username = "ada@home".into();
 
-        credentials.validate()?;
+        assert_err!(credentials.validate());
+    }
 
-        Ok(credentials)
+    #[test]
+    fn a_login_identifier_can_be_an_email_or_a_username() {
+        let login = |identifier : &str| LoginCredentials { identifier : identifier.into(), password : "secret".into() };
+
+        assert!(login("ada@example.com").is_email());
+        assert_ok!(login("ada@example.com").validate());
+        assert!(!login("ada").is_email());
+        assert_ok!(login("ada").validate());
+        assert_err!(login("ada@").validate());
     }
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+use std::net::IpAddr;
+
+use chrono::Duration;
+
+use crate::configurations::LoginThrottleSettings;
+
+use super::normalize_username;
+
+pub enum AttemptKey {
+    Username(String),
+    Ip(IpAddr),
+}
+
+impl AttemptKey {
+    pub fn username(username: &str) -> Self {
+        Self::Username(normalize_username(username).to_lowercase())
+    }
+
+    pub fn key_type(&self) -> &'static str {
+        match self {
+            AttemptKey::Username(_) => "username",
+            AttemptKey::Ip(_) => "ip",
+        }
+    }
+
+    pub fn key_value(&self) -> String {
+        match self {
+            AttemptKey::Username(v) => v.to_string(),
+            AttemptKey::Ip(v) => v.to_string(),
+        }
+    }
+}
+
+/// Lockout to apply after `failed_count` consecutive failures. Nothing is locked until the
+/// threshold is reached, after which the lockout doubles with each further failure.
+pub fn lockout_duration(failed_count: u32, settings: &LoginThrottleSettings) -> Option<Duration> {
+    if failed_count < settings.max_attempts {
+        return None;
+    }
+
+    let exponent = (failed_count - settings.max_attempts).min(30);
+    let seconds = settings
+        .base_lockout_seconds
+        .saturating_mul(1 << exponent)
+        .min(settings.max_lockout_seconds);
+
+    Duration::try_seconds(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_none, assert_some_eq};
+    use chrono::Duration;
+
+    use crate::configurations::LoginThrottleSettings;
+
+    use super::lockout_duration;
+
+    fn test_settings() -> LoginThrottleSettings {
+        LoginThrottleSettings {
+            max_attempts: 5,
+            attempt_window_seconds: 900,
+            base_lockout_seconds: 30,
+            max_lockout_seconds: 3600,
+        }
+    }
+
+    #[test]
+    fn failures_below_the_threshold_are_not_locked() {
+        let settings = test_settings();
+
+        for v in 0..settings.max_attempts {
+            assert_none!(lockout_duration(v, &settings));
+        }
+    }
+
+    #[test]
+    fn lockout_doubles_after_each_failure_past_the_threshold() {
+        let settings = test_settings();
+
+        assert_some_eq!(lockout_duration(5, &settings), Duration::seconds(30));
+        assert_some_eq!(lockout_duration(6, &settings), Duration::seconds(60));
+        assert_some_eq!(lockout_duration(7, &settings), Duration::seconds(120));
+    }
+
+    #[test]
+    fn lockout_is_capped_at_the_maximum() {
+        let settings = test_settings();
+
+        assert_some_eq!(lockout_duration(100, &settings), Duration::seconds(3600));
+    }
+}
@@ -0,0 +1,58 @@
+use validator::Validate;
+
+use crate::errors::AppError;
+use crate::features::auth::models::MagicLinkFormData;
+use crate::utils::randomizer::generate_random_string;
+
+use super::{normalize_email, MAX_EMAIL_LENGTH};
+
+const MAGIC_LINK_TOKEN_LENGTH: usize = 32;
+const MAGIC_LINK_NONCE_LENGTH: usize = 32;
+/// Magic links stand in for the password entirely, so they expire sooner than reset links.
+pub const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+/// Set on the browser that asked for a link. A forwarded link is useless without it.
+pub const MAGIC_LINK_NONCE_COOKIE: &str = "magic_link_nonce";
+
+#[derive(Validate)]
+pub struct MagicLinkRequest {
+    #[validate(email(code = "invalid_email"), length(max = "MAX_EMAIL_LENGTH", code = "invalid_email"))]
+    pub email : String
+}
+
+impl TryFrom<MagicLinkFormData> for MagicLinkRequest {
+    type Error = AppError;
+
+    fn try_from(value: MagicLinkFormData) -> Result<Self, Self::Error> {
+        let request = MagicLinkRequest { email : normalize_email(&value.email) };
+
+        request.validate()?;
+
+        Ok(request)
+    }
+}
+
+pub fn generate_magic_link_token() -> String {
+    generate_random_string(MAGIC_LINK_TOKEN_LENGTH)
+}
+
+pub fn generate_magic_link_nonce() -> String {
+    generate_random_string(MAGIC_LINK_NONCE_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::features::auth::models::MagicLinkFormData;
+
+    use super::MagicLinkRequest;
+
+    #[test]
+    fn the_email_is_trimmed_and_validated() {
+        let request = MagicLinkRequest::try_from(MagicLinkFormData { email : " ada@example.com ".into() });
+
+        assert_ok!(&request);
+        assert_eq!("ada@example.com", request.unwrap().email);
+        assert_err!(MagicLinkRequest::try_from(MagicLinkFormData { email : "ada".into() }).map(|_| ()));
+    }
+}
\ No newline at end of file
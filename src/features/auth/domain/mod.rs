@@ -1,3 +1,13 @@
+mod account_status;
 mod credentials;
+mod lockout;
+mod magic_link;
+mod password_policy;
+mod password_reset;
 
-pub use credentials::*;
\ No newline at end of file
+pub use account_status::*;
+pub use credentials::*;
+pub use lockout::*;
+pub use magic_link::*;
+pub use password_policy::*;
+pub use password_reset::*;
\ No newline at end of file
@@ -0,0 +1,258 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use unicode_segmentation::UnicodeSegmentation;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::{configurations::PasswordPolicySettings, errors::AppError};
+
+use super::Credentials;
+
+/// Rules a new password must pass. Only applied when a password is set, so passwords chosen
+/// under an older policy keep working at login.
+pub struct PasswordPolicy {
+    settings: PasswordPolicySettings,
+    breached_passwords: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn from_settings(settings: PasswordPolicySettings) -> Result<Self, AppError> {
+        let contents = std::fs::read_to_string(&settings.breached_passwords_path).map_err(|e| {
+            AppError::UnexpectedError(format!(
+                "failed to read breached passwords {}: {}",
+                settings.breached_passwords_path, e
+            ))
+        })?;
+
+        let breached_passwords = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase)
+            .collect();
+
+        Ok(Self {
+            settings,
+            breached_passwords,
+        })
+    }
+
+    pub fn check(&self, credentials: &Credentials) -> Result<(), AppError> {
+        let violations = self.violations(&credentials.password, &credentials.username);
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        let mut errors = ValidationErrors::new();
+        for (code, message) in violations {
+            errors.add(
+                "password",
+                ValidationError::new(code).with_message(Cow::Borrowed(message)),
+            );
+        }
+
+        Err(errors.into())
+    }
+
+    fn violations(&self, password: &str, username: &str) -> Vec<(&'static str, &'static str)> {
+        let settings = &self.settings;
+        let length = password.graphemes(true).count();
+        let mut violations = Vec::new();
+
+        if length < settings.min_length {
+            violations.push(("password_too_short", "Password is too short."));
+        }
+
+        if length > settings.max_length {
+            violations.push(("password_too_long", "Password is too long."));
+        }
+
+        let character_classes = [
+            (
+                settings.require_lowercase,
+                password.chars().any(char::is_lowercase),
+                ("password_missing_lowercase", "Password needs a lowercase letter."),
+            ),
+            (
+                settings.require_uppercase,
+                password.chars().any(char::is_uppercase),
+                ("password_missing_uppercase", "Password needs an uppercase letter."),
+            ),
+            (
+                settings.require_digit,
+                password.chars().any(|c| c.is_ascii_digit()),
+                ("password_missing_digit", "Password needs a digit."),
+            ),
+            (
+                settings.require_symbol,
+                password.chars().any(|c| !c.is_alphanumeric()),
+                ("password_missing_symbol", "Password needs a symbol."),
+            ),
+        ];
+
+        for (required, present, violation) in character_classes {
+            if required && !present {
+                violations.push(violation);
+            }
+        }
+
+        if self.breached_passwords.contains(&password.to_lowercase()) {
+            violations.push((
+                "password_breached",
+                "Password appears in a list of breached passwords.",
+            ));
+        }
+
+        if estimate_strength(password, username) < settings.min_strength {
+            violations.push(("password_too_weak", "Password is too easy to guess."));
+        }
+
+        violations
+    }
+}
+
+/// A rough guessability score from 0 (trivial) to 4 (strong). Entropy is estimated from the
+/// character pool and length, ignoring repeated characters and runs like "abc" or "321", and a
+/// password built around the username is capped at 1.
+pub fn estimate_strength(password: &str, username: &str) -> u8 {
+    let chars = password.chars().collect::<Vec<_>>();
+
+    let pool = [
+        (chars.iter().any(|c| c.is_ascii_lowercase()), 26),
+        (chars.iter().any(|c| c.is_ascii_uppercase()), 26),
+        (chars.iter().any(|c| c.is_ascii_digit()), 10),
+        (chars.iter().any(|c| c.is_ascii() && !c.is_ascii_alphanumeric()), 33),
+        (chars.iter().any(|c| !c.is_ascii()), 100),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum::<u32>();
+
+    let effective_length = chars
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| {
+            let step = |a: char, b: char| b as i64 - a as i64;
+            let is_repeat = *i >= 1 && chars[i - 1] == **c;
+            let is_run = *i >= 2
+                && step(chars[i - 1], **c).abs() == 1
+                && step(chars[i - 2], chars[i - 1]) == step(chars[i - 1], **c);
+
+            !is_repeat && !is_run
+        })
+        .count();
+
+    let bits = effective_length as f64 * (pool.max(1) as f64).log2();
+
+    let score = match bits {
+        b if b < 28.0 => 0,
+        b if b < 36.0 => 1,
+        b if b < 50.0 => 2,
+        b if b < 70.0 => 3,
+        _ => 4,
+    };
+
+    let username = username.trim().to_lowercase();
+    if username.chars().count() >= 3 && password.to_lowercase().contains(&username) {
+        return score.min(1);
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::configurations::PasswordPolicySettings;
+    use crate::features::auth::domain::Credentials;
+
+    use super::{estimate_strength, PasswordPolicy};
+
+    fn test_settings() -> PasswordPolicySettings {
+        PasswordPolicySettings {
+            min_length: 10,
+            max_length: 128,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            min_strength: 2,
+            breached_passwords_path: "configurations/breached_passwords.txt".into(),
+        }
+    }
+
+    fn credentials(password: &str) -> Credentials {
+        Credentials {
+            username: "tester".into(),
+            password: password.into(),
+            email: None,
+        }
+    }
+
+    fn violation_codes(policy: &PasswordPolicy, password: &str) -> Vec<&'static str> {
+        policy
+            .violations(password, "tester")
+            .into_iter()
+            .map(|(code, _)| code)
+            .collect()
+    }
+
+    #[test]
+    fn a_long_passphrase_is_accepted() {
+        let policy = PasswordPolicy::from_settings(test_settings()).unwrap();
+
+        assert_ok!(policy.check(&credentials("violet kettle marching under rain")));
+    }
+
+    #[test]
+    fn length_limits_are_enforced() {
+        let policy = PasswordPolicy::from_settings(test_settings()).unwrap();
+
+        assert!(violation_codes(&policy, "Xk9#").contains(&"password_too_short"));
+        assert!(violation_codes(&policy, &"Xk9#".repeat(40)).contains(&"password_too_long"));
+    }
+
+    #[test]
+    fn breached_passwords_are_rejected_regardless_of_case() {
+        let policy = PasswordPolicy::from_settings(test_settings()).unwrap();
+
+        assert!(violation_codes(&policy, "PASSWORDPASSWORD").contains(&"password_breached"));
+        assert_err!(policy.check(&credentials("correct horse battery staple")));
+    }
+
+    #[test]
+    fn required_character_classes_are_reported_separately() {
+        let settings = PasswordPolicySettings {
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..test_settings()
+        };
+        let policy = PasswordPolicy::from_settings(settings).unwrap();
+
+        let codes = violation_codes(&policy, "violet kettle marching");
+
+        assert!(codes.contains(&"password_missing_uppercase"));
+        assert!(codes.contains(&"password_missing_digit"));
+        assert!(!codes.contains(&"password_missing_symbol"));
+        assert!(!codes.contains(&"password_missing_lowercase"));
+    }
+
+    #[test]
+    fn repeats_and_runs_are_weak() {
+        assert_eq!(0, estimate_strength("aaaaaaaaaaaaaaaa", "tester"));
+        assert_eq!(0, estimate_strength("abcdefghijklmnop", "tester"));
+        assert_eq!(0, estimate_strength("9876543210", "tester"));
+    }
+
+    #[test]
+    fn random_passwords_are_strong() {
+        assert_eq!(4, estimate_strength("q7Vx2mLp9TzR", "tester"));
+    }
+
+    #[test]
+    fn passwords_containing_the_username_are_weak() {
+        assert!(estimate_strength("Tester-2024-Rocks!", "tester") <= 1);
+    }
+}
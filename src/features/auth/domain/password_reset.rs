@@ -0,0 +1,9 @@
+use crate::utils::randomizer::generate_random_string;
+
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 32;
+/// Reset links are short lived because they bypass the current password.
+pub const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+pub fn generate_password_reset_token() -> String {
+    generate_random_string(PASSWORD_RESET_TOKEN_LENGTH)
+}
\ No newline at end of file
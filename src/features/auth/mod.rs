@@ -1,4 +1,4 @@
 pub mod repository;
 pub mod controller;
 pub mod domain;
-mod models;
\ No newline at end of file
+pub(crate) mod models;
\ No newline at end of file
@@ -1,19 +1,47 @@
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use sqlx::FromRow;
 use uuid::Uuid;
 
+/// `username` also accepts an email address, and can be sent as `email` instead.
 #[derive(Deserialize)]
 pub struct LoginFormData {
+    #[serde(alias = "email")]
     pub username : String,
-    pub password : String
+    pub password : String,
+    pub device_label : Option<String>
 }
 
 #[derive(Deserialize)]
 pub struct RegisterFormData {
     pub username : String,
+    pub password : String,
+    pub email : Option<String>,
+    pub device_label : Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetFormData {
+    pub token : String,
     pub password : String
 }
 
+#[derive(Deserialize)]
+pub struct MagicLinkFormData {
+    pub email : String
+}
+
+#[derive(Deserialize)]
+pub struct ConsumeMagicLinkFormData {
+    pub token : String,
+    pub device_label : Option<String>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct MagicLinkData {
+    pub user_id : Uuid,
+    pub expires_at : DateTime<Utc>
+}
 
 #[derive(FromRow, Deserialize)]
 pub struct UserData{
@@ -22,5 +50,56 @@ pub struct UserData{
 
 #[derive(FromRow, Deserialize)]
 pub struct UserTokenData {
-    pub user_id : Uuid
+    pub id : Uuid,
+    pub user_id : Uuid,
+    pub family_id : Uuid,
+    pub rotated_at : Option<DateTime<Utc>>
+}
+
+pub struct SessionMetadata {
+    pub user_agent : Option<String>,
+    pub ip : String,
+    pub device_label : Option<String>
+}
+
+#[derive(FromRow, Deserialize, Clone)]
+pub struct SessionData {
+    pub id : Uuid,
+    pub user_agent : Option<String>,
+    pub ip : Option<String>,
+    pub device_label : Option<String>,
+    pub created_at : DateTime<Utc>,
+    pub last_used_at : DateTime<Utc>,
+    pub expires_at : DateTime<Utc>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct SessionIdData {
+    pub id : Uuid
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct LockoutData {
+    pub locked_until : DateTime<Utc>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct FailedLoginData {
+    pub failed_count : i32
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct RoleData {
+    pub name : String
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct RoleIdData {
+    pub id : Uuid
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct PasswordResetData {
+    pub user_id : Uuid,
+    pub username : String
 }
\ No newline at end of file
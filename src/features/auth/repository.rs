@@ -1,18 +1,37 @@
+use crate::configurations::LoginThrottleSettings;
 use crate::db::{DbContext, TxContext};
 use crate::errors::AppError;
-use crate::features::auth::domain::Credentials;
+use crate::features::auth::domain::{
+    lockout_duration, normalize_username, AccountState, AccountStatus, AttemptKey, Credentials,
+    LoginCredentials,
+};
 use crate::utils::password_hasher::PwdHasher;
+use crate::utils::token_hash::hash_token;
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use sqlx::postgres::PgRow;
 use sqlx::FromRow;
 use uuid::{NoContext, Timestamp, Uuid};
 
-use super::models::{UserData, UserTokenData};
+use super::models::{
+    FailedLoginData, LockoutData, MagicLinkData, PasswordResetData, RoleData, RoleIdData,
+    SessionData, SessionIdData, SessionMetadata, UserData, UserTokenData,
+};
+
+#[derive(Deserialize, FromRow)]
+pub struct AccountStateData {
+    status: String,
+    status_reason: Option<String>,
+    status_until: Option<DateTime<Utc>>,
+}
 
 #[derive(Deserialize, FromRow)]
 struct ValidationResult {
     id: Uuid,
     password: String,
+    status: String,
+    status_reason: Option<String>,
+    status_until: Option<DateTime<Utc>>,
 }
 
 #[tracing::instrument(
@@ -20,29 +39,82 @@ struct ValidationResult {
     skip(credentials, db, pwd_hasher)
 )]
 pub async fn validate_credentials(
-    credentials: &Credentials,
+    credentials: &LoginCredentials,
     db: &impl DbContext,
     pwd_hasher: &impl PwdHasher,
 ) -> Result<Uuid, AppError> {
-    let query = sqlx::query_as(
-        r#"
-            SELECT id, password FROM users WHERE username = $1
-        "#,
-    )
-    .bind(credentials.username.to_string());
+    let sql = match credentials.is_email() {
+        true => {
+            r#"
+                SELECT id, password, status, status_reason, status_until FROM users
+                WHERE lower(email) = lower($1)
+            "#
+        }
+        false => {
+            r#"
+                SELECT id, password, status, status_reason, status_until FROM users
+                WHERE lower(username) = lower($1)
+            "#
+        }
+    };
+    let query = sqlx::query_as(sql).bind(credentials.identifier.to_string());
 
     let result = db.fetch_optional::<ValidationResult>(query).await?;
 
-    let (id, password) = match result {
-        Some(data) => (data.id, data.password),
+    let (id, password, state) = match result {
+        Some(data) => (
+            data.id,
+            data.password,
+            account_state(AccountStateData {
+                status: data.status,
+                status_reason: data.status_reason,
+                status_until: data.status_until,
+            }),
+        ),
         None => return Err(AppError::UnauthorizedError("Invalid Username".into())),
     };
 
     pwd_hasher.verify_password(&credentials.password, &password).await?;
 
+    // Checked after the password so the account state is only revealed to its owner.
+    state.ensure_active()?;
+
+    if pwd_hasher.needs_rehash(&password) {
+        // The login already succeeded, so a failed upgrade is retried on the next one.
+        if let Err(e) = rehash_password(id, &credentials.password, &password, db, pwd_hasher).await {
+            tracing::warn!(error = %e, user_id = %id, "Failed to rehash password.");
+        }
+    }
+
     Ok(id)
 }
 
+#[tracing::instrument(name = "Rehashing User password", skip(password, old_hash, db, pwd_hasher))]
+async fn rehash_password(
+    user_id: Uuid,
+    password: &str,
+    old_hash: &str,
+    db: &impl DbContext,
+    pwd_hasher: &impl PwdHasher,
+) -> Result<(), AppError> {
+    let new_hash = pwd_hasher.hash_password(password).await?;
+
+    // Skips the update if the password changed since it was verified.
+    let query = sqlx::query!(
+        r#"
+            UPDATE users SET password = $2 WHERE id = $1 AND password = $3
+        "#,
+        user_id,
+        new_hash,
+        old_hash
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// The registration email is not stored here; it is set once the user confirms it.
 #[tracing::instrument(name = "Creating User", skip(credentials, db, pwd_hasher))]
 pub async fn create_user(
     credentials: &Credentials,
@@ -86,40 +158,204 @@ pub async fn get_user_by_id(user_id: Uuid, db: &impl DbContext) -> Result<UserDa
     }
 }
 
-#[tracing::instrument(name = "Fetching User by Id", skip(rt, db))]
+#[tracing::instrument(name = "Fetching User token by token", skip(rt, db))]
 pub async fn get_user_tokens_by_token(
     rt: &str,
     db: &impl DbContext,
 ) -> Result<Option<UserTokenData>, AppError> {
     let query = sqlx::query_as(
         r#"
-            SELECT user_id FROM user_tokens WHERE refresh_token = $1
+            SELECT id, user_id, family_id, rotated_at FROM user_tokens WHERE token_hash = $1
         "#,
     )
-    .bind(rt.to_string());
+    .bind(hash_token(rt));
 
     let result = db.fetch_optional::<UserTokenData>(query).await?;
 
     Ok(result)
 }
 
-#[tracing::instrument(name = "Adding Refresh token", skip(token, user_id, db))]
+#[tracing::instrument(name = "Adding Refresh token", skip(token, user_id, metadata, db))]
 pub async fn add_refresh_token_by_user_id(
     token: &str,
     user_id: Uuid,
+    expires_at: DateTime<Utc>,
+    metadata: &SessionMetadata,
     db: &impl DbContext,
 ) -> Result<(), AppError> {
     let id = Uuid::new_v7(Timestamp::now(NoContext));
 
     let query = sqlx::query!(
         r#"
-            INSERT INTO user_tokens (id, refresh_token, user_id, created_at)
+            INSERT INTO user_tokens
+            (id, token_hash, user_id, created_at, user_agent, ip, device_label, last_used_at, expires_at, family_id)
             VALUES
-            ($1, $2, $3, now())
+            ($1, $2, $3, now(), $4, $5, $6, now(), $7, $1)
         "#,
         id,
-        token,
-        user_id
+        hash_token(token),
+        user_id,
+        metadata.user_agent,
+        metadata.ip,
+        metadata.device_label,
+        expires_at
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Marks `parent` as rotated and adds `token` to its family. Returns `false` when `parent` was
+/// already rotated, which means the presented token is being reused.
+#[tracing::instrument(name = "Rotating Refresh token", skip(parent, token, metadata, db))]
+pub async fn rotate_refresh_token(
+    parent: &UserTokenData,
+    token: &str,
+    expires_at: DateTime<Utc>,
+    metadata: &SessionMetadata,
+    db: &impl DbContext,
+) -> Result<bool, AppError> {
+    let mut tx = db.get_transaction().await?;
+
+    let query = sqlx::query(
+        r#"
+            UPDATE user_tokens SET rotated_at = now()
+            WHERE id = $1 AND rotated_at IS NULL
+            RETURNING id
+        "#,
+    )
+    .bind(parent.id);
+
+    if tx.fetch_optional(query).await?.is_none() {
+        return Ok(false);
+    }
+
+    let id = Uuid::new_v7(Timestamp::now(NoContext));
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO user_tokens
+            (id, token_hash, user_id, created_at, user_agent, ip, device_label, last_used_at, expires_at, family_id, parent_id)
+            SELECT $1, $2, user_id, now(), $3, $4, device_label, now(), $5, family_id, id
+            FROM user_tokens WHERE id = $6
+        "#,
+        id,
+        hash_token(token),
+        metadata.user_agent,
+        metadata.ip,
+        expires_at,
+        parent.id
+    );
+
+    tx.execute_query(query).await?;
+    tx.execute_transaction().await?;
+
+    Ok(true)
+}
+
+#[tracing::instrument(name = "Deleting refresh token family", skip(family_id, db))]
+pub async fn delete_refresh_token_family(family_id: Uuid, db: &impl DbContext) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM user_tokens WHERE family_id = $1
+        "#,
+        family_id
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Recording security incident", skip(user_id, family_id, metadata, db))]
+pub async fn add_security_incident(
+    kind: &str,
+    user_id: Uuid,
+    family_id: Option<Uuid>,
+    metadata: &SessionMetadata,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let id = Uuid::new_v7(Timestamp::now(NoContext));
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO security_incidents (id, kind, user_id, family_id, ip, user_agent, created_at)
+            VALUES
+            ($1, $2, $3, $4, $5, $6, now())
+        "#,
+        id,
+        kind,
+        user_id,
+        family_id,
+        metadata.ip,
+        metadata.user_agent
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetching sessions by user id", skip(user_id, db))]
+pub async fn get_sessions_by_user_id(
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Vec<SessionData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT
+                t.family_id AS id,
+                t.user_agent,
+                t.ip,
+                t.device_label,
+                (SELECT min(f.created_at) FROM user_tokens f WHERE f.family_id = t.family_id) AS created_at,
+                t.last_used_at,
+                t.expires_at
+            FROM user_tokens t
+            WHERE t.user_id = $1 AND t.rotated_at IS NULL AND t.expires_at > now()
+            ORDER BY t.last_used_at DESC
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_all::<SessionData>(query).await?;
+
+    Ok(result)
+}
+
+#[tracing::instrument(name = "Deleting session by id", skip(session_id, user_id, db))]
+pub async fn delete_session_by_id(
+    session_id: Uuid,
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Uuid, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            DELETE FROM user_tokens WHERE family_id = $1 AND user_id = $2 RETURNING family_id AS id
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id);
+
+    match db.fetch_all::<SessionIdData>(query).await?.pop() {
+        Some(data) => Ok(data.id),
+        None => Err(AppError::NotFoundError("Session was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Deleting other sessions by user id", skip(user_id, current_session_id, db))]
+pub async fn delete_other_sessions_by_user_id(
+    user_id: Uuid,
+    current_session_id: Option<Uuid>,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM user_tokens WHERE user_id = $1 AND ($2::uuid IS NULL OR family_id <> $2)
+        "#,
+        user_id,
+        current_session_id
     );
 
     db.execute_query(query).await?;
@@ -134,9 +370,9 @@ pub async fn delete_refresh_token_by_token(
 ) -> Result<(), AppError> {
     let query = sqlx::query!(
         r#"
-            DELETE FROM user_tokens WHERE refresh_token = $1
+            DELETE FROM user_tokens WHERE token_hash = $1
         "#,
-        token
+        hash_token(token)
     );
 
     db.execute_query(query).await?;
@@ -161,6 +397,106 @@ pub async fn delete_all_refresh_token_by_user_id(
     Ok(())
 }
 
+#[tracing::instrument(name = "Deleting all refresh tokens by User id", skip(user_id, tx))]
+pub async fn delete_all_refresh_token_by_user_id_tx(
+    user_id: Uuid,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM user_tokens WHERE user_id = $1
+        "#,
+        user_id
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetching login lockout", skip(key, db))]
+pub async fn get_locked_until(
+    key: &AttemptKey,
+    db: &impl DbContext,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT locked_until FROM login_attempts
+            WHERE key_type = $1 AND key_value = $2 AND locked_until > now()
+        "#,
+    )
+    .bind(key.key_type())
+    .bind(key.key_value());
+
+    let result = db.fetch_optional::<LockoutData>(query).await?;
+
+    Ok(result.map(|data| data.locked_until))
+}
+
+#[tracing::instrument(name = "Recording failed login", skip(key, settings, db))]
+pub async fn record_failed_login(
+    key: &AttemptKey,
+    settings: &LoginThrottleSettings,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query_as(
+        r#"
+            INSERT INTO login_attempts (key_type, key_value, failed_count, last_failed_at)
+            VALUES ($1, $2, 1, now())
+            ON CONFLICT (key_type, key_value) DO UPDATE SET
+                failed_count = CASE
+                    WHEN login_attempts.last_failed_at < now() - make_interval(secs => $3)
+                    THEN 1
+                    ELSE login_attempts.failed_count + 1
+                END,
+                last_failed_at = now()
+            RETURNING failed_count
+        "#,
+    )
+    .bind(key.key_type())
+    .bind(key.key_value())
+    .bind(settings.attempt_window_seconds as f64);
+
+    let failed_count = match db.fetch_optional::<FailedLoginData>(query).await? {
+        Some(data) => data.failed_count,
+        None => return Ok(()),
+    };
+
+    let lockout = match lockout_duration(failed_count as u32, settings) {
+        Some(duration) => duration,
+        None => return Ok(()),
+    };
+
+    let query = sqlx::query!(
+        r#"
+            UPDATE login_attempts SET locked_until = $3
+            WHERE key_type = $1 AND key_value = $2
+        "#,
+        key.key_type(),
+        key.key_value(),
+        Utc::now() + lockout
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Clearing failed logins", skip(key, db))]
+pub async fn clear_failed_logins(key: &AttemptKey, db: &impl DbContext) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM login_attempts WHERE key_type = $1 AND key_value = $2
+        "#,
+        key.key_type(),
+        key.key_value()
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
 #[tracing::instrument(
     name = "Verifying User by Id",
     skip(user_id, tx)
@@ -181,6 +517,298 @@ pub async fn verify_user_by_id_tx(
     Ok(result)
 }
 
+#[tracing::instrument(name = "Fetching User by username", skip(username, db))]
+pub async fn get_user_by_username(username: &str, db: &impl DbContext) -> Result<UserData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id FROM users WHERE lower(username) = lower($1)
+        "#,
+    )
+    .bind(normalize_username(username));
+
+    let result = db.fetch_optional::<UserData>(query).await?;
+
+    match result {
+        Some(data) => Ok(data),
+        None => Err(AppError::NotFoundError("User was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Fetching User roles", skip(user_id, db))]
+pub async fn get_roles_by_user_id(user_id: Uuid, db: &impl DbContext) -> Result<Vec<String>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT r.name FROM user_roles ur
+            INNER JOIN roles r ON r.id = ur.role_id
+            WHERE ur.user_id = $1
+            ORDER BY r.name
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_all::<RoleData>(query).await?;
+
+    Ok(result.into_iter().map(|r| r.name).collect())
+}
+
+#[tracing::instrument(name = "Fetching Role by name", skip(tx))]
+async fn get_role_id_by_name_tx(name: &str, tx: &mut impl TxContext) -> Result<Uuid, AppError> {
+    let query = sqlx::query(
+        r#"
+            SELECT id FROM roles WHERE name = $1
+        "#,
+    )
+    .bind(name.to_string());
+
+    match tx.fetch_optional(query).await? {
+        Some(row) => Ok(RoleIdData::from_row(&row)?.id),
+        None => Err(AppError::NotFoundError("Role was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Granting User role", skip(user_id, db))]
+pub async fn grant_role_to_user(user_id: Uuid, role: &str, db: &impl DbContext) -> Result<(), AppError> {
+    let mut tx = db.get_transaction().await?;
+
+    grant_role_to_user_tx(user_id, role, &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Granting User role", skip(user_id, tx))]
+pub async fn grant_role_to_user_tx(
+    user_id: Uuid,
+    role: &str,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let role_id = get_role_id_by_name_tx(role, tx).await?;
+    if verify_user_by_id_tx(user_id, tx).await?.is_none() {
+        return Err(AppError::NotFoundError("User was not found".into()));
+    }
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO user_roles (user_id, role_id, granted_at)
+            VALUES ($1, $2, now())
+            ON CONFLICT (user_id, role_id) DO NOTHING
+        "#,
+        user_id,
+        role_id
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Revoking User role", skip(user_id, db))]
+pub async fn revoke_role_from_user(user_id: Uuid, role: &str, db: &impl DbContext) -> Result<(), AppError> {
+    let mut tx = db.get_transaction().await?;
+
+    revoke_role_from_user_tx(user_id, role, &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Revoking User role", skip(user_id, tx))]
+pub async fn revoke_role_from_user_tx(
+    user_id: Uuid,
+    role: &str,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let role_id = get_role_id_by_name_tx(role, tx).await?;
+
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2
+        "#,
+        user_id,
+        role_id
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Replaces any reset the user still has pending, so only the latest link works.
+#[tracing::instrument(name = "Adding password reset", skip(token, user_id, expires_at, tx))]
+pub async fn add_password_reset(
+    token: &str,
+    user_id: Uuid,
+    expires_at: DateTime<Utc>,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM password_resets WHERE user_id = $1
+        "#,
+        user_id
+    );
+
+    tx.execute_query(query).await?;
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO password_resets (token_hash, user_id, created_at, expires_at)
+            VALUES ($1, $2, now(), $3)
+        "#,
+        hash_token(token),
+        user_id,
+        expires_at
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetching password reset", skip(token, db))]
+pub async fn get_password_reset(token: &str, db: &impl DbContext) -> Result<PasswordResetData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT r.user_id, u.username FROM password_resets r
+            JOIN users u ON u.id = r.user_id
+            WHERE r.token_hash = $1 AND r.expires_at > now()
+        "#,
+    )
+    .bind(hash_token(token));
+
+    match db.fetch_optional::<PasswordResetData>(query).await? {
+        Some(data) => Ok(data),
+        None => Err(AppError::UnauthorizedError("Invalid or expired token".into())),
+    }
+}
+
+/// Fails if another request already used the token.
+#[tracing::instrument(name = "Deleting password reset", skip(token, db))]
+pub async fn delete_password_reset(token: &str, db: &impl DbContext) -> Result<(), AppError> {
+    let query = sqlx::query_as(
+        r#"
+            DELETE FROM password_resets WHERE token_hash = $1 RETURNING user_id AS id
+        "#,
+    )
+    .bind(hash_token(token));
+
+    match db.fetch_all::<UserData>(query).await?.pop() {
+        Some(_) => Ok(()),
+        None => Err(AppError::UnauthorizedError("Invalid or expired token".into())),
+    }
+}
+
+#[tracing::instrument(name = "Fetching User id by email", skip(email, db))]
+pub async fn get_user_id_by_email(email: &str, db: &impl DbContext) -> Result<Option<Uuid>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id FROM users WHERE lower(email) = lower($1)
+        "#,
+    )
+    .bind(email.to_string());
+
+    let result = db.fetch_optional::<(Uuid,)>(query).await?;
+
+    Ok(result.map(|(id,)| id))
+}
+
+#[tracing::instrument(name = "Fetching Username by email", skip(email, db))]
+pub async fn get_username_by_email(email: &str, db: &impl DbContext) -> Result<Option<String>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT username FROM users WHERE lower(email) = lower($1)
+        "#,
+    )
+    .bind(email.to_string());
+
+    let result = db.fetch_optional::<(String,)>(query).await?;
+
+    Ok(result.map(|(username,)| username))
+}
+
+/// Replaces any link the user requested before, so only the latest email works.
+#[tracing::instrument(name = "Adding magic link", skip(token, nonce, user_id, expires_at, db))]
+pub async fn add_magic_link(
+    token: &str,
+    nonce: &str,
+    user_id: Uuid,
+    expires_at: DateTime<Utc>,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM magic_links WHERE user_id = $1
+        "#,
+        user_id
+    );
+
+    db.execute_query(query).await?;
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO magic_links (token_hash, user_id, nonce_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, now(), $4)
+        "#,
+        hash_token(token),
+        user_id,
+        hash_token(nonce),
+        expires_at
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Deletes the link as it is read, so it works at most once even under concurrent requests. Only
+/// a request carrying the nonce of the browser that asked for the link can take it.
+#[tracing::instrument(name = "Taking magic link", skip(token, nonce, db))]
+pub async fn take_magic_link(
+    token: &str,
+    nonce: &str,
+    db: &impl DbContext,
+) -> Result<MagicLinkData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            DELETE FROM magic_links WHERE token_hash = $1 AND nonce_hash = $2
+            RETURNING user_id, expires_at
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(hash_token(nonce));
+
+    match db.fetch_all::<MagicLinkData>(query).await?.pop() {
+        Some(data) if data.expires_at > Utc::now() => Ok(data),
+        _ => Err(AppError::UnauthorizedError("Invalid or expired token".into())),
+    }
+}
+
+/// `None` once the user has been deleted.
+#[tracing::instrument(name = "Fetching account state", skip(user_id, db))]
+pub async fn get_account_state(
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Option<AccountState>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT status, status_reason, status_until FROM users WHERE id = $1
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_optional::<AccountStateData>(query).await?;
+
+    Ok(result.map(account_state))
+}
+
+fn account_state(data: AccountStateData) -> AccountState {
+    AccountState {
+        status: AccountStatus::parse(&data.status),
+        reason: data.status_reason,
+        until: data.status_until,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use claims::{assert_err, assert_ok};
@@ -190,15 +818,15 @@ mod tests {
     use crate::{
         db::MockDbContext,
         features::auth::{
-            domain::Credentials,
+            domain::LoginCredentials,
             repository::{validate_credentials, ValidationResult},
         },
         utils::{password_hasher::MockPwdHasher, randomizer::generate_random_string},
     };
 
-    fn generate_test_user() -> Credentials {
-        Credentials {
-            username: generate_random_string(12),
+    fn generate_test_user() -> LoginCredentials {
+        LoginCredentials {
+            identifier: generate_random_string(12),
             password: generate_random_string(12),
         }
     }
@@ -214,6 +842,9 @@ mod tests {
             Ok(Some(ValidationResult {
                 id: Uuid::new_v4(),
                 password: Password(1..12).fake(),
+                status: "active".into(),
+                status_reason: None,
+                status_until: None,
             }))
         });
 
@@ -222,11 +853,73 @@ mod tests {
             .times(1)
             .returning(|_, _| Ok(()));
 
+        pwd_mock.expect_needs_rehash().returning(|_| false);
+
         let result = validate_credentials(&credentials, &db_mock, &pwd_mock).await;
 
         assert_ok!(result);
     }
 
+    #[tokio::test]
+    async fn an_outdated_hash_is_upgraded_after_a_valid_login() {
+        let credentials = generate_test_user();
+
+        let mut db_mock = MockDbContext::new();
+        let mut pwd_mock = MockPwdHasher::new();
+
+        db_mock.expect_fetch_optional().times(1).returning(|_| {
+            Ok(Some(ValidationResult {
+                id: Uuid::new_v4(),
+                password: Password(1..12).fake(),
+                status: "active".into(),
+                status_reason: None,
+                status_until: None,
+            }))
+        });
+        db_mock.expect_execute_query().times(1).returning(|_| Ok(()));
+
+        pwd_mock
+            .expect_verify_password()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        pwd_mock.expect_needs_rehash().times(1).returning(|_| true);
+        pwd_mock
+            .expect_hash_password()
+            .times(1)
+            .returning(|_| Ok("new hash".into()));
+
+        let result = validate_credentials(&credentials, &db_mock, &pwd_mock).await;
+
+        assert_ok!(result);
+    }
+
+    #[tokio::test]
+    async fn a_disabled_account_is_rejected_after_a_valid_password() {
+        let credentials = generate_test_user();
+
+        let mut db_mock = MockDbContext::new();
+        let mut pwd_mock = MockPwdHasher::new();
+
+        db_mock.expect_fetch_optional().times(1).returning(|_| {
+            Ok(Some(ValidationResult {
+                id: Uuid::new_v4(),
+                password: Password(1..12).fake(),
+                status: "disabled".into(),
+                status_reason: None,
+                status_until: None,
+            }))
+        });
+
+        pwd_mock
+            .expect_verify_password()
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let result = validate_credentials(&credentials, &db_mock, &pwd_mock).await;
+
+        assert!(matches!(result, Err(crate::errors::AppError::AccountInactiveError { .. })));
+    }
+
     #[tokio::test]
     async fn an_invalid_username_is_rejected() {
         let credentials = generate_test_user();
@@ -263,6 +956,9 @@ mod tests {
                 Ok(Some(ValidationResult {
                     id: Uuid::new_v4(),
                     password: Password(1..12).fake(),
+                    status: "active".into(),
+                    status_reason: None,
+                    status_until: None,
                 }))
             });
 
@@ -294,9 +990,9 @@ mod tests {
             .expect_fetch_optional::<ValidationResult>()
             .times(1)
             .returning(|_| {
-                return Err(crate::errors::AppError::DbError(
+                Err(crate::errors::AppError::DbError(
                     sqlx::error::Error::ColumnNotFound("Error".into()),
-                ));
+                ))
             });
 
         pwd_mock
@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+
+use crate::app_state::AppState;
+
+#[tracing::instrument(
+    name = "Fetching JSON Web Key Set",
+    skip(app_state)
+)]
+pub async fn jwks(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (StatusCode::OK, Json(app_state.jwt_keys.jwk_set().clone())).into_response()
+}
@@ -1,2 +1,8 @@
 pub mod health_check;
-pub mod auth;
\ No newline at end of file
+pub mod admin;
+pub mod api_keys;
+pub mod auth;
+pub mod jwks;
+pub mod organizations;
+pub mod todos;
+pub mod users;
\ No newline at end of file
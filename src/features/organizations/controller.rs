@@ -0,0 +1,361 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    db::{DbContext, TxContext},
+    errors::AppError,
+    features::auth::repository::get_user_by_username,
+    utils::{
+        scopes::{OrganizationsRead, OrganizationsWrite, RequireScopes, RequiredScopes},
+        tenant::Tenant,
+    },
+};
+
+use super::{
+    domain::{NewOrganization, OrganizationRole, INVITATION_TTL_DAYS},
+    models::{CreateOrganizationFormData, InviteMemberFormData, SetMemberRoleFormData},
+    repository::{
+        accept_invitation, add_invitation, create_organization, delete_invitation,
+        get_invitations_by_user_id, get_member_role_tx, get_members, get_organization,
+        get_organizations_by_user_id, lock_owners, remove_member, set_member_role,
+    },
+};
+
+pub fn organization_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_organizations).post(add_organization))
+        .route("/current", get(get_current_organization))
+        .route("/current/members", get(get_current_members))
+        .route(
+            "/current/members/:user_id",
+            put(change_member_role).delete(delete_member),
+        )
+        .route("/current/invitations", post(invite_member))
+        .route("/invitations", get(get_invitations))
+        .route("/invitations/:id", delete(decline_invitation))
+        .route("/invitations/:id/accept", post(accept_member_invitation))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OrganizationResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// The current user's role in the organization.
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MemberResponse {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InvitationResponse {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub organization_name: String,
+    pub role: OrganizationRole,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Creating Organization", skip(app_state, user, input))]
+async fn add_organization(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<OrganizationsWrite>,
+    Json(input): Json<CreateOrganizationFormData>,
+) -> Result<Response, AppError> {
+    let input: NewOrganization = input.try_into()?;
+
+    let (id, created_at) = create_organization(user.id, &input, &app_state.pool).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(OrganizationResponse {
+            id,
+            name: input.name,
+            role: OrganizationRole::Owner,
+            created_at,
+        }),
+    )
+        .into_response())
+}
+
+/// Every organization the user belongs to. Clients switch between them with the
+/// organization header.
+#[tracing::instrument(name = "Listing Organizations", skip(app_state, user))]
+async fn get_organizations(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<OrganizationsRead>,
+) -> Result<Response, AppError> {
+    let organizations = get_organizations_by_user_id(user.id, &app_state.pool)
+        .await?
+        .into_iter()
+        .map(|o| OrganizationResponse {
+            id: o.id,
+            name: o.name,
+            role: OrganizationRole::parse(&o.role),
+            created_at: o.created_at,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(organizations)).into_response())
+}
+
+#[tracing::instrument(name = "Fetching current Organization", skip(app_state, tenant))]
+async fn get_current_organization(
+    State(app_state): State<Arc<AppState>>,
+    tenant: Tenant<OrganizationsRead>,
+) -> Result<Response, AppError> {
+    let organization = get_organization(&tenant.id, &app_state.pool).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(OrganizationResponse {
+            id: organization.id,
+            name: organization.name,
+            role: tenant.role,
+            created_at: organization.created_at,
+        }),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(name = "Listing Members", skip(app_state, tenant))]
+async fn get_current_members(
+    State(app_state): State<Arc<AppState>>,
+    tenant: Tenant<OrganizationsRead>,
+) -> Result<Response, AppError> {
+    let members = get_members(&tenant.id, &app_state.pool)
+        .await?
+        .into_iter()
+        .map(|m| MemberResponse {
+            user_id: m.user_id,
+            username: m.username,
+            role: OrganizationRole::parse(&m.role),
+            created_at: m.created_at,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(members)).into_response())
+}
+
+/// Changes the role of an existing member. New members join through an invitation.
+#[tracing::instrument(name = "Changing Member role", skip(app_state, tenant, input))]
+async fn change_member_role(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    tenant: Tenant<OrganizationsWrite>,
+    Json(input): Json<SetMemberRoleFormData>,
+) -> Result<Response, AppError> {
+    let mut tx = app_state.pool.get_transaction().await?;
+    let owners = lock_owners(&tenant.id, &mut tx).await?;
+    let (caller_role, current_role) = lock_member_roles(&tenant, user_id, &mut tx).await?;
+
+    ensure_can_manage_members(caller_role)?;
+
+    if input.role == OrganizationRole::Owner {
+        ensure_owner(caller_role)?;
+    }
+
+    if current_role == OrganizationRole::Owner {
+        ensure_owner(caller_role)?;
+
+        if input.role != OrganizationRole::Owner {
+            ensure_not_last_owner(owners)?;
+        }
+    }
+
+    set_member_role(&tenant.id, user_id, input.role, &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+/// Managers remove others, any member may remove themselves.
+#[tracing::instrument(name = "Removing Member", skip(app_state, tenant))]
+async fn delete_member(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    tenant: Tenant<OrganizationsWrite>,
+) -> Result<Response, AppError> {
+    let mut tx = app_state.pool.get_transaction().await?;
+    let owners = lock_owners(&tenant.id, &mut tx).await?;
+    let (caller_role, current_role) = lock_member_roles(&tenant, user_id, &mut tx).await?;
+
+    if user_id != tenant.user.id {
+        ensure_can_manage_members(caller_role)?;
+    }
+
+    if current_role == OrganizationRole::Owner {
+        if user_id != tenant.user.id {
+            ensure_owner(caller_role)?;
+        }
+
+        ensure_not_last_owner(owners)?;
+    }
+
+    remove_member(&tenant.id, user_id, &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+/// Invites the user to join with `role` once they accept. Answered alike whether or not the
+/// username exists, so invitations do not reveal accounts.
+#[tracing::instrument(name = "Inviting Member", skip(app_state, tenant, input))]
+async fn invite_member(
+    State(app_state): State<Arc<AppState>>,
+    tenant: Tenant<OrganizationsWrite>,
+    Json(input): Json<InviteMemberFormData>,
+) -> Result<Response, AppError> {
+    ensure_can_manage_members(tenant.role)?;
+
+    if input.role == OrganizationRole::Owner {
+        ensure_owner(tenant.role)?;
+    }
+
+    let user = match get_user_by_username(&input.username, &app_state.pool).await {
+        Ok(data) => data,
+        Err(AppError::NotFoundError(_)) => return Ok((StatusCode::ACCEPTED).into_response()),
+        Err(e) => return Err(e),
+    };
+
+    let expires_at = Utc::now() + Duration::days(INVITATION_TTL_DAYS);
+
+    add_invitation(
+        &tenant.id,
+        user.id,
+        input.role,
+        tenant.user.id,
+        expires_at,
+        &app_state.pool,
+    )
+    .await?;
+
+    Ok((StatusCode::ACCEPTED).into_response())
+}
+
+/// Invitations the current user can still accept.
+#[tracing::instrument(name = "Listing Invitations", skip(app_state, user))]
+async fn get_invitations(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<OrganizationsRead>,
+) -> Result<Response, AppError> {
+    let invitations = get_invitations_by_user_id(user.id, &app_state.pool)
+        .await?
+        .into_iter()
+        .map(|i| InvitationResponse {
+            id: i.id,
+            organization_id: i.organization_id,
+            organization_name: i.organization_name,
+            role: OrganizationRole::parse(&i.role),
+            expires_at: i.expires_at,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(invitations)).into_response())
+}
+
+#[tracing::instrument(name = "Accepting Invitation", skip(app_state, user))]
+async fn accept_member_invitation(
+    State(app_state): State<Arc<AppState>>,
+    Path(invitation_id): Path<Uuid>,
+    RequireScopes { user, .. }: RequireScopes<OrganizationsWrite>,
+) -> Result<Response, AppError> {
+    // Joining is the user's own decision.
+    user.reject_impersonation()?;
+
+    accept_invitation(invitation_id, user.id, &app_state.pool).await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Declining Invitation", skip(app_state, user))]
+async fn decline_invitation(
+    State(app_state): State<Arc<AppState>>,
+    Path(invitation_id): Path<Uuid>,
+    RequireScopes { user, .. }: RequireScopes<OrganizationsWrite>,
+) -> Result<Response, AppError> {
+    delete_invitation(invitation_id, user.id, &app_state.pool).await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+/// The roles of the caller and of the member `user_id`, read after `lock_owners` in the same
+/// transaction. The role `Tenant` checked may have changed since, so permissions are checked
+/// against these. Rows are locked in id order so members managing each other cannot deadlock.
+async fn lock_member_roles<S: RequiredScopes>(
+    tenant: &Tenant<S>,
+    user_id: Uuid,
+    tx: &mut impl TxContext,
+) -> Result<(OrganizationRole, OrganizationRole), AppError> {
+    let caller_first = tenant.user.id <= user_id;
+    let user_ids = match caller_first {
+        true => [tenant.user.id, user_id],
+        false => [user_id, tenant.user.id],
+    };
+
+    let mut roles = [OrganizationRole::Member; 2];
+    for (role, id) in roles.iter_mut().zip(user_ids) {
+        *role = match get_member_role_tx(&tenant.id, id, tx).await {
+            Err(AppError::NotFoundError(_)) if id == tenant.user.id => {
+                return Err(AppError::ForbiddenError(
+                    "You are not a member of this organization.".into(),
+                ))
+            }
+            result => result?,
+        };
+    }
+
+    match caller_first {
+        true => Ok((roles[0], roles[1])),
+        false => Ok((roles[1], roles[0])),
+    }
+}
+
+fn ensure_can_manage_members(role: OrganizationRole) -> Result<(), AppError> {
+    if !role.can_manage_members() {
+        return Err(AppError::ForbiddenError(
+            "Only owners and admins can manage members.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn ensure_owner(role: OrganizationRole) -> Result<(), AppError> {
+    if role != OrganizationRole::Owner {
+        return Err(AppError::ForbiddenError(
+            "Only owners can grant or revoke ownership.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// An organization without an owner could never be managed again. `owners` must come from
+/// `lock_owners` in the transaction that makes the change.
+fn ensure_not_last_owner(owners: i64) -> Result<(), AppError> {
+    if owners <= 1 {
+        return Err(AppError::ConflictError(
+            "An organization needs at least one owner.".into(),
+        ));
+    }
+
+    Ok(())
+}
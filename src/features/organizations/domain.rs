@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use validator::{Validate, ValidationError};
+
+use crate::errors::AppError;
+
+use super::models::CreateOrganizationFormData;
+
+const MAX_ORGANIZATION_NAME_LENGTH: usize = 64;
+
+/// How long an invited user has to accept before the invitation has to be sent again.
+pub const INVITATION_TTL_DAYS: i64 = 7;
+
+/// What a member may do within one organization. Independent of the global roles in
+/// `utils::roles`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OrganizationRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl OrganizationRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrganizationRole::Owner => "owner",
+            OrganizationRole::Admin => "admin",
+            OrganizationRole::Member => "member",
+        }
+    }
+
+    /// Unknown values get the least privileged role.
+    pub fn parse(v: &str) -> Self {
+        match v {
+            "owner" => OrganizationRole::Owner,
+            "admin" => OrganizationRole::Admin,
+            _ => OrganizationRole::Member,
+        }
+    }
+
+    pub fn can_manage_members(&self) -> bool {
+        matches!(self, OrganizationRole::Owner | OrganizationRole::Admin)
+    }
+}
+
+#[derive(Validate)]
+pub struct NewOrganization {
+    #[validate(custom(function = "parse_organization_name"))]
+    pub name : String
+}
+
+fn parse_organization_name (v : &str) -> Result<(), ValidationError> {
+    let length = v.graphemes(true).count();
+
+    if length == 0 || length > MAX_ORGANIZATION_NAME_LENGTH || v.chars().any(char::is_control) {
+        return Err(ValidationError::new("invalid_name").with_message(std::borrow::Cow::Borrowed("Invalid Name")))
+    }
+
+    Ok(())
+}
+
+impl TryFrom<CreateOrganizationFormData> for NewOrganization {
+    type Error = AppError;
+
+    fn try_from(value: CreateOrganizationFormData) -> Result<Self, Self::Error> {
+        let organization = NewOrganization { name : value.name.trim().to_string() };
+
+        organization.validate()?;
+
+        Ok(organization)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::features::organizations::models::CreateOrganizationFormData;
+
+    use super::{NewOrganization, OrganizationRole};
+
+    fn form(name : &str) -> CreateOrganizationFormData {
+        CreateOrganizationFormData { name : name.into() }
+    }
+
+    #[test]
+    fn organization_names_are_trimmed_and_bounded() {
+        assert_eq!("Acme", NewOrganization::try_from(form("  Acme ")).unwrap().name);
+        assert_ok!(NewOrganization::try_from(form(&"a".repeat(64))).map(|_| ()));
+        assert_err!(NewOrganization::try_from(form("   ")).map(|_| ()));
+        assert_err!(NewOrganization::try_from(form(&"a".repeat(65))).map(|_| ()));
+        assert_err!(NewOrganization::try_from(form("Ac\u{7}me")).map(|_| ()));
+    }
+
+    #[test]
+    fn only_owners_and_admins_manage_members() {
+        assert!(OrganizationRole::Owner.can_manage_members());
+        assert!(OrganizationRole::Admin.can_manage_members());
+        assert!(!OrganizationRole::Member.can_manage_members());
+        assert_eq!(OrganizationRole::Member, OrganizationRole::parse("superuser"));
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::domain::OrganizationRole;
+
+#[derive(Deserialize)]
+pub struct CreateOrganizationFormData {
+    pub name : String
+}
+
+#[derive(Deserialize)]
+pub struct SetMemberRoleFormData {
+    pub role : OrganizationRole
+}
+
+#[derive(Deserialize)]
+pub struct InviteMemberFormData {
+    pub username : String,
+    pub role : OrganizationRole
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct OrganizationData {
+    pub id : Uuid,
+    pub name : String,
+    pub created_at : DateTime<Utc>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct MembershipData {
+    pub id : Uuid,
+    pub name : String,
+    pub role : String,
+    pub created_at : DateTime<Utc>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct MemberData {
+    pub user_id : Uuid,
+    pub username : String,
+    pub role : String,
+    pub created_at : DateTime<Utc>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct InvitationData {
+    pub id : Uuid,
+    pub organization_id : Uuid,
+    pub organization_name : String,
+    pub role : String,
+    pub expires_at : DateTime<Utc>
+}
\ No newline at end of file
@@ -0,0 +1,368 @@
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use uuid::{NoContext, Timestamp, Uuid};
+
+use crate::db::{DbContext, TxContext};
+use crate::errors::AppError;
+use crate::utils::tenant::TenantId;
+
+use super::domain::{NewOrganization, OrganizationRole};
+use super::models::{InvitationData, MemberData, MembershipData, OrganizationData};
+
+#[tracing::instrument(name = "Creating Organization", skip(user_id, new_organization, db))]
+pub async fn create_organization(
+    user_id: Uuid,
+    new_organization: &NewOrganization,
+    db: &impl DbContext,
+) -> Result<(Uuid, DateTime<Utc>), AppError> {
+    let id = Uuid::new_v7(Timestamp::now(NoContext));
+    let created_at = Utc::now();
+
+    // One statement, so an organization never exists without its owner.
+    let query = sqlx::query!(
+        r#"
+            WITH organization AS (
+                INSERT INTO organizations (id, name, created_at)
+                VALUES ($1, $2, $3)
+                RETURNING id
+            )
+            INSERT INTO memberships (organization_id, user_id, role, created_at)
+            SELECT id, $4, 'owner', $3 FROM organization
+        "#,
+        id,
+        new_organization.name,
+        created_at,
+        user_id
+    );
+
+    db.execute_query(query).await?;
+
+    Ok((id, created_at))
+}
+
+#[tracing::instrument(name = "Fetching Organizations by user id", skip(user_id, db))]
+pub async fn get_organizations_by_user_id(
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Vec<MembershipData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT o.id, o.name, m.role, o.created_at
+            FROM memberships m
+            JOIN organizations o ON o.id = m.organization_id
+            WHERE m.user_id = $1
+            ORDER BY o.created_at, o.id
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_all::<MembershipData>(query).await?;
+
+    Ok(result)
+}
+
+/// The only lookup that takes a raw organization id. `Tenant` uses it to establish a
+/// `TenantId`, every other query in a tenant goes through that.
+#[tracing::instrument(name = "Fetching Membership role", skip(organization_id, user_id, db))]
+pub async fn get_membership_role(
+    organization_id: Uuid,
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Option<OrganizationRole>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT role FROM memberships WHERE organization_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(organization_id)
+    .bind(user_id);
+
+    let result = db.fetch_optional::<(String,)>(query).await?;
+
+    Ok(result.map(|(role,)| OrganizationRole::parse(&role)))
+}
+
+#[tracing::instrument(name = "Fetching Organization", skip(tenant, db))]
+pub async fn get_organization(
+    tenant: &TenantId,
+    db: &impl DbContext,
+) -> Result<OrganizationData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id, name, created_at FROM organizations WHERE id = $1
+        "#,
+    )
+    .bind(tenant.id());
+
+    match db.fetch_optional::<OrganizationData>(query).await? {
+        Some(data) => Ok(data),
+        None => Err(AppError::NotFoundError("Organization was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Fetching Members", skip(tenant, db))]
+pub async fn get_members(
+    tenant: &TenantId,
+    db: &impl DbContext,
+) -> Result<Vec<MemberData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT m.user_id, u.username, m.role, m.created_at
+            FROM memberships m
+            JOIN users u ON u.id = m.user_id
+            WHERE m.organization_id = $1
+            ORDER BY m.created_at, m.user_id
+        "#,
+    )
+    .bind(tenant.id());
+
+    let result = db.fetch_all::<MemberData>(query).await?;
+
+    Ok(result)
+}
+
+/// Counts the organization's owners and locks their memberships until the transaction ends, so
+/// two owners stepping down at once cannot both see the other one left.
+#[tracing::instrument(name = "Locking Owners", skip(tenant, tx))]
+pub async fn lock_owners(tenant: &TenantId, tx: &mut impl TxContext) -> Result<i64, AppError> {
+    let query = sqlx::query(
+        r#"
+            SELECT count(*) AS owners FROM (
+                SELECT 1 FROM memberships
+                WHERE organization_id = $1 AND role = 'owner'
+                FOR UPDATE
+            ) o
+        "#,
+    )
+    .bind(tenant.id());
+
+    match tx.fetch_optional(query).await? {
+        Some(row) => Ok(row.try_get("owners")?),
+        None => Ok(0),
+    }
+}
+
+/// Locks the owners of every organization `user_id` owns, like `lock_owners` does for one, and
+/// counts those the user is the only owner of while other members remain. Deleting the user in
+/// the same transaction would leave these without an owner.
+#[tracing::instrument(name = "Locking Owners of User's Organizations", skip(user_id, tx))]
+pub async fn lock_sole_ownerships(user_id: Uuid, tx: &mut impl TxContext) -> Result<i64, AppError> {
+    let query = sqlx::query(
+        r#"
+            SELECT count(*) AS organizations FROM (
+                SELECT o.organization_id FROM (
+                    SELECT organization_id FROM memberships
+                    WHERE role = 'owner' AND organization_id IN (
+                        SELECT organization_id FROM memberships
+                        WHERE user_id = $1 AND role = 'owner'
+                    )
+                    ORDER BY organization_id
+                    FOR UPDATE
+                ) o
+                GROUP BY o.organization_id
+                HAVING count(*) = 1
+            ) sole
+            WHERE EXISTS (
+                SELECT 1 FROM memberships m
+                WHERE m.organization_id = sole.organization_id AND m.user_id <> $1
+            )
+        "#,
+    )
+    .bind(user_id);
+
+    match tx.fetch_optional(query).await? {
+        Some(row) => Ok(row.try_get("organizations")?),
+        None => Ok(0),
+    }
+}
+
+/// Deletes the organizations no one but `user_id` belongs to, along with their todos.
+#[tracing::instrument(name = "Deleting Organizations of sole member", skip(user_id, tx))]
+pub async fn delete_sole_member_organizations(
+    user_id: Uuid,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM organizations o
+            WHERE EXISTS (
+                SELECT 1 FROM memberships m WHERE m.organization_id = o.id AND m.user_id = $1
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM memberships m WHERE m.organization_id = o.id AND m.user_id <> $1
+            )
+        "#,
+        user_id
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetching Member role", skip(tenant, user_id, tx))]
+pub async fn get_member_role_tx(
+    tenant: &TenantId,
+    user_id: Uuid,
+    tx: &mut impl TxContext,
+) -> Result<OrganizationRole, AppError> {
+    let query = sqlx::query(
+        r#"
+            SELECT role FROM memberships WHERE organization_id = $1 AND user_id = $2
+            FOR UPDATE
+        "#,
+    )
+    .bind(tenant.id())
+    .bind(user_id);
+
+    match tx.fetch_optional(query).await? {
+        Some(row) => Ok(OrganizationRole::parse(row.try_get("role")?)),
+        None => Err(AppError::NotFoundError("Member was not found".into())),
+    }
+}
+
+/// Members are only ever added by accepting an invitation, see `accept_invitation`.
+#[tracing::instrument(name = "Setting Member role", skip(tenant, user_id, tx))]
+pub async fn set_member_role(
+    tenant: &TenantId,
+    user_id: Uuid,
+    role: OrganizationRole,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            UPDATE memberships SET role = $3 WHERE organization_id = $1 AND user_id = $2
+        "#,
+        tenant.id(),
+        user_id,
+        role.as_str()
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Removing Member", skip(tenant, user_id, tx))]
+pub async fn remove_member(
+    tenant: &TenantId,
+    user_id: Uuid,
+    tx: &mut impl TxContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM memberships WHERE organization_id = $1 AND user_id = $2
+        "#,
+        tenant.id(),
+        user_id
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Replaces an invitation the user still has pending for the organization.
+#[tracing::instrument(name = "Adding Invitation", skip(tenant, user_id, invited_by, db))]
+pub async fn add_invitation(
+    tenant: &TenantId,
+    user_id: Uuid,
+    role: OrganizationRole,
+    invited_by: Uuid,
+    expires_at: DateTime<Utc>,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO organization_invitations
+            (id, organization_id, user_id, role, invited_by, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, now(), $6)
+            ON CONFLICT (organization_id, user_id) DO UPDATE
+            SET role = EXCLUDED.role, invited_by = EXCLUDED.invited_by,
+                created_at = EXCLUDED.created_at, expires_at = EXCLUDED.expires_at
+        "#,
+        Uuid::new_v7(Timestamp::now(NoContext)),
+        tenant.id(),
+        user_id,
+        role.as_str(),
+        invited_by,
+        expires_at
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetching Invitations by user id", skip(user_id, db))]
+pub async fn get_invitations_by_user_id(
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<Vec<InvitationData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT i.id, i.organization_id, o.name AS organization_name, i.role, i.expires_at
+            FROM organization_invitations i
+            JOIN organizations o ON o.id = i.organization_id
+            WHERE i.user_id = $1 AND i.expires_at > now()
+            ORDER BY i.created_at, i.id
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_all::<InvitationData>(query).await?;
+
+    Ok(result)
+}
+
+/// Turns the invitation into a membership in one statement. A user who already is a member
+/// keeps their role.
+#[tracing::instrument(name = "Accepting Invitation", skip(invitation_id, user_id, db))]
+pub async fn accept_invitation(
+    invitation_id: Uuid,
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query_as(
+        r#"
+            WITH invitation AS (
+                DELETE FROM organization_invitations
+                WHERE id = $1 AND user_id = $2 AND expires_at > now()
+                RETURNING organization_id, user_id, role
+            ), membership AS (
+                INSERT INTO memberships (organization_id, user_id, role, created_at)
+                SELECT organization_id, user_id, role, now() FROM invitation
+                ON CONFLICT (organization_id, user_id) DO NOTHING
+            )
+            SELECT organization_id FROM invitation
+        "#,
+    )
+    .bind(invitation_id)
+    .bind(user_id);
+
+    match db.fetch_all::<(Uuid,)>(query).await?.pop() {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFoundError("Invitation was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Declining Invitation", skip(invitation_id, user_id, db))]
+pub async fn delete_invitation(
+    invitation_id: Uuid,
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query_as(
+        r#"
+            DELETE FROM organization_invitations WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+    )
+    .bind(invitation_id)
+    .bind(user_id);
+
+    match db.fetch_all::<(Uuid,)>(query).await?.pop() {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFoundError("Invitation was not found".into())),
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, patch},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::AppError,
+    utils::{
+        scopes::{TodosRead, TodosWrite},
+        tenant::Tenant,
+    },
+};
+
+use super::{
+    domain::{NewTodo, TodoStatus, TodoUpdate},
+    models::{CreateTodoFormData, TodoData, UpdateTodoFormData},
+    repository::{add_todo, delete_todo, get_todos, update_todo},
+};
+
+/// Todos belong to the organization selected by the organization header.
+pub fn todo_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_current_todos).post(create_todo))
+        .route("/:id", patch(change_todo).delete(remove_todo))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TodoResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub status: TodoStatus,
+    pub owner_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<TodoData> for TodoResponse {
+    fn from(value: TodoData) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            status: TodoStatus::parse(&value.status),
+            owner_id: value.owner_id,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Listing Todos", skip(app_state, tenant))]
+async fn get_current_todos(
+    State(app_state): State<Arc<AppState>>,
+    tenant: Tenant<TodosRead>,
+) -> Result<Response, AppError> {
+    let todos = get_todos(&tenant.id, &app_state.pool)
+        .await?
+        .into_iter()
+        .map(TodoResponse::from)
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(todos)).into_response())
+}
+
+#[tracing::instrument(name = "Creating Todo", skip(app_state, tenant, input))]
+async fn create_todo(
+    State(app_state): State<Arc<AppState>>,
+    tenant: Tenant<TodosWrite>,
+    Json(input): Json<CreateTodoFormData>,
+) -> Result<Response, AppError> {
+    let input: NewTodo = input.try_into()?;
+
+    let todo = add_todo(&tenant.id, tenant.user.id, &input, &app_state.pool).await?;
+
+    Ok((StatusCode::CREATED, Json(TodoResponse::from(todo))).into_response())
+}
+
+#[tracing::instrument(name = "Updating Todo", skip(app_state, tenant, input))]
+async fn change_todo(
+    State(app_state): State<Arc<AppState>>,
+    Path(todo_id): Path<Uuid>,
+    tenant: Tenant<TodosWrite>,
+    Json(input): Json<UpdateTodoFormData>,
+) -> Result<Response, AppError> {
+    let input: TodoUpdate = input.try_into()?;
+
+    let todo = update_todo(&tenant.id, todo_id, &input, &app_state.pool).await?;
+
+    Ok((StatusCode::OK, Json(TodoResponse::from(todo))).into_response())
+}
+
+#[tracing::instrument(name = "Deleting Todo", skip(app_state, tenant))]
+async fn remove_todo(
+    State(app_state): State<Arc<AppState>>,
+    Path(todo_id): Path<Uuid>,
+    tenant: Tenant<TodosWrite>,
+) -> Result<Response, AppError> {
+    delete_todo(&tenant.id, todo_id, &app_state.pool).await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
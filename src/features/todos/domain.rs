@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::errors::AppError;
+
+use super::models::{CreateTodoFormData, UpdateTodoFormData};
+
+const MAX_TODO_NAME_LENGTH: u64 = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoStatus {
+    Open,
+    Done,
+}
+
+impl TodoStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoStatus::Open => "open",
+            TodoStatus::Done => "done",
+        }
+    }
+
+    pub fn parse(v: &str) -> Self {
+        match v {
+            "done" => TodoStatus::Done,
+            _ => TodoStatus::Open,
+        }
+    }
+}
+
+#[derive(Validate)]
+pub struct NewTodo {
+    #[validate(length(min = 1, max = "MAX_TODO_NAME_LENGTH", code = "invalid_name"))]
+    pub name : String
+}
+
+impl TryFrom<CreateTodoFormData> for NewTodo {
+    type Error = AppError;
+
+    fn try_from(value: CreateTodoFormData) -> Result<Self, Self::Error> {
+        let todo = NewTodo { name : value.name.trim().to_string() };
+
+        todo.validate()?;
+
+        Ok(todo)
+    }
+}
+
+/// Fields left out keep their current value.
+#[derive(Validate)]
+pub struct TodoUpdate {
+    #[validate(length(min = 1, max = "MAX_TODO_NAME_LENGTH", code = "invalid_name"))]
+    pub name : Option<String>,
+    pub status : Option<TodoStatus>
+}
+
+impl TryFrom<UpdateTodoFormData> for TodoUpdate {
+    type Error = AppError;
+
+    fn try_from(value: UpdateTodoFormData) -> Result<Self, Self::Error> {
+        let update = TodoUpdate {
+            name : value.name.map(|n| n.trim().to_string()),
+            status : value.status
+        };
+
+        update.validate()?;
+
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::features::todos::models::{CreateTodoFormData, UpdateTodoFormData};
+
+    use super::{NewTodo, TodoStatus, TodoUpdate};
+
+    #[test]
+    fn todo_names_are_trimmed_and_bounded() {
+        let create = |name : &str| NewTodo::try_from(CreateTodoFormData { name : name.into() }).map(|t| t.name);
+
+        assert_eq!("Buy milk", create(" Buy milk ").unwrap());
+        assert_err!(create("  "));
+        assert_err!(create(&"a".repeat(201)));
+    }
+
+    #[test]
+    fn todo_updates_validate_only_given_fields() {
+        let update = |name : Option<&str>| TodoUpdate::try_from(UpdateTodoFormData {
+            name : name.map(Into::into),
+            status : Some(TodoStatus::Done)
+        }).map(|_| ());
+
+        assert_ok!(update(None));
+        assert_ok!(update(Some("Renamed")));
+        assert_err!(update(Some(" ")));
+    }
+}
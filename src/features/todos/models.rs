@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::domain::TodoStatus;
+
+#[derive(Deserialize)]
+pub struct CreateTodoFormData {
+    pub name : String
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTodoFormData {
+    pub name : Option<String>,
+    pub status : Option<TodoStatus>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct TodoData {
+    pub id : Uuid,
+    pub name : String,
+    pub status : String,
+    pub owner_id : Uuid,
+    pub created_at : DateTime<Utc>,
+    pub updated_at : Option<DateTime<Utc>>
+}
\ No newline at end of file
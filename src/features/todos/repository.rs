@@ -0,0 +1,98 @@
+use uuid::{NoContext, Timestamp, Uuid};
+
+use crate::db::DbContext;
+use crate::errors::AppError;
+use crate::utils::tenant::TenantId;
+
+use super::domain::{NewTodo, TodoStatus, TodoUpdate};
+use super::models::TodoData;
+
+#[tracing::instrument(name = "Fetching Todos", skip(tenant, db))]
+pub async fn get_todos(tenant: &TenantId, db: &impl DbContext) -> Result<Vec<TodoData>, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id, name, status, owner_id, created_at, updated_at
+            FROM todos
+            WHERE organization_id = $1
+            ORDER BY created_at, id
+        "#,
+    )
+    .bind(tenant.id());
+
+    let result = db.fetch_all::<TodoData>(query).await?;
+
+    Ok(result)
+}
+
+#[tracing::instrument(name = "Adding Todo", skip(tenant, owner_id, new_todo, db))]
+pub async fn add_todo(
+    tenant: &TenantId,
+    owner_id: Uuid,
+    new_todo: &NewTodo,
+    db: &impl DbContext,
+) -> Result<TodoData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            INSERT INTO todos (id, name, status, created_at, owner_id, organization_id)
+            VALUES ($1, $2, $3, now(), $4, $5)
+            RETURNING id, name, status, owner_id, created_at, updated_at
+        "#,
+    )
+    .bind(Uuid::new_v7(Timestamp::now(NoContext)))
+    .bind(new_todo.name.clone())
+    .bind(TodoStatus::Open.as_str())
+    .bind(owner_id)
+    .bind(tenant.id());
+
+    match db.fetch_all::<TodoData>(query).await?.pop() {
+        Some(data) => Ok(data),
+        None => Err(AppError::UnexpectedError("Todo was not created".into())),
+    }
+}
+
+#[tracing::instrument(name = "Updating Todo", skip(tenant, todo_id, update, db))]
+pub async fn update_todo(
+    tenant: &TenantId,
+    todo_id: Uuid,
+    update: &TodoUpdate,
+    db: &impl DbContext,
+) -> Result<TodoData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            UPDATE todos
+            SET name = COALESCE($3, name), status = COALESCE($4, status), updated_at = now()
+            WHERE organization_id = $1 AND id = $2
+            RETURNING id, name, status, owner_id, created_at, updated_at
+        "#,
+    )
+    .bind(tenant.id())
+    .bind(todo_id)
+    .bind(update.name.clone())
+    .bind(update.status.map(|s| s.as_str()));
+
+    match db.fetch_all::<TodoData>(query).await?.pop() {
+        Some(data) => Ok(data),
+        None => Err(AppError::NotFoundError("Todo was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Deleting Todo", skip(tenant, todo_id, db))]
+pub async fn delete_todo(
+    tenant: &TenantId,
+    todo_id: Uuid,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query_as(
+        r#"
+            DELETE FROM todos WHERE organization_id = $1 AND id = $2
+            RETURNING id
+        "#,
+    )
+    .bind(tenant.id())
+    .bind(todo_id);
+
+    match db.fetch_all::<(Uuid,)>(query).await?.pop() {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFoundError("Todo was not found".into())),
+    }
+}
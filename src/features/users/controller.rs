@@ -0,0 +1,322 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{AppendHeaders, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::TypedHeader;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use cookie::time::Duration;
+use reqwest::header::SET_COOKIE;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    configurations::SessionMode,
+    db::{DbContext, TxContext},
+    errors::AppError,
+    features::{
+        auth::{
+            controller::{get_current_session_id, refresh_token_cookie},
+            domain::{AttemptKey, Credentials},
+            repository::{
+                clear_failed_logins, delete_other_sessions_by_user_id, get_locked_until,
+                get_roles_by_user_id, record_failed_login,
+            },
+        },
+        organizations::repository::{delete_sole_member_organizations, lock_sole_ownerships},
+    },
+    utils::{
+        jwt::AuthUser,
+        scopes::{AccountRead, AccountWrite, RequireScopes},
+        session_store::session_cookie,
+    },
+};
+
+use super::{
+    domain::{generate_email_change_token, EmailChange, ProfileUpdate, EMAIL_CHANGE_TTL_HOURS},
+    models::{
+        ChangeEmailFormData, ChangePasswordFormData, ConfirmEmailFormData, DeleteAccountFormData,
+        UpdateProfileFormData,
+    },
+    repository::{
+        add_email_change, delete_user, get_user_profile_by_id, take_email_change,
+        update_display_name, update_user_email, update_user_password, verify_user_password,
+    },
+};
+
+pub fn user_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/me",
+            get(get_profile).patch(update_profile).delete(delete_account),
+        )
+        .route("/me/password", post(change_password))
+        .route("/me/email", post(request_email_change))
+        .route("/email/confirm", post(confirm_email_change))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserProfileResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    pub roles: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    /// Lets clients show that an administrator is acting as this user.
+    pub impersonator_id: Option<Uuid>,
+}
+
+#[tracing::instrument(name = "Fetching own profile", skip(app_state, user))]
+async fn get_profile(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<AccountRead>,
+) -> Result<Response, AppError> {
+    let profile = profile_response(&user, &app_state).await?;
+
+    Ok((StatusCode::OK, Json(profile)).into_response())
+}
+
+#[tracing::instrument(name = "Updating own profile", skip(app_state, user, input))]
+async fn update_profile(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<AccountWrite>,
+    Json(input): Json<UpdateProfileFormData>,
+) -> Result<Response, AppError> {
+    let input: ProfileUpdate = input.try_into()?;
+
+    if let Some(display_name) = &input.display_name {
+        let display_name = Some(display_name.as_str()).filter(|v| !v.is_empty());
+
+        update_display_name(user.id, display_name, &app_state.pool).await?;
+    }
+
+    let profile = profile_response(&user, &app_state).await?;
+
+    Ok((StatusCode::OK, Json(profile)).into_response())
+}
+
+/// Signs out every other session. Access tokens already issued to them stay valid until they
+/// expire.
+#[tracing::instrument(name = "Changing own password", skip(app_state, cookie, user, input))]
+async fn change_password(
+    State(app_state): State<Arc<AppState>>,
+    cookie: Option<TypedHeader<headers::Cookie>>,
+    RequireScopes { user, .. }: RequireScopes<AccountWrite>,
+    Json(input): Json<ChangePasswordFormData>,
+) -> Result<Response, AppError> {
+    reject_api_key(&user)?;
+    user.reject_impersonation()?;
+
+    let username = verify_current_password(user.id, &input.current_password, &app_state).await?;
+
+    let credentials = Credentials {
+        username,
+        password: input.new_password,
+        email: None,
+    };
+    credentials.validate()?;
+    app_state.password_policy.check(&credentials)?;
+
+    let current_server_session = cookie
+        .as_ref()
+        .and_then(|TypedHeader(c)| c.get(&app_state.session_settings.cookie_name))
+        .map(String::from);
+    let current_session_id = get_current_session_id(cookie, user.id, &app_state).await?;
+
+    update_user_password(
+        user.id,
+        &credentials.password,
+        &app_state.pool,
+        &app_state.pwd_hasher,
+    )
+    .await?;
+    delete_other_sessions_by_user_id(user.id, current_session_id, &app_state.pool).await?;
+    app_state
+        .session_store
+        .delete_user_sessions(user.id, current_server_session.as_deref())
+        .await?;
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+/// Nothing changes until the link sent to the new address is opened, which proves the user
+/// controls it.
+#[tracing::instrument(name = "Requesting email change", skip(app_state, user, input))]
+async fn request_email_change(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<AccountWrite>,
+    Json(input): Json<ChangeEmailFormData>,
+) -> Result<Response, AppError> {
+    reject_api_key(&user)?;
+    user.reject_impersonation()?;
+
+    let input: EmailChange = input.try_into()?;
+
+    verify_current_password(user.id, &input.password, &app_state).await?;
+
+    send_email_confirmation(user.id, &input.email, &app_state).await?;
+
+    Ok((StatusCode::ACCEPTED).into_response())
+}
+
+/// Emails `email` a link that sets it on the account once opened, so an address is only stored
+/// after its owner confirmed it.
+pub async fn send_email_confirmation(
+    user_id: Uuid,
+    email: &str,
+    app_state: &AppState,
+) -> Result<(), AppError> {
+    let token = generate_email_change_token();
+    let expires_at = Utc::now() + ChronoDuration::hours(EMAIL_CHANGE_TTL_HOURS);
+
+    add_email_change(&token, user_id, email, expires_at, &app_state.pool).await?;
+
+    let link = format!(
+        "{}/confirm-email?token={}",
+        app_state.client_url.trim_end_matches('/'),
+        token
+    );
+    app_state
+        .email_client
+        .send_email(
+            email,
+            "Confirm your email address",
+            &format!(
+                "Open <a href=\"{}\">this link</a> to confirm your email address.",
+                link
+            ),
+            &format!("Open {} to confirm your email address.", link),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Confirming email change", skip(app_state, input))]
+async fn confirm_email_change(
+    State(app_state): State<Arc<AppState>>,
+    Json(input): Json<ConfirmEmailFormData>,
+) -> Result<Response, AppError> {
+    let change = take_email_change(&input.token, &app_state.pool).await?;
+
+    match update_user_email(change.user_id, &change.new_email, &app_state.pool).await {
+        Err(e) if e.is_unique_violation() => {
+            return Err(AppError::ConflictError("Email is already taken.".into()))
+        }
+        result => result?,
+    };
+
+    Ok((StatusCode::NO_CONTENT).into_response())
+}
+
+#[tracing::instrument(name = "Deleting own account", skip(app_state, user, input))]
+async fn delete_account(
+    State(app_state): State<Arc<AppState>>,
+    RequireScopes { user, .. }: RequireScopes<AccountWrite>,
+    Json(input): Json<DeleteAccountFormData>,
+) -> Result<Response, AppError> {
+    reject_api_key(&user)?;
+    user.reject_impersonation()?;
+
+    verify_current_password(user.id, &input.password, &app_state).await?;
+
+    // Memberships go with the user, so organizations they alone own must be handed over first.
+    // Organizations nobody else belongs to are deleted with the account.
+    let mut tx = app_state.pool.get_transaction().await?;
+    if lock_sole_ownerships(user.id, &mut tx).await? > 0 {
+        return Err(AppError::ConflictError(
+            "Transfer ownership of your organizations before deleting your account.".into(),
+        ));
+    }
+    delete_sole_member_organizations(user.id, &mut tx).await?;
+    delete_user(user.id, &mut tx).await?;
+    tx.execute_transaction().await?;
+
+    app_state.session_store.delete_user_sessions(user.id, None).await?;
+
+    let empty_cookie = match app_state.session_settings.mode {
+        SessionMode::Jwt => {
+            refresh_token_cookie(String::new(), Duration::ZERO, &app_state.jwt_settings)
+        }
+        SessionMode::Server => session_cookie(
+            String::new(),
+            Duration::ZERO,
+            &app_state.session_settings,
+            &app_state.jwt_settings,
+        ),
+    };
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        AppendHeaders([(SET_COOKIE, empty_cookie.to_string())]),
+    )
+        .into_response())
+}
+
+/// Re-authentication proves the caller knows the password, which an API key holder may not.
+fn reject_api_key(user: &AuthUser) -> Result<(), AppError> {
+    if user.api_key_id.is_some() {
+        return Err(AppError::ForbiddenError(
+            "API keys cannot be used to manage credentials.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn profile_response(
+    user: &AuthUser,
+    app_state: &AppState,
+) -> Result<UserProfileResponse, AppError> {
+    let profile = get_user_profile_by_id(user.id, &app_state.pool).await?;
+    let roles = get_roles_by_user_id(user.id, &app_state.pool).await?;
+
+    Ok(UserProfileResponse {
+        id: profile.id,
+        username: profile.username,
+        display_name: profile.display_name,
+        email: profile.email,
+        roles,
+        created_at: profile.created_at,
+        impersonator_id: user.impersonator_id,
+    })
+}
+
+/// Shares the login lockout, which is keyed by the account's username, so a stolen access token
+/// cannot be used to guess the password without limit.
+async fn verify_current_password(
+    user_id: Uuid,
+    password: &str,
+    app_state: &AppState,
+) -> Result<String, AppError> {
+    let profile = get_user_profile_by_id(user_id, &app_state.pool).await?;
+    let key = AttemptKey::username(&profile.username);
+
+    if let Some(locked_until) = get_locked_until(&key, &app_state.pool).await? {
+        let retry_after = (locked_until - Utc::now()).num_seconds().max(1);
+
+        return Err(AppError::LockedError(
+            "Too many failed password attempts.".into(),
+            retry_after,
+        ));
+    }
+
+    match verify_user_password(user_id, password, &app_state.pool, &app_state.pwd_hasher).await {
+        Ok(username) => {
+            clear_failed_logins(&key, &app_state.pool).await?;
+            Ok(username)
+        }
+        Err(AppError::UnauthorizedError(e)) => {
+            record_failed_login(&key, &app_state.login_throttle, &app_state.pool).await?;
+            Err(AppError::UnauthorizedError(e))
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,105 @@
+use unicode_segmentation::UnicodeSegmentation;
+use validator::{Validate, ValidationError};
+
+use crate::errors::AppError;
+use crate::features::auth::domain::{normalize_email, MAX_EMAIL_LENGTH};
+use crate::utils::randomizer::generate_random_string;
+
+use super::models::{ChangeEmailFormData, UpdateProfileFormData};
+
+const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+const EMAIL_CHANGE_TOKEN_LENGTH: usize = 32;
+pub const EMAIL_CHANGE_TTL_HOURS: i64 = 24;
+
+#[derive(Validate)]
+pub struct ProfileUpdate {
+    #[validate(custom(function = "parse_display_name"))]
+    pub display_name : Option<String>
+}
+
+fn parse_display_name (v : &str) -> Result<(), ValidationError> {
+    if v.graphemes(true).count() > MAX_DISPLAY_NAME_LENGTH || v.chars().any(char::is_control) {
+        return Err(ValidationError::new("invalid_display_name").with_message(std::borrow::Cow::Borrowed("Invalid Display Name")))
+    }
+
+    Ok(())
+}
+
+impl TryFrom<UpdateProfileFormData> for ProfileUpdate {
+    type Error = AppError;
+
+    fn try_from(value: UpdateProfileFormData) -> Result<Self, Self::Error> {
+        let UpdateProfileFormData { display_name } = value;
+
+        let update = ProfileUpdate { display_name : display_name.map(|v| v.trim().to_string()) };
+
+        update.validate()?;
+
+        Ok(update)
+    }
+}
+
+/// A request to move an account to `email`. Takes effect once the link sent there is opened.
+#[derive(Validate)]
+pub struct EmailChange {
+    #[validate(email(code = "invalid_email"), length(max = "MAX_EMAIL_LENGTH", code = "invalid_email"))]
+    pub email : String,
+    pub password : String
+}
+
+impl TryFrom<ChangeEmailFormData> for EmailChange {
+    type Error = AppError;
+
+    fn try_from(value: ChangeEmailFormData) -> Result<Self, Self::Error> {
+        let ChangeEmailFormData { email, password } = value;
+
+        let change = EmailChange { email : normalize_email(&email), password };
+
+        change.validate()?;
+
+        Ok(change)
+    }
+}
+
+pub fn generate_email_change_token() -> String {
+    generate_random_string(EMAIL_CHANGE_TOKEN_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::features::users::models::{ChangeEmailFormData, UpdateProfileFormData};
+
+    use super::{EmailChange, ProfileUpdate};
+
+    fn form(display_name : &str) -> UpdateProfileFormData {
+        UpdateProfileFormData { display_name : Some(display_name.into()) }
+    }
+
+    #[test]
+    fn a_display_name_is_trimmed() {
+        let update = ProfileUpdate::try_from(form("  Ada Lovelace ")).unwrap();
+
+        assert_eq!(Some("Ada Lovelace".to_string()), update.display_name);
+    }
+
+    #[test]
+    fn an_empty_display_name_is_accepted() {
+        assert_ok!(ProfileUpdate::try_from(form("   ")).map(|_| ()));
+    }
+
+    #[test]
+    fn a_long_or_control_character_display_name_is_rejected() {
+        assert_err!(ProfileUpdate::try_from(form(&"a".repeat(65))).map(|_| ()));
+        assert_err!(ProfileUpdate::try_from(form("Ada\nLovelace")).map(|_| ()));
+    }
+
+    #[test]
+    fn a_new_email_must_be_valid() {
+        let change = |email : &str| EmailChange::try_from(ChangeEmailFormData { email : email.into(), password : "secret".into() });
+
+        assert_eq!("ada@example.com", change(" ada@example.com ").unwrap().email);
+        assert_err!(change("ada").map(|_| ()));
+    }
+}
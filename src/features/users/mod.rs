@@ -0,0 +1,4 @@
+pub mod controller;
+pub mod domain;
+pub mod repository;
+mod models;
\ No newline at end of file
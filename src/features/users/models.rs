@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// `display_name` is left unchanged when missing and cleared when empty.
+#[derive(Deserialize)]
+pub struct UpdateProfileFormData {
+    pub display_name : Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordFormData {
+    pub current_password : String,
+    pub new_password : String
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailFormData {
+    pub email : String,
+    pub password : String
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailFormData {
+    pub token : String
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountFormData {
+    pub password : String
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct UserProfileData {
+    pub id : Uuid,
+    pub username : String,
+    pub display_name : Option<String>,
+    pub email : Option<String>,
+    pub created_at : DateTime<Utc>
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct UserPasswordData {
+    pub username : String,
+    pub password : String
+}
+
+#[derive(FromRow, Deserialize)]
+pub struct EmailChangeData {
+    pub user_id : Uuid,
+    pub new_email : String,
+    pub expires_at : DateTime<Utc>
+}
\ No newline at end of file
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::{DbContext, TxContext};
+use crate::errors::AppError;
+use crate::utils::password_hasher::PwdHasher;
+use crate::utils::token_hash::hash_token;
+
+use super::models::{EmailChangeData, UserPasswordData, UserProfileData};
+
+#[tracing::instrument(name = "Fetching User profile", skip(user_id, db))]
+pub async fn get_user_profile_by_id(
+    user_id: Uuid,
+    db: &impl DbContext,
+) -> Result<UserProfileData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT id, username, display_name, email, created_at FROM users WHERE id = $1
+        "#,
+    )
+    .bind(user_id);
+
+    let result = db.fetch_optional::<UserProfileData>(query).await?;
+
+    match result {
+        Some(data) => Ok(data),
+        None => Err(AppError::NotFoundError("User was not found".into())),
+    }
+}
+
+#[tracing::instrument(name = "Updating User display name", skip(user_id, display_name, db))]
+pub async fn update_display_name(
+    user_id: Uuid,
+    display_name: Option<&str>,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            UPDATE users SET display_name = $2 WHERE id = $1
+        "#,
+        user_id,
+        display_name
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Re-authenticates a signed in user. Returns their username for further validation.
+#[tracing::instrument(name = "Verifying User password", skip(user_id, password, db, pwd_hasher))]
+pub async fn verify_user_password(
+    user_id: Uuid,
+    password: &str,
+    db: &impl DbContext,
+    pwd_hasher: &impl PwdHasher,
+) -> Result<String, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            SELECT username, password FROM users WHERE id = $1
+        "#,
+    )
+    .bind(user_id);
+
+    let user = match db.fetch_optional::<UserPasswordData>(query).await? {
+        Some(data) => data,
+        None => return Err(AppError::NotFoundError("User was not found".into())),
+    };
+
+    pwd_hasher.verify_password(password, &user.password).await?;
+
+    Ok(user.username)
+}
+
+#[tracing::instrument(name = "Updating User password", skip(user_id, password, db, pwd_hasher))]
+pub async fn update_user_password(
+    user_id: Uuid,
+    password: &str,
+    db: &impl DbContext,
+    pwd_hasher: &impl PwdHasher,
+) -> Result<(), AppError> {
+    let password = pwd_hasher.hash_password(password).await?;
+
+    let query = sqlx::query!(
+        r#"
+            UPDATE users SET password = $2 WHERE id = $1
+        "#,
+        user_id,
+        password
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Tokens, roles and API keys are removed along with the user by `ON DELETE CASCADE`.
+#[tracing::instrument(name = "Deleting User", skip(user_id, tx))]
+pub async fn delete_user(user_id: Uuid, tx: &mut impl TxContext) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM users WHERE id = $1
+        "#,
+        user_id
+    );
+
+    tx.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Replaces any change the user still has pending, so only the latest link works.
+#[tracing::instrument(name = "Adding email change", skip(token, user_id, new_email, expires_at, db))]
+pub async fn add_email_change(
+    token: &str,
+    user_id: Uuid,
+    new_email: &str,
+    expires_at: DateTime<Utc>,
+    db: &impl DbContext,
+) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            DELETE FROM email_changes WHERE user_id = $1
+        "#,
+        user_id
+    );
+
+    db.execute_query(query).await?;
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO email_changes (token_hash, user_id, new_email, created_at, expires_at)
+            VALUES ($1, $2, $3, now(), $4)
+        "#,
+        hash_token(token),
+        user_id,
+        new_email,
+        expires_at
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
+
+/// Consumes a pending change. The token is single use even if applying it fails.
+#[tracing::instrument(name = "Taking email change", skip(token, db))]
+pub async fn take_email_change(token: &str, db: &impl DbContext) -> Result<EmailChangeData, AppError> {
+    let query = sqlx::query_as(
+        r#"
+            DELETE FROM email_changes WHERE token_hash = $1 RETURNING user_id, new_email, expires_at
+        "#,
+    )
+    .bind(hash_token(token));
+
+    match db.fetch_all::<EmailChangeData>(query).await?.pop() {
+        Some(data) if data.expires_at > Utc::now() => Ok(data),
+        _ => Err(AppError::UnauthorizedError("Invalid or expired token".into())),
+    }
+}
+
+#[tracing::instrument(name = "Updating User email", skip(user_id, email, db))]
+pub async fn update_user_email(user_id: Uuid, email: &str, db: &impl DbContext) -> Result<(), AppError> {
+    let query = sqlx::query!(
+        r#"
+            UPDATE users SET email = $2 WHERE id = $1
+        "#,
+        user_id,
+        email
+    );
+
+    db.execute_query(query).await?;
+
+    Ok(())
+}
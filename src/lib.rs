@@ -3,6 +3,7 @@ pub mod features;
 pub mod utils;
 pub mod db;
 pub mod errors;
+pub mod maintenance;
 pub mod startup;
 pub mod configurations;
 pub mod telemetry;
\ No newline at end of file
@@ -0,0 +1,285 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use sqlx::PgPool;
+
+use crate::{
+    configurations::{LoginThrottleSettings, MaintenanceSettings},
+    errors::AppError,
+};
+
+/// A TTL-based cleanup. `sql` deletes at most `$1` rows older than `$2` seconds and is run
+/// repeatedly until a batch comes back short.
+struct PurgeJob {
+    name: &'static str,
+    sql: &'static str,
+    retention_seconds: f64,
+}
+
+#[derive(Default)]
+pub struct MaintenanceMetrics {
+    rows_purged: Mutex<HashMap<&'static str, u64>>,
+    failures: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl MaintenanceMetrics {
+    pub fn rows_purged(&self, job: &str) -> u64 {
+        Self::get(&self.rows_purged, job)
+    }
+
+    pub fn failures(&self, job: &str) -> u64 {
+        Self::get(&self.failures, job)
+    }
+
+    /// Rows purged per job since the server started.
+    pub fn snapshot(&self) -> BTreeMap<String, u64> {
+        Self::collect(&self.rows_purged)
+    }
+
+    /// Failed runs per job since the server started.
+    pub fn failure_snapshot(&self) -> BTreeMap<String, u64> {
+        Self::collect(&self.failures)
+    }
+
+    fn record(&self, job: &'static str, rows: u64) -> u64 {
+        Self::add(&self.rows_purged, job, rows)
+    }
+
+    fn record_failure(&self, job: &'static str) -> u64 {
+        Self::add(&self.failures, job, 1)
+    }
+
+    fn get(counters: &Mutex<HashMap<&'static str, u64>>, job: &str) -> u64 {
+        counters
+            .lock()
+            .map(|m| m.get(job).copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    fn collect(counters: &Mutex<HashMap<&'static str, u64>>) -> BTreeMap<String, u64> {
+        counters
+            .lock()
+            .map(|m| m.iter().map(|(job, count)| (job.to_string(), *count)).collect())
+            .unwrap_or_default()
+    }
+
+    fn add(counters: &Mutex<HashMap<&'static str, u64>>, job: &'static str, count: u64) -> u64 {
+        match counters.lock() {
+            Ok(mut m) => {
+                let total = m.entry(job).or_insert(0);
+                *total += count;
+                *total
+            }
+            Err(_) => 0,
+        }
+    }
+}
+
+pub struct MaintenanceWorker {
+    pool: PgPool,
+    settings: MaintenanceSettings,
+    jobs: Vec<PurgeJob>,
+    metrics: Arc<MaintenanceMetrics>,
+}
+
+impl MaintenanceWorker {
+    pub fn new(
+        pool: PgPool,
+        settings: MaintenanceSettings,
+        login_throttle: &LoginThrottleSettings,
+    ) -> Self {
+        let jobs = vec![
+            PurgeJob {
+                name: "expired_refresh_tokens",
+                sql: r#"
+                    DELETE FROM user_tokens WHERE id IN (
+                        SELECT id FROM user_tokens
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_revoked_tokens",
+                sql: r#"
+                    DELETE FROM revoked_tokens WHERE jti IN (
+                        SELECT jti FROM revoked_tokens
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_api_keys",
+                sql: r#"
+                    DELETE FROM api_keys WHERE id IN (
+                        SELECT id FROM api_keys
+                        WHERE LEAST(expires_at, revoked_at) < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_email_changes",
+                sql: r#"
+                    DELETE FROM email_changes WHERE token_hash IN (
+                        SELECT token_hash FROM email_changes
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_password_resets",
+                sql: r#"
+                    DELETE FROM password_resets WHERE token_hash IN (
+                        SELECT token_hash FROM password_resets
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_magic_links",
+                sql: r#"
+                    DELETE FROM magic_links WHERE token_hash IN (
+                        SELECT token_hash FROM magic_links
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_server_sessions",
+                sql: r#"
+                    DELETE FROM server_sessions WHERE id_hash IN (
+                        SELECT id_hash FROM server_sessions
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "expired_organization_invitations",
+                sql: r#"
+                    DELETE FROM organization_invitations WHERE id IN (
+                        SELECT id FROM organization_invitations
+                        WHERE expires_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: 0.0,
+            },
+            PurgeJob {
+                name: "stale_login_attempts",
+                sql: r#"
+                    DELETE FROM login_attempts WHERE (key_type, key_value) IN (
+                        SELECT key_type, key_value FROM login_attempts
+                        WHERE last_failed_at < now() - make_interval(secs => $2)
+                        AND (locked_until IS NULL OR locked_until < now())
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: login_throttle.attempt_window_seconds as f64,
+            },
+            PurgeJob {
+                name: "old_security_incidents",
+                sql: r#"
+                    DELETE FROM security_incidents WHERE id IN (
+                        SELECT id FROM security_incidents
+                        WHERE created_at < now() - make_interval(secs => $2)
+                        LIMIT $1
+                    )
+                "#,
+                retention_seconds: settings.security_incident_retention_days as f64 * 86400.0,
+            },
+        ];
+
+        Self {
+            pool,
+            settings,
+            jobs,
+            metrics: Arc::new(MaintenanceMetrics::default()),
+        }
+    }
+
+    pub fn metrics(&self) -> Arc<MaintenanceMetrics> {
+        self.metrics.clone()
+    }
+
+    pub async fn run(self) {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.settings.interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            // Each failing job has already been logged and counted.
+            let _ = self.run_once().await;
+        }
+    }
+
+    /// Runs every job once. A failing job does not stop the ones after it; the first error is
+    /// returned once all of them have run.
+    #[tracing::instrument(name = "Running maintenance", skip(self))]
+    pub async fn run_once(&self) -> Result<(), AppError> {
+        let mut first_error = None;
+
+        for job in &self.jobs {
+            match self.purge(job).await {
+                Ok(purged) => {
+                    let total = self.metrics.record(job.name, purged);
+                    tracing::info!(
+                        job = job.name,
+                        rows_purged = purged,
+                        rows_purged_total = total,
+                        "Maintenance job finished."
+                    );
+                }
+                Err(e) => {
+                    let failures = self.metrics.record_failure(job.name);
+                    tracing::error!(
+                        error = %e,
+                        job = job.name,
+                        failures_total = failures,
+                        "Maintenance job failed."
+                    );
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    async fn purge(&self, job: &PurgeJob) -> Result<u64, AppError> {
+        let mut purged = 0;
+
+        loop {
+            let result = sqlx::query(job.sql)
+                .bind(self.settings.batch_size)
+                .bind(job.retention_seconds)
+                .execute(&self.pool)
+                .await?;
+
+            purged += result.rows_affected();
+
+            if result.rows_affected() < self.settings.batch_size as u64 {
+                return Ok(purged);
+            }
+        }
+    }
+}
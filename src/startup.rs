@@ -1,6 +1,10 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
 use axum::http::HeaderValue;
+use axum::middleware::{from_fn_with_state, AddExtension};
+use axum::extract::ConnectInfo;
 use axum::{routing::get, serve::Serve, Router};
 use reqwest::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use reqwest::Method;
@@ -9,16 +13,40 @@ use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 
+use crate::features::auth::domain::PasswordPolicy;
+use crate::maintenance::MaintenanceWorker;
+use crate::utils::account_status_cache::AccountStatusCache;
+use crate::utils::csrf::{csrf_protect, CSRF_HEADER};
+use crate::utils::email_client::EmailClient;
+use crate::utils::jwks::JwtKeys;
+use crate::utils::jwt::API_KEY_HEADER;
 use crate::utils::password_hasher::ServerPwdHasher;
+use crate::utils::rate_limiter::{rate_limit, InMemoryRateLimitStore, RateLimitStore, RateLimiter};
+use crate::utils::session_store::session_store;
+use crate::utils::tenant::ORGANIZATION_HEADER;
+use crate::utils::token_denylist::TokenDenylist;
 use crate::{
     app_state::AppState,
-    configurations::{DatabaseSettings, JwtSettings, Settings},
+    configurations::{
+        DatabaseSettings, RateLimitSettings, Settings,
+    },
     db::DbPool,
-    features::{auth::controller::auth_routes, health_check::controller::health_check},
+    features::{
+        admin::controller::admin_routes, api_keys::controller::api_key_routes,
+        auth::controller::auth_routes, health_check::controller::health_check,
+        jwks::controller::jwks, organizations::controller::organization_routes,
+        todos::controller::todo_routes, users::controller::user_routes,
+    },
 };
 
+type AppServer = Serve<
+    IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    AddExtension<Router, ConnectInfo<SocketAddr>>,
+>;
+
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: AppServer,
+    maintenance: MaintenanceWorker,
     port: u16,
 }
 
@@ -30,11 +58,41 @@ impl Application {
 
         let port = address.local_addr().unwrap().port();
         let pool = get_db_pool(&config.database);
-        let pwd_hasher = ServerPwdHasher;
-        let app_routes = get_app_routes(config.app.client_url, pool, config.jwt, pwd_hasher);
-        let server = axum::serve(address, app_routes);
+        let jwt_keys = JwtKeys::from_settings(&config.jwt)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let password_policy = PasswordPolicy::from_settings(config.password_policy)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let pwd_hasher = ServerPwdHasher::new(&config.password_hashing)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let maintenance =
+            MaintenanceWorker::new(pool.clone(), config.maintenance, &config.login_throttle);
+        let session_store = session_store(&config.session, &pool);
+        let app_state = AppState {
+            pool: DbPool { pool },
+            jwt_settings: config.jwt,
+            jwt_keys,
+            session_settings: config.session,
+            session_store,
+            token_denylist: TokenDenylist::default(),
+            account_status_cache: AccountStatusCache::default(),
+            login_throttle: config.login_throttle,
+            password_policy,
+            pwd_hasher,
+            email_client: EmailClient::new(&config.email_client),
+            maintenance_metrics: maintenance.metrics(),
+            client_url: config.app.client_url,
+        };
+        let app_routes = get_app_routes(app_state, config.rate_limit);
+        let server = axum::serve(
+            address,
+            app_routes.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
-        Ok(Self { server, port })
+        Ok(Self {
+            server,
+            maintenance,
+            port,
+        })
     }
 
     pub fn get_port(&self) -> u16 {
@@ -42,37 +100,67 @@ impl Application {
     }
 
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        let maintenance = tokio::spawn(self.maintenance.run());
+
+        let result = self.server.await;
+        maintenance.abort();
+
+        result
     }
 }
 
-fn get_app_routes(
-    client_url: String,
-    pool: PgPool,
-    jwt_settings: JwtSettings,
-    pwd_hasher: ServerPwdHasher,
-) -> Router {
-    let app_state = Arc::new(AppState {
-        pool: DbPool { pool },
-        jwt_settings,
-        pwd_hasher,
-    });
+fn get_app_routes(app_state: AppState, rate_limit_settings: RateLimitSettings) -> Router {
+    let app_state = Arc::new(app_state);
+
+    let rate_limit_store: Arc<dyn RateLimitStore> = Arc::new(InMemoryRateLimitStore::default());
+    let default_limiter = RateLimiter {
+        group: "default",
+        policy: rate_limit_settings.default,
+        store: rate_limit_store.clone(),
+        app_state: app_state.clone(),
+    };
+    let auth_limiter = RateLimiter {
+        group: "auth",
+        policy: rate_limit_settings.auth,
+        store: rate_limit_store,
+        app_state: app_state.clone(),
+    };
 
     Router::new()
+        .route("/.well-known/jwks.json", get(jwks))
         .nest(
             "/api",
             Router::new()
                 .route("/health_check", get(health_check))
-                .nest("/auth", auth_routes()),
+                .nest("/admin", admin_routes())
+                .nest("/api-keys", api_key_routes())
+                .nest("/organizations", organization_routes())
+                .nest("/todos", todo_routes())
+                .nest("/users", user_routes())
+                .nest(
+                    "/auth",
+                    auth_routes().layer(from_fn_with_state(auth_limiter, rate_limit)),
+                )
+                // Session cookies authenticate every route, not only those under /auth.
+                .layer(from_fn_with_state(app_state.clone(), csrf_protect))
+                .layer(from_fn_with_state(default_limiter, rate_limit)),
         )
         .layer(
             CorsLayer::new()
-                .allow_origin(client_url.parse::<HeaderValue>().unwrap())
+                .allow_origin(app_state.client_url.parse::<HeaderValue>().unwrap())
                 .allow_credentials(true)
-                .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE])
+                .allow_headers([
+                    AUTHORIZATION,
+                    ACCEPT,
+                    CONTENT_TYPE,
+                    API_KEY_HEADER.clone(),
+                    CSRF_HEADER.clone(),
+                    ORGANIZATION_HEADER.clone(),
+                ])
                 .allow_methods([
                     Method::GET,
                     Method::POST,
+                    Method::PUT,
                     Method::PATCH,
                     Method::DELETE,
                     Method::OPTIONS,
@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    db::DbContext,
+    errors::AppError,
+    features::auth::{domain::AccountState, repository::get_account_state},
+};
+
+const MAX_CACHED_USERS: usize = 10_000;
+const EVICTION_BATCH: usize = MAX_CACHED_USERS / 10;
+
+/// How long a user's status is trusted before Postgres is asked again, which bounds how long
+/// access tokens of a disabled user keep working on instances that did not disable them.
+const STATUS_TTL: Duration = Duration::from_secs(30);
+
+struct CacheEntry {
+    state: Option<AccountState>,
+    cached_until: Instant,
+}
+
+/// Account states checked on every authenticated request, cached in memory so that check
+/// rarely hits the database.
+#[derive(Default)]
+pub struct AccountStatusCache {
+    cache: Mutex<HashMap<Uuid, CacheEntry>>,
+}
+
+impl AccountStatusCache {
+    /// `None` once the user has been deleted.
+    #[tracing::instrument(name = "Checking account state", skip(self, db))]
+    pub async fn get(
+        &self,
+        user_id: Uuid,
+        db: &impl DbContext,
+    ) -> Result<Option<AccountState>, AppError> {
+        if let Some(state) = self.cached(user_id)? {
+            return Ok(state);
+        }
+
+        let state = get_account_state(user_id, db).await?;
+        self.cache_entry(user_id, state.clone())?;
+
+        Ok(state)
+    }
+
+    /// Makes a status change on this instance apply to the next request right away.
+    pub fn invalidate(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.cache
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))?
+            .remove(&user_id);
+
+        Ok(())
+    }
+
+    fn cached(&self, user_id: Uuid) -> Result<Option<Option<AccountState>>, AppError> {
+        let cache = self
+            .cache
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+        Ok(cache
+            .get(&user_id)
+            .filter(|entry| entry.cached_until > Instant::now())
+            .map(|entry| entry.state.clone()))
+    }
+
+    fn cache_entry(&self, user_id: Uuid, state: Option<AccountState>) -> Result<(), AppError> {
+        let now = Instant::now();
+        let mut cache = self
+            .cache
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+        if !cache.contains_key(&user_id) && cache.len() >= MAX_CACHED_USERS {
+            evict(&mut cache, now);
+        }
+
+        cache.insert(
+            user_id,
+            CacheEntry {
+                state,
+                cached_until: now + STATUS_TTL,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// Drops expired entries, then the ones closest to expiring when every entry is still fresh so
+/// the map stays bounded.
+fn evict(cache: &mut HashMap<Uuid, CacheEntry>, now: Instant) {
+    cache.retain(|_, entry| entry.cached_until > now);
+
+    if cache.len() < MAX_CACHED_USERS {
+        return;
+    }
+
+    let mut oldest = cache
+        .iter()
+        .map(|(user_id, entry)| (entry.cached_until, *user_id))
+        .collect::<Vec<_>>();
+    oldest.sort_unstable();
+
+    for (_, user_id) in oldest.into_iter().take(EVICTION_BATCH) {
+        cache.remove(&user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_ok;
+    use uuid::Uuid;
+
+    use crate::{db::MockDbContext, features::auth::repository::AccountStateData};
+
+    use super::{AccountStatusCache, MAX_CACHED_USERS};
+
+    #[tokio::test]
+    async fn a_state_is_looked_up_once() {
+        let cache = AccountStatusCache::default();
+        let user_id = Uuid::new_v4();
+        let mut db_mock = MockDbContext::new();
+
+        db_mock
+            .expect_fetch_optional::<AccountStateData>()
+            .times(1)
+            .returning(|_| Ok(None));
+
+        assert_ok!(cache.get(user_id, &db_mock).await);
+        assert!(cache.get(user_id, &db_mock).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn an_invalidated_state_is_looked_up_again() {
+        let cache = AccountStatusCache::default();
+        let user_id = Uuid::new_v4();
+        let mut db_mock = MockDbContext::new();
+
+        db_mock
+            .expect_fetch_optional::<AccountStateData>()
+            .times(2)
+            .returning(|_| Ok(None));
+
+        assert_ok!(cache.get(user_id, &db_mock).await);
+        assert_ok!(cache.invalidate(user_id));
+        assert_ok!(cache.get(user_id, &db_mock).await);
+    }
+
+    #[test]
+    fn the_number_of_cached_users_is_bounded() {
+        let cache = AccountStatusCache::default();
+
+        for _ in 0..=MAX_CACHED_USERS {
+            assert_ok!(cache.cache_entry(Uuid::new_v4(), None));
+        }
+
+        assert!(cache.cache.lock().unwrap().len() <= MAX_CACHED_USERS);
+    }
+}
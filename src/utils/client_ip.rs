@@ -0,0 +1,30 @@
+use std::net::{IpAddr, SocketAddr};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+
+use crate::errors::AppError;
+
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(addr) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or(AppError::UnexpectedError(
+                "Client address was not found.".into(),
+            ))?;
+
+        Ok(Self(addr.ip()))
+    }
+}
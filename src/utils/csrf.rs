@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, HeaderName, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use cookie::{time::Duration, Cookie};
+use headers::HeaderMapExt;
+use reqwest::{
+    header::{ORIGIN, REFERER},
+    Url,
+};
+
+use crate::{
+    app_state::AppState,
+    configurations::JwtSettings,
+    errors::AppError,
+    utils::{randomizer::generate_random_string, token_hash::hash_token},
+};
+
+const CSRF_TOKEN_LENGTH: usize = 32;
+pub const CSRF_COOKIE: &str = "csrf_token";
+/// Clients echo the token they were given in this header, next to the cookie.
+pub static CSRF_HEADER: HeaderName = HeaderName::from_static("x-csrf-token");
+
+pub fn generate_csrf_token() -> String {
+    generate_random_string(CSRF_TOKEN_LENGTH)
+}
+
+/// Lasts as long as a refresh token, so a session never outlives the token that protects it.
+pub fn csrf_cookie(value: String, jwt_settings: &JwtSettings) -> Cookie<'static> {
+    let settings = &jwt_settings.refresh_cookie;
+
+    let mut cookie = cookie::CookieBuilder::new(CSRF_COOKIE, value)
+        .http_only(true)
+        .max_age(Duration::seconds(jwt_settings.refresh_token_ttl_seconds))
+        .path(settings.path.to_string())
+        .same_site((&settings.same_site).into())
+        .secure(settings.secure);
+
+    if let Some(domain) = &settings.domain {
+        cookie = cookie.domain(domain.to_string());
+    }
+
+    cookie.build()
+}
+
+/// Guards requests authenticated by the refresh or session cookie alone. Those must come from
+/// `client_url` and carry the double-submitted CSRF token. Requests with an `Authorization`
+/// header are left alone: a cross-site page cannot set one.
+pub async fn csrf_protect(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let cookie = headers.typed_get::<headers::Cookie>();
+    let has_auth_cookie = cookie.as_ref().is_some_and(|c| {
+        c.get(&app_state.jwt_settings.refresh_cookie.name).is_some()
+            || c.get(&app_state.session_settings.cookie_name).is_some()
+    });
+
+    if is_safe(request.method()) || !has_auth_cookie || headers.contains_key(AUTHORIZATION) {
+        return next.run(request).await;
+    }
+
+    let result = check_origin(headers, &app_state.client_url).and_then(|_| {
+        check_token(headers, cookie.as_ref().and_then(|c| c.get(CSRF_COOKIE)))
+    });
+
+    match result {
+        Ok(_) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Browsers send `Origin` on cross-site POSTs. `Referer` is the fallback, and requests with
+/// neither rely on the token alone.
+fn check_origin(headers: &HeaderMap, client_url: &str) -> Result<(), AppError> {
+    let source = headers
+        .get(ORIGIN)
+        .or_else(|| headers.get(REFERER))
+        .and_then(|v| v.to_str().ok());
+
+    let source = match source {
+        Some(data) => data,
+        None => return Ok(()),
+    };
+
+    match (origin_of(source), origin_of(client_url)) {
+        (Some(source), Some(client)) if source == client => Ok(()),
+        _ => Err(AppError::ForbiddenError("Request origin is not allowed.".into())),
+    }
+}
+
+fn origin_of(url: &str) -> Option<String> {
+    Url::parse(url).ok().map(|u| u.origin().ascii_serialization())
+}
+
+fn check_token(headers: &HeaderMap, cookie: Option<&str>) -> Result<(), AppError> {
+    let header = headers.get(&CSRF_HEADER).and_then(|v| v.to_str().ok());
+
+    // Comparing digests keeps the comparison time independent of the token.
+    match (header, cookie) {
+        (Some(header), Some(cookie)) if hash_token(header) == hash_token(cookie) => Ok(()),
+        _ => Err(AppError::ForbiddenError("CSRF token is missing or invalid.".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue};
+    use claims::{assert_err, assert_ok};
+
+    use super::{check_origin, check_token, CSRF_HEADER};
+
+    fn headers(name: &str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            HeaderValue::from_str(value).unwrap(),
+        );
+
+        headers
+    }
+
+    #[test]
+    fn only_the_client_origin_is_allowed() {
+        let client_url = "http://localhost:3000";
+
+        assert_ok!(check_origin(&headers("origin", "http://localhost:3000"), client_url));
+        assert_ok!(check_origin(&headers("referer", "http://localhost:3000/login"), client_url));
+        assert_ok!(check_origin(&HeaderMap::new(), client_url));
+        assert_err!(check_origin(&headers("origin", "https://evil.example"), client_url));
+        assert_err!(check_origin(&headers("referer", "http://localhost:3001/"), client_url));
+    }
+
+    #[test]
+    fn the_header_must_match_the_cookie() {
+        let valid = headers(CSRF_HEADER.as_str(), "token");
+
+        assert_ok!(check_token(&valid, Some("token")));
+        assert_err!(check_token(&valid, Some("other")));
+        assert_err!(check_token(&valid, None));
+        assert_err!(check_token(&HeaderMap::new(), Some("token")));
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+
+use crate::{configurations::EmailClientSettings, errors::AppError};
+
+pub struct EmailClient {
+    http_client: Client,
+    base_url: String,
+    sender: String,
+    authorization_token: Secret<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+}
+
+impl EmailClient {
+    pub fn new(settings: &EmailClientSettings) -> Self {
+        let http_client = Client::builder()
+            .timeout(Duration::from_millis(settings.timeout_milliseconds))
+            .build()
+            .expect("Failed to build email HTTP client.");
+
+        Self {
+            http_client,
+            base_url: settings.base_url.trim_end_matches('/').to_string(),
+            sender: settings.sender_email.to_string(),
+            authorization_token: settings.authorization_token.clone(),
+        }
+    }
+
+    #[tracing::instrument(name = "Sending email", skip(self, html_body, text_body))]
+    pub async fn send_email(
+        &self,
+        recipient: &str,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+    ) -> Result<(), AppError> {
+        let body = SendEmailRequest {
+            from: &self.sender,
+            to: recipient,
+            subject,
+            html_body,
+            text_body,
+        };
+
+        self.http_client
+            .post(format!("{}/email", self.base_url))
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&body)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| AppError::UnexpectedError(format!("failed to send email: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use secrecy::Secret;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::configurations::EmailClientSettings;
+
+    use super::EmailClient;
+
+    fn email_client(base_url: String) -> EmailClient {
+        EmailClient::new(&EmailClientSettings {
+            base_url,
+            sender_email: "no-reply@example.com".into(),
+            authorization_token: Secret::new("token".into()),
+            timeout_milliseconds: 200,
+        })
+    }
+
+    #[tokio::test]
+    async fn send_email_posts_to_the_email_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/email"))
+            .and(header("Content-Type", "application/json"))
+            .and(header_exists("X-Postmark-Server-Token"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let result = email_client(server.uri())
+            .send_email("ada@example.com", "Subject", "<p>Body</p>", "Body")
+            .await;
+
+        assert_ok!(result);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_when_the_server_errors_or_times_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(1)))
+            .mount(&server)
+            .await;
+        let client = email_client(server.uri());
+
+        assert_err!(client.send_email("ada@example.com", "Subject", "<p>Body</p>", "Body").await);
+        assert_err!(client.send_email("ada@example.com", "Subject", "<p>Body</p>", "Body").await);
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header,
+};
+use simple_asn1::ASN1Block;
+
+use crate::{
+    configurations::{JwtAlgorithm, JwtKeySettings, JwtSettings},
+    errors::AppError,
+};
+
+/// Access token keys loaded from the PEM files listed in `JwtSettings`. Every configured key
+/// is accepted for verification so tokens signed before a rotation stay valid, while only
+/// `signing_key_id` is used to sign new ones.
+pub struct JwtKeys {
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, (Algorithm, DecodingKey)>,
+    jwk_set: JwkSet,
+}
+
+impl JwtKeys {
+    pub fn from_settings(settings: &JwtSettings) -> Result<Self, AppError> {
+        let mut decoding_keys = HashMap::new();
+        let mut jwks = Vec::new();
+
+        for key in &settings.keys {
+            let public_pem = read_key_file(&key.public_key_path)?;
+
+            let decoding_key = match key.algorithm {
+                JwtAlgorithm::RS256 => DecodingKey::from_rsa_pem(&public_pem),
+                JwtAlgorithm::EdDSA => DecodingKey::from_ed_pem(&public_pem),
+            }
+            .map_err(|e| key_error(&key.kid, e))?;
+
+            decoding_keys.insert(key.kid.to_string(), (key.algorithm.into(), decoding_key));
+            jwks.push(public_key_to_jwk(key, &public_pem)?);
+        }
+
+        let signing_key = settings
+            .keys
+            .iter()
+            .find(|key| key.kid == settings.signing_key_id)
+            .ok_or(AppError::UnexpectedError(format!(
+                "signing key {} is not configured.",
+                settings.signing_key_id
+            )))?;
+
+        let private_key_path = signing_key.private_key_path.as_ref().ok_or(
+            AppError::UnexpectedError(format!(
+                "signing key {} has no private key.",
+                signing_key.kid
+            )),
+        )?;
+        let private_pem = read_key_file(private_key_path)?;
+
+        let encoding_key = match signing_key.algorithm {
+            JwtAlgorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem),
+            JwtAlgorithm::EdDSA => EncodingKey::from_ed_pem(&private_pem),
+        }
+        .map_err(|e| key_error(&signing_key.kid, e))?;
+
+        Ok(Self {
+            signing_kid: signing_key.kid.to_string(),
+            signing_algorithm: signing_key.algorithm.into(),
+            encoding_key,
+            decoding_keys,
+            jwk_set: JwkSet { keys: jwks },
+        })
+    }
+
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = Some(self.signing_kid.to_string());
+
+        header
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self, kid: &str) -> Option<&(Algorithm, DecodingKey)> {
+        self.decoding_keys.get(kid)
+    }
+
+    pub fn jwk_set(&self) -> &JwkSet {
+        &self.jwk_set
+    }
+}
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(value: JwtAlgorithm) -> Self {
+        match value {
+            JwtAlgorithm::RS256 => Algorithm::RS256,
+            JwtAlgorithm::EdDSA => Algorithm::EdDSA,
+        }
+    }
+}
+
+fn read_key_file(path: &str) -> Result<Vec<u8>, AppError> {
+    std::fs::read(path)
+        .map_err(|e| AppError::UnexpectedError(format!("failed to read key {}: {}", path, e)))
+}
+
+fn key_error(kid: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::UnexpectedError(format!("invalid key {}: {}", kid, e))
+}
+
+fn public_key_to_jwk(key: &JwtKeySettings, public_pem: &[u8]) -> Result<Jwk, AppError> {
+    let public_key = subject_public_key(public_pem).map_err(|e| key_error(&key.kid, e))?;
+
+    let (key_algorithm, algorithm) = match key.algorithm {
+        JwtAlgorithm::EdDSA => (
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(public_key),
+            }),
+        ),
+        JwtAlgorithm::RS256 => {
+            let (n, e) = rsa_components(&public_key).map_err(|e| key_error(&key.kid, e))?;
+
+            (
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(n),
+                    e: URL_SAFE_NO_PAD.encode(e),
+                }),
+            )
+        }
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(key.kid.to_string()),
+            ..Default::default()
+        },
+        algorithm,
+    })
+}
+
+/// The raw `subjectPublicKey` bits of a PEM encoded SubjectPublicKeyInfo.
+fn subject_public_key(public_pem: &[u8]) -> Result<Vec<u8>, String> {
+    let pem = pem::parse(public_pem).map_err(|e| e.to_string())?;
+    let blocks = simple_asn1::from_der(pem.contents()).map_err(|e| e.to_string())?;
+
+    match blocks.first() {
+        Some(ASN1Block::Sequence(_, fields)) => match fields.get(1) {
+            Some(ASN1Block::BitString(_, _, bits)) => Ok(bits.to_vec()),
+            _ => Err("missing subjectPublicKey".into()),
+        },
+        _ => Err("not a SubjectPublicKeyInfo".into()),
+    }
+}
+
+/// Big-endian modulus and exponent of a DER encoded RSAPublicKey.
+fn rsa_components(public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let blocks = simple_asn1::from_der(public_key).map_err(|e| e.to_string())?;
+
+    match blocks.first() {
+        Some(ASN1Block::Sequence(_, fields)) => match fields.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                Ok((n.to_bytes_be().1, e.to_bytes_be().1))
+            }
+            _ => Err("missing RSA modulus or exponent".into()),
+        },
+        _ => Err("not an RSAPublicKey".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok, assert_some};
+    use jsonwebtoken::jwk::AlgorithmParameters;
+    use secrecy::Secret;
+
+    use crate::configurations::{
+        CookieSameSite, JwtAlgorithm, JwtKeySettings, JwtSettings, RefreshCookieSettings,
+    };
+
+    use super::{rsa_components, subject_public_key, JwtKeys};
+
+    const TEST_RSA_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQC8rP8RGDbK9+94TqIq5HfWQh3m
+MDc30Li8lF5MKUbsZYD1ipwG44OM5SWSFDSBSuQ1N2N9YopkMi6XDnEYTHIz1WfF
+VWkPCfQre0+/dbE/+7OUQrYEzVgp003GLSaz2xQ0/Ar7Dl725wB0+TzGMTnxT2Lt
+EgQO591pDPnkFrWqLQIDAQAB
+-----END PUBLIC KEY-----";
+
+    fn dev_key(kid: &str, with_private_key: bool) -> JwtKeySettings {
+        key_pair(kid, "dev-signing", with_private_key)
+    }
+
+    /// `name` is one of the development key pairs in `configurations/keys`.
+    fn key_pair(kid: &str, name: &str, with_private_key: bool) -> JwtKeySettings {
+        JwtKeySettings {
+            kid: kid.into(),
+            algorithm: JwtAlgorithm::EdDSA,
+            private_key_path: with_private_key.then(|| format!("configurations/keys/{}.pem", name)),
+            public_key_path: format!("configurations/keys/{}.pub.pem", name),
+        }
+    }
+
+    fn test_settings(signing_key_id: &str, keys: Vec<JwtKeySettings>) -> JwtSettings {
+        JwtSettings {
+            issuer: "test".into(),
+            audience: "test".into(),
+            signing_key_id: signing_key_id.into(),
+            keys,
+            refresh_token_secret: Secret::new("test".into()),
+            access_token_ttl_seconds: 60,
+            refresh_token_ttl_seconds: 3600,
+            refresh_cookie: RefreshCookieSettings {
+                name: "rt".into(),
+                domain: None,
+                path: "/".into(),
+                same_site: CookieSameSite::Lax,
+                secure: true,
+            },
+        }
+    }
+
+    #[test]
+    fn configured_keys_are_published_as_jwks() {
+        let settings = test_settings("current", vec![dev_key("current", true), dev_key("old", false)]);
+
+        let keys = assert_ok!(JwtKeys::from_settings(&settings));
+
+        assert_eq!(2, keys.jwk_set().keys.len());
+        let jwk = assert_some!(keys.jwk_set().find("current"));
+        match &jwk.algorithm {
+            AlgorithmParameters::OctetKeyPair(params) => assert_eq!(43, params.x.len()),
+            _ => panic!("expected an OKP key"),
+        }
+    }
+
+    fn sign(keys: &JwtKeys) -> String {
+        assert_ok!(jsonwebtoken::encode(
+            &keys.header(),
+            &serde_json::json!({ "exp": usize::MAX }),
+            keys.encoding_key(),
+        ))
+    }
+
+    fn verify(keys: &JwtKeys, kid: &str, token: &str) -> bool {
+        let (algorithm, decoding_key) = assert_some!(keys.decoding_key(kid));
+        let mut validation = jsonwebtoken::Validation::new(*algorithm);
+        validation.required_spec_claims.clear();
+
+        jsonwebtoken::decode::<serde_json::Value>(token, decoding_key, &validation).is_ok()
+    }
+
+    #[test]
+    fn tokens_signed_before_a_rotation_are_still_verified() {
+        let before = test_settings("old", vec![key_pair("old", "dev-signing", true)]);
+        let after = test_settings(
+            "new",
+            vec![key_pair("new", "dev-rotated", true), key_pair("old", "dev-signing", false)],
+        );
+        let old_keys = assert_ok!(JwtKeys::from_settings(&before));
+        let new_keys = assert_ok!(JwtKeys::from_settings(&after));
+
+        let old_token = sign(&old_keys);
+        let new_token = sign(&new_keys);
+
+        assert_eq!(Some("new".to_string()), new_keys.header().kid);
+        assert!(verify(&new_keys, "old", &old_token));
+        assert!(verify(&new_keys, "new", &new_token));
+        // The two key pairs are distinct, so each token only verifies with its own key.
+        assert!(!verify(&new_keys, "new", &old_token));
+        assert!(!verify(&new_keys, "old", &new_token));
+    }
+
+    #[test]
+    fn a_signing_key_without_a_private_key_is_rejected() {
+        let settings = test_settings("current", vec![dev_key("current", false)]);
+
+        assert_err!(JwtKeys::from_settings(&settings).map(|_| ()));
+    }
+
+    #[test]
+    fn an_unknown_signing_key_is_rejected() {
+        let settings = test_settings("missing", vec![dev_key("current", true)]);
+
+        assert_err!(JwtKeys::from_settings(&settings).map(|_| ()));
+    }
+
+    #[test]
+    fn rsa_public_keys_are_split_into_modulus_and_exponent() {
+        let public_key = assert_ok!(subject_public_key(TEST_RSA_PUBLIC_KEY.as_bytes()));
+
+        let (n, e) = assert_ok!(rsa_components(&public_key));
+
+        assert_eq!(128, n.len());
+        assert_eq!(vec![1, 0, 1], e);
+    }
+}
@@ -3,18 +3,35 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use axum::{
     extract::{FromRef, FromRequestParts},
-    http::request::Parts,
+    http::{request::Parts, HeaderName},
     RequestPartsExt,
 };
 use axum_extra::TypedHeader;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use headers::{authorization::Bearer, Authorization};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use jsonwebtoken::{
+    decode, decode_header, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, TokenData,
+    Validation,
+};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{app_state::AppState, configurations::JwtSettings, errors::AppError};
+use crate::{
+    app_state::AppState,
+    configurations::{JwtSettings, SessionMode},
+    errors::AppError,
+    features::{
+        admin::repository::add_audit_entry,
+        api_keys::repository::{get_api_key_by_key, touch_api_key},
+        auth::repository::get_roles_by_user_id,
+    },
+    utils::{
+        jwks::JwtKeys,
+        roles::{Admin, Role},
+        scopes::ALL_SCOPES,
+    },
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct Claims {
@@ -22,63 +39,168 @@ pub struct Claims {
     pub aud: String,
     pub exp: usize,
     pub id: Uuid,
+    pub jti: Uuid,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Space separated, as in OAuth 2.0.
+    #[serde(default)]
+    pub scope: String,
+    /// Set when an administrator acts as this user, as in RFC 8693.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
 }
 
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Actor {
+    pub sub: Uuid,
+}
+
+/// Impersonation tokens never outlive this, however long regular access tokens are.
+pub const IMPERSONATION_TTL_SECONDS: i64 = 300;
+
+/// Access tokens are signed with the asymmetric keys in `JwtKeys` so other services can verify
+/// them through the JWKS endpoint. Refresh tokens never leave this service and keep using the
+/// shared `refresh_token_secret`.
 pub fn generate_jwt(
     user_id: Uuid,
+    roles: &[String],
+    scopes: &[&str],
     jwt_settings: &JwtSettings,
+    jwt_keys: &JwtKeys,
     is_refresh_token: bool,
-) -> Result<String, jsonwebtoken::errors::Error> {
+) -> Result<(String, DateTime<Utc>), jsonwebtoken::errors::Error> {
+    let ttl_seconds = if is_refresh_token {
+        jwt_settings.refresh_token_ttl_seconds
+    } else {
+        jwt_settings.access_token_ttl_seconds
+    };
+    let expires_at = Utc::now() + Duration::seconds(ttl_seconds);
+
     let claims = Claims {
         iss: jwt_settings.issuer.to_string(),
         aud: jwt_settings.audience.to_string(),
         id: user_id,
-        exp: if is_refresh_token {
-            (Utc::now() + Duration::try_weeks(7).unwrap()).timestamp() as usize
-        } else {
-            (Utc::now() + Duration::try_minutes(12).unwrap()).timestamp() as usize
-        },
+        exp: expires_at.timestamp() as usize,
+        jti: Uuid::new_v4(),
+        roles: roles.to_vec(),
+        scope: scopes.join(" "),
+        act: None,
     };
 
-    let secret_key = if is_refresh_token {
-        jwt_settings.refresh_token_secret.expose_secret()
+    let token = if is_refresh_token {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(jwt_settings.refresh_token_secret.expose_secret().as_bytes()),
+        )?
     } else {
-        jwt_settings.access_token_secret.expose_secret()
+        encode(&jwt_keys.header(), &claims, jwt_keys.encoding_key())?
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret_key.as_bytes()),
-    )
+    Ok((token, expires_at))
+}
+
+/// An access token for `user_id` carrying `actor_id` in its `act` claim. No refresh token is
+/// issued, so an impersonation ends when this token expires.
+pub fn generate_impersonation_jwt(
+    user_id: Uuid,
+    actor_id: Uuid,
+    roles: &[String],
+    scopes: &[&str],
+    jwt_settings: &JwtSettings,
+    jwt_keys: &JwtKeys,
+) -> Result<(String, DateTime<Utc>), jsonwebtoken::errors::Error> {
+    let ttl_seconds = jwt_settings
+        .access_token_ttl_seconds
+        .min(IMPERSONATION_TTL_SECONDS);
+    let expires_at = Utc::now() + Duration::seconds(ttl_seconds);
+
+    let claims = Claims {
+        iss: jwt_settings.issuer.to_string(),
+        aud: jwt_settings.audience.to_string(),
+        id: user_id,
+        exp: expires_at.timestamp() as usize,
+        jti: Uuid::new_v4(),
+        roles: roles.to_vec(),
+        scope: scopes.join(" "),
+        act: Some(Actor { sub: actor_id }),
+    };
+
+    let token = encode(&jwt_keys.header(), &claims, jwt_keys.encoding_key())?;
+
+    Ok((token, expires_at))
 }
 
 pub fn decode_jwt(
     token: &str,
     jwt_settings: &JwtSettings,
+    jwt_keys: &JwtKeys,
     is_refresh_token: bool,
 ) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
-    let mut validation = Validation::new(jsonwebtoken::Algorithm::HS256);
+    if is_refresh_token {
+        let validation = validation(jsonwebtoken::Algorithm::HS256, jwt_settings);
+
+        return decode(
+            token,
+            &DecodingKey::from_secret(jwt_settings.refresh_token_secret.expose_secret().as_bytes()),
+            &validation,
+        );
+    }
+
+    let kid = decode_header(token)?.kid.ok_or(ErrorKind::InvalidToken)?;
+    let (algorithm, decoding_key) = jwt_keys
+        .decoding_key(&kid)
+        .ok_or(ErrorKind::InvalidToken)?;
+
+    decode(token, decoding_key, &validation(*algorithm, jwt_settings))
+}
+
+fn validation(algorithm: jsonwebtoken::Algorithm, jwt_settings: &JwtSettings) -> Validation {
+    let mut validation = Validation::new(algorithm);
     validation.set_audience(&[jwt_settings.audience.to_string()]);
     validation.set_issuer(&[jwt_settings.issuer.to_string()]);
 
-    let secret_key = if is_refresh_token {
-        jwt_settings.refresh_token_secret.expose_secret()
-    } else {
-        jwt_settings.access_token_secret.expose_secret()
-    };
-
-    decode(
-        token,
-        &DecodingKey::from_secret(secret_key.as_bytes()),
-        &validation,
-    )
+    validation
 }
 
+/// Machine clients send an API key in this header instead of a bearer token.
+pub static API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
 pub struct AuthUser {
     pub id: Uuid,
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+    pub api_key_id: Option<Uuid>,
+    /// The administrator acting as this user, if any.
+    pub impersonator_id: Option<Uuid>,
 }
 
+impl AuthUser {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Guards operations that must only ever be done by the account owner themselves.
+    pub fn reject_impersonation(&self) -> Result<(), AppError> {
+        if self.impersonator_id.is_some() {
+            return Err(AppError::ForbiddenError(
+                "This operation is not available while impersonating a user.".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Marks a request whose impersonation has already been audited, as several extractors of the
+/// same handler may each authenticate it.
+#[derive(Clone)]
+struct ImpersonationAudited;
+
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthUser
 where
@@ -88,18 +210,160 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map_err(|_| AppError::UnauthorizedError("Bearer token was not found.".into()))?;
-
         let app_state = Arc::from_ref(state);
 
-        let token_data = decode_jwt(bearer.token(), &app_state.jwt_settings, false)
-            .map_err(|e| AppError::UnauthorizedError(e.to_string()))?;
+        let user = match parts.headers.get(&API_KEY_HEADER) {
+            Some(key) => {
+                let key = key
+                    .to_str()
+                    .map_err(|_| AppError::UnauthorizedError("API key is not valid.".into()))?;
+
+                authenticate_api_key(key, &app_state).await?
+            }
+            None => match app_state.session_settings.mode {
+                SessionMode::Jwt => authenticate_bearer(parts, &app_state).await?,
+                SessionMode::Server => authenticate_session(parts, &app_state).await?,
+            },
+        };
 
-        Ok(Self {
-            id: token_data.claims.id,
-        })
+        match app_state.account_status_cache.get(user.id, &app_state.pool).await? {
+            Some(state) => state.ensure_active()?,
+            None => return Err(AppError::UnauthorizedError("User no longer exists.".into())),
+        }
+
+        if let Some(impersonator_id) = user.impersonator_id {
+            ensure_active_admin(impersonator_id, &app_state).await?;
+
+            if parts.extensions.get::<ImpersonationAudited>().is_none() {
+                let request = format!("{} {}", parts.method, parts.uri.path());
+                tracing::info!(%impersonator_id, user_id = %user.id, %request, "Impersonated request");
+
+                add_audit_entry(
+                    impersonator_id,
+                    "impersonation.request",
+                    Some(user.id),
+                    Some(&request),
+                    &app_state.pool,
+                )
+                .await?;
+                parts.extensions.insert(ImpersonationAudited);
+            }
+        }
+
+        Ok(user)
     }
 }
+
+/// An impersonation token stops working as soon as the administrator who started it is
+/// restricted or loses the role, rather than when it expires.
+async fn ensure_active_admin(admin_id: Uuid, app_state: &AppState) -> Result<(), AppError> {
+    let is_active = matches!(
+        app_state.account_status_cache.get(admin_id, &app_state.pool).await?,
+        Some(state) if state.ensure_active().is_ok()
+    );
+    let is_admin = get_roles_by_user_id(admin_id, &app_state.pool)
+        .await?
+        .iter()
+        .any(|r| r == Admin::NAME);
+
+    if !is_active || !is_admin {
+        return Err(AppError::UnauthorizedError(
+            "The impersonating administrator is no longer authorized.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn authenticate_bearer(parts: &mut Parts, app_state: &AppState) -> Result<AuthUser, AppError> {
+    let TypedHeader(Authorization(bearer)) = parts
+        .extract::<TypedHeader<Authorization<Bearer>>>()
+        .await
+        .map_err(|_| AppError::UnauthorizedError("Bearer token was not found.".into()))?;
+
+    let token_data = decode_jwt(
+        bearer.token(),
+        &app_state.jwt_settings,
+        &app_state.jwt_keys,
+        false,
+    )
+        .map_err(|e| AppError::UnauthorizedError(e.to_string()))?;
+
+    let expires_at = DateTime::from_timestamp(token_data.claims.exp as i64, 0)
+        .unwrap_or_else(Utc::now);
+
+    if app_state
+        .token_denylist
+        .is_revoked(token_data.claims.jti, expires_at, &app_state.pool)
+        .await?
+    {
+        return Err(AppError::UnauthorizedError("Token has been revoked.".into()));
+    }
+
+    Ok(AuthUser {
+        id: token_data.claims.id,
+        scopes: token_data
+            .claims
+            .scope
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+        roles: token_data.claims.roles,
+        api_key_id: None,
+        impersonator_id: token_data.claims.act.map(|act| act.sub),
+    })
+}
+
+async fn authenticate_session(parts: &mut Parts, app_state: &AppState) -> Result<AuthUser, AppError> {
+    let TypedHeader(cookie) = parts
+        .extract::<TypedHeader<headers::Cookie>>()
+        .await
+        .map_err(|_| AppError::UnauthorizedError("Session cookie was not found.".into()))?;
+
+    let session_id = cookie
+        .get(&app_state.session_settings.cookie_name)
+        .ok_or(AppError::UnauthorizedError("Session cookie was not found.".into()))?;
+
+    let session = app_state
+        .session_store
+        .get(session_id)
+        .await?
+        .ok_or(AppError::UnauthorizedError("Session has expired.".into()))?;
+
+    let roles = get_roles_by_user_id(session.user_id, &app_state.pool).await?;
+
+    Ok(AuthUser {
+        id: session.user_id,
+        roles,
+        scopes: ALL_SCOPES.iter().map(|s| s.to_string()).collect(),
+        api_key_id: None,
+        impersonator_id: None,
+    })
+}
+
+async fn authenticate_api_key(key: &str, app_state: &AppState) -> Result<AuthUser, AppError> {
+    let api_key = get_api_key_by_key(key, &app_state.pool)
+        .await?
+        .ok_or(AppError::UnauthorizedError("API key is not valid.".into()))?;
+
+    if api_key.revoked_at.is_some() {
+        return Err(AppError::UnauthorizedError("API key has been revoked.".into()));
+    }
+
+    if api_key.expires_at <= Utc::now() {
+        return Err(AppError::UnauthorizedError("API key has expired.".into()));
+    }
+
+    touch_api_key(api_key.id, &app_state.pool).await?;
+
+    let roles = get_roles_by_user_id(api_key.user_id, &app_state.pool).await?;
+
+    Ok(AuthUser {
+        id: api_key.user_id,
+        roles,
+        // Scopes are stored with every key, see `NewApiKey`.
+        scopes: api_key.scopes.unwrap_or_default(),
+        api_key_id: Some(api_key.id),
+        impersonator_id: None,
+    })
+}
@@ -1,3 +1,15 @@
+pub mod account_status_cache;
+pub mod client_ip;
+pub mod csrf;
+pub mod email_client;
+pub mod jwks;
 pub mod jwt;
 pub mod password_hasher;
-pub mod randomizer;
\ No newline at end of file
+pub mod randomizer;
+pub mod rate_limiter;
+pub mod roles;
+pub mod scopes;
+pub mod session_store;
+pub mod tenant;
+pub mod token_denylist;
+pub mod token_hash;
\ No newline at end of file
@@ -1,59 +1,274 @@
+use std::collections::HashMap;
+
+use crate::configurations::PasswordHashingSettings;
 use crate::errors::AppError;
-use argon2::PasswordVerifier;
+use argon2::{Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordVerifier, Version};
 use async_trait::async_trait;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
-use password_hash::{PasswordHasher, SaltString};
+use password_hash::{PasswordHash, PasswordHasher, SaltString};
+use secrecy::{ExposeSecret, Secret};
 
-pub struct ServerPwdHasher;
+#[derive(Clone)]
+pub struct ServerPwdHasher {
+    params : Params,
+    peppers : HashMap<String, Secret<String>>
+}
 
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait PwdHasher {
     async fn hash_password(&self, password : &str) -> Result<String, AppError>;
     async fn verify_password(&self, password : &str, hashed_password : &str) -> Result<(), AppError>;
+    /// Whether `hashed_password` was made with settings other than the current ones.
+    fn needs_rehash(&self, hashed_password : &str) -> bool;
+}
+
+impl ServerPwdHasher {
+    pub fn new(settings : &PasswordHashingSettings) -> Result<Self, AppError> {
+        let settings_error = |e : argon2::Error| AppError::UnexpectedError(format!("invalid password hashing settings: {}", e));
+
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(settings.memory_cost_kib)
+            .t_cost(settings.time_cost)
+            .p_cost(settings.parallelism);
+
+        if let Some(id) = &settings.current_pepper_id {
+            builder.keyid(KeyId::new(id.as_bytes()).map_err(settings_error)?);
+        }
+
+        let params = builder.build().map_err(settings_error)?;
+
+        let peppers = settings.peppers
+            .iter()
+            .map(|(id, secret)| (id.to_string(), secret.clone()))
+            .collect();
+
+        Ok(Self { params, peppers })
+    }
+
+    /// The pepper a hash was made with, looked up by the `keyid` stored in it.
+    fn pepper_for(&self, params : &Params) -> Result<Option<Vec<u8>>, AppError> {
+        if params.keyid().is_empty() {
+            return Ok(None);
+        }
+
+        let id = String::from_utf8_lossy(params.keyid());
+
+        match self.peppers.get(id.as_ref()) {
+            Some(secret) => Ok(Some(secret.expose_secret().as_bytes().to_vec())),
+            None => Err(AppError::UnexpectedError(format!("pepper {} is not configured.", id)))
+        }
+    }
 }
 
 #[async_trait]
 impl PwdHasher for ServerPwdHasher {
     async fn hash_password(&self, password : &str) -> Result<String, AppError> {
         let pwd = password.to_string();
+        let params = self.params.clone();
+        let pepper = self.pepper_for(&params)?;
         tokio::task::spawn_blocking(move || {
-            hash(&pwd)
+            hash(&pwd, params, pepper.as_deref())
         })
         .await
         .map_err(|_| AppError::UnexpectedError("failed spawn.".into()))?
     }
 
     async fn verify_password(&self, password : &str, hashed_password : &str) -> Result<(), AppError> {
+        let pepper = PasswordHash::new(hashed_password)
+            .ok()
+            .and_then(|phc_string| Params::try_from(&phc_string).ok())
+            .map(|params| self.pepper_for(&params))
+            .transpose()?
+            .flatten();
         let hashed_pwd = hashed_password.to_string();
         let pwd = password.to_string();
         tokio::task::spawn_blocking(move || {
-            verify(&pwd, &hashed_pwd)
+            verify(&pwd, &hashed_pwd, pepper.as_deref())
         })
         .await
         .map_err(|_| AppError::UnexpectedError("failed spawn.".into()))?
     }
+
+    fn needs_rehash(&self, hashed_password : &str) -> bool {
+        let phc_string = match PasswordHash::new(hashed_password) {
+            Ok(data) => data,
+            Err(_) => return true
+        };
+
+        let is_current_algorithm = Algorithm::try_from(phc_string.algorithm) == Ok(Algorithm::Argon2id)
+            && phc_string.version == Some(Version::V0x13.into());
+
+        let params = match Params::try_from(&phc_string) {
+            Ok(data) => data,
+            Err(_) => return true
+        };
+
+        !is_current_algorithm
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+            || params.keyid() != self.params.keyid()
+    }
+}
+
+fn argon2(algorithm : Algorithm, version : Version, params : Params, pepper : Option<&[u8]>) -> Result<Argon2<'_>, AppError> {
+    match pepper {
+        Some(secret) => Argon2::new_with_secret(secret, algorithm, version, params)
+            .map_err(|e| AppError::UnexpectedError(e.to_string())),
+        None => Ok(Argon2::new(algorithm, version, params))
+    }
 }
 
-fn hash(password : &str) -> Result<String, AppError> {
-    let params = argon2::Params::new(15000, 2, 1, None).unwrap();
+fn hash(password : &str, params : Params, pepper : Option<&[u8]>) -> Result<String, AppError> {
     let salt = SaltString::generate(rand::thread_rng());
-    let hasher = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
-    
+    let hasher = argon2(Algorithm::Argon2id, Version::V0x13, params, pepper)?;
+
     let result = hasher.hash_password(password.as_bytes(), &salt)
         .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
 
     Ok(result.to_string())
 }
 
-fn verify(password : &str, hashed_password : &str) -> Result<(), AppError> {
-    let phc_string = argon2::PasswordHash::new(hashed_password)
-        .map_err(|e| AppError::UnauthorizedError(e.to_string()))?;
+/// Verifies with the algorithm, version, cost and pepper stored in the PHC string rather than
+/// the current settings, so hashes made before a settings change keep working. Only a password
+/// that does not match is unauthorized; a hash that cannot be read is unexpected.
+fn verify(password : &str, hashed_password : &str, pepper : Option<&[u8]>) -> Result<(), AppError> {
+    let phc_string = PasswordHash::new(hashed_password)
+        .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+    let algorithm = Algorithm::try_from(phc_string.algorithm)
+        .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+    let version = phc_string.version
+        .map(Version::try_from)
+        .transpose()
+        .map_err(|e| AppError::UnexpectedError(e.to_string()))?
+        .unwrap_or_default();
+    let params = Params::try_from(&phc_string)
+        .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
 
-    argon2::Argon2::default()
+    argon2(algorithm, version, params, pepper)?
         .verify_password(password.as_bytes(), &phc_string)
-        .map_err(|e| AppError::UnauthorizedError(e.to_string()))?;
+        .map_err(|e| match e {
+            password_hash::Error::Password => AppError::UnauthorizedError(e.to_string()),
+            _ => AppError::UnexpectedError(e.to_string())
+        })?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use secrecy::Secret;
+
+    use crate::{configurations::PasswordHashingSettings, errors::AppError};
+
+    use super::{PwdHasher, ServerPwdHasher};
+
+    fn test_settings(memory_cost_kib : u32) -> PasswordHashingSettings {
+        PasswordHashingSettings {
+            memory_cost_kib,
+            time_cost : 1,
+            parallelism : 1,
+            current_pepper_id : None,
+            peppers : Default::default()
+        }
+    }
+
+    fn test_hasher(memory_cost_kib : u32) -> ServerPwdHasher {
+        ServerPwdHasher::new(&test_settings(memory_cost_kib)).unwrap()
+    }
+
+    fn peppered_hasher(current_pepper_id : &str, pepper_ids : &[&str]) -> ServerPwdHasher {
+        ServerPwdHasher::new(&PasswordHashingSettings {
+            current_pepper_id : Some(current_pepper_id.into()),
+            peppers : pepper_ids
+                .iter()
+                .map(|id| (id.to_string(), Secret::new(format!("{}-secret", id))))
+                .collect(),
+            ..test_settings(1024)
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn hashes_made_with_old_params_still_verify() {
+        let old_hasher = test_hasher(1024);
+        let new_hasher = test_hasher(2048);
+
+        let hashed_password = old_hasher.hash_password("violet kettle").await.unwrap();
+
+        assert_ok!(new_hasher.verify_password("violet kettle", &hashed_password).await);
+        assert_err!(new_hasher.verify_password("violet kettles", &hashed_password).await);
+    }
+
+    #[tokio::test]
+    async fn only_a_mismatch_is_unauthorized() {
+        let hasher = test_hasher(1024);
+
+        let hashed_password = hasher.hash_password("violet kettle").await.unwrap();
+
+        assert!(matches!(
+            hasher.verify_password("violet kettles", &hashed_password).await,
+            Err(AppError::UnauthorizedError(_))
+        ));
+        assert!(matches!(
+            hasher.verify_password("violet kettle", "not a hash").await,
+            Err(AppError::UnexpectedError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn only_hashes_with_outdated_params_need_rehash() {
+        let old_hasher = test_hasher(1024);
+        let new_hasher = test_hasher(2048);
+
+        let hashed_password = old_hasher.hash_password("violet kettle").await.unwrap();
+
+        assert!(!old_hasher.needs_rehash(&hashed_password));
+        assert!(new_hasher.needs_rehash(&hashed_password));
+    }
+
+    #[test]
+    fn other_algorithms_need_rehash() {
+        let hasher = test_hasher(1024);
+
+        assert!(hasher.needs_rehash("$argon2i$v=19$m=1024,t=1,p=1$c29tZXNhbHQ$iWh06vD8Fy27wf9npn6FXWiCX4K6pW6Ue1Bnzz07Z8A"));
+        assert!(hasher.needs_rehash("not a hash"));
+    }
+
+    #[tokio::test]
+    async fn the_pepper_id_is_stored_in_the_hash() {
+        let hasher = peppered_hasher("p1", &["p1"]);
+
+        let hashed_password = hasher.hash_password("violet kettle").await.unwrap();
+
+        assert!(hashed_password.contains("keyid="));
+        assert_ok!(hasher.verify_password("violet kettle", &hashed_password).await);
+        assert_err!(test_hasher(1024).verify_password("violet kettle", &hashed_password).await);
+    }
+
+    #[tokio::test]
+    async fn hashes_with_a_retired_pepper_verify_and_need_rehash() {
+        let old_hasher = peppered_hasher("p1", &["p1"]);
+        let new_hasher = peppered_hasher("p2", &["p1", "p2"]);
+
+        let hashed_password = old_hasher.hash_password("violet kettle").await.unwrap();
+
+        assert_ok!(new_hasher.verify_password("violet kettle", &hashed_password).await);
+        assert!(new_hasher.needs_rehash(&hashed_password));
+    }
+
+    #[tokio::test]
+    async fn unpeppered_hashes_verify_and_need_rehash() {
+        let hasher = peppered_hasher("p1", &["p1"]);
+
+        let hashed_password = test_hasher(1024).hash_password("violet kettle").await.unwrap();
+
+        assert_ok!(hasher.verify_password("violet kettle", &hashed_password).await);
+        assert!(hasher.needs_rehash(&hashed_password));
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use headers::{authorization::Bearer, Authorization, HeaderMapExt};
+
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    configurations::{RateLimitPolicy, SessionMode},
+    errors::AppError,
+    features::api_keys::repository::get_api_key_by_key,
+    utils::jwt::{decode_jwt, API_KEY_HEADER},
+};
+
+const MAX_TRACKED_KEYS: usize = 10_000;
+/// How many of the least recently used buckets are dropped at once when every tracked key is
+/// still in use.
+const EVICTION_BATCH: usize = MAX_TRACKED_KEYS / 10;
+
+static RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+static RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+static RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_after: i64,
+}
+
+/// Storage for rate limit buckets. Implement this over a shared store (e.g. Redis) when the
+/// limits must hold across several instances of the server.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, AppError>;
+}
+
+/// Keeps the policy it was created with, since one store serves every route group.
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+    policy: RateLimitPolicy,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.policy.refill_per_second).min(self.policy.capacity as f64);
+        self.updated_at = now;
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.policy.capacity as f64
+    }
+
+    fn seconds_until(&self, tokens: f64) -> i64 {
+        let missing = (tokens - self.tokens).max(0.0);
+        (missing / self.policy.refill_per_second).ceil() as i64
+    }
+}
+
+/// Drops buckets that refilled completely, since they behave like new ones. When every key is
+/// still in use, drops the least recently used ones so the map stays bounded.
+fn evict(buckets: &mut HashMap<String, TokenBucket>, now: Instant) {
+    buckets.retain(|_, bucket| {
+        bucket.refill(now);
+        !bucket.is_full()
+    });
+
+    if buckets.len() < MAX_TRACKED_KEYS {
+        return;
+    }
+
+    let mut oldest = buckets
+        .iter()
+        .map(|(key, bucket)| (bucket.updated_at, key.clone()))
+        .collect::<Vec<_>>();
+    oldest.sort_unstable();
+
+    for (_, key) in oldest.into_iter().take(EVICTION_BATCH) {
+        buckets.remove(&key);
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, AppError> {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+        if !buckets.contains_key(key) && buckets.len() >= MAX_TRACKED_KEYS {
+            evict(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(TokenBucket {
+            tokens: policy.capacity as f64,
+            updated_at: now,
+            policy: *policy,
+        });
+        bucket.refill(now);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(RateLimitDecision {
+            allowed,
+            limit: policy.capacity,
+            remaining: bucket.tokens.floor() as u32,
+            reset_after: if allowed {
+                bucket.seconds_until(policy.capacity as f64)
+            } else {
+                bucket.seconds_until(1.0)
+            },
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    pub group: &'static str,
+    pub policy: RateLimitPolicy,
+    pub store: Arc<dyn RateLimitStore>,
+    pub app_state: Arc<AppState>,
+}
+
+#[tracing::instrument(name = "Rate limiting request", skip(limiter, request, next))]
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut decisions = Vec::with_capacity(2);
+
+    if let Some(ConnectInfo(addr)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        let key = format!("{}:ip:{}", limiter.group, addr.ip());
+        match limiter.store.take(&key, &limiter.policy).await {
+            Ok(decision) => decisions.push(decision),
+            Err(e) => return e.into_response(),
+        }
+    }
+
+    // Resolving the user costs a lookup, so an address that is already over its limit is
+    // turned away before anything is read.
+    if decisions.iter().all(|d| d.allowed) {
+        if let Some(user_id) = requesting_user_id(request.headers(), &limiter.app_state).await {
+            let key = format!("{}:user:{}", limiter.group, user_id);
+            match limiter.store.take(&key, &limiter.policy).await {
+                Ok(decision) => decisions.push(decision),
+                Err(e) => return e.into_response(),
+            }
+        }
+    }
+
+    // Report the most restrictive bucket: a rejection first, then the lowest remaining quota.
+    let decision = match decisions
+        .into_iter()
+        .min_by_key(|d| (d.allowed, d.remaining))
+    {
+        Some(decision) => decision,
+        None => return next.run(request).await,
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        AppError::TooManyRequestsError(
+            "Rate limit exceeded.".into(),
+            decision.reset_after,
+        )
+        .into_response()
+    };
+
+    set_rate_limit_headers(response.headers_mut(), &decision);
+
+    response
+}
+
+/// Who the request claims to be, without authorizing it. Unknown credentials are still limited
+/// by address.
+async fn requesting_user_id(headers: &HeaderMap, app_state: &AppState) -> Option<Uuid> {
+    if let Some(key) = headers.get(&API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return get_api_key_by_key(key, &app_state.pool)
+            .await
+            .ok()
+            .flatten()
+            .map(|api_key| api_key.user_id);
+    }
+
+    // Requests are authenticated by the session cookie alone in this mode.
+    if app_state.session_settings.mode == SessionMode::Server {
+        let cookie = headers.typed_get::<headers::Cookie>()?;
+        let session_id = cookie.get(&app_state.session_settings.cookie_name)?;
+
+        return app_state
+            .session_store
+            .get(session_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|session| session.user_id);
+    }
+
+    let Authorization(bearer) = headers.typed_get::<Authorization<Bearer>>()?;
+
+    decode_jwt(bearer.token(), &app_state.jwt_settings, &app_state.jwt_keys, false)
+        .ok()
+        .map(|token_data| token_data.claims.id)
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    // Nested route groups are limited by several layers; keep whichever quota is lower.
+    let inner_remaining = headers
+        .get(&RATELIMIT_REMAINING)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u32>().ok());
+
+    if inner_remaining.is_some_and(|remaining| remaining <= decision.remaining) {
+        return;
+    }
+
+    headers.insert(RATELIMIT_LIMIT.clone(), HeaderValue::from(decision.limit));
+    headers.insert(RATELIMIT_REMAINING.clone(), HeaderValue::from(decision.remaining));
+    headers.insert(RATELIMIT_RESET.clone(), HeaderValue::from(decision.reset_after));
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_ok;
+
+    use crate::configurations::RateLimitPolicy;
+
+    use super::{InMemoryRateLimitStore, RateLimitStore, EVICTION_BATCH, MAX_TRACKED_KEYS};
+
+    fn test_policy() -> RateLimitPolicy {
+        RateLimitPolicy {
+            capacity: 3,
+            refill_per_second: 0.01,
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_within_capacity_are_allowed() {
+        let store = InMemoryRateLimitStore::default();
+        let policy = test_policy();
+
+        for v in (0..3).rev() {
+            let decision = assert_ok!(store.take("key", &policy).await);
+
+            assert!(decision.allowed);
+            assert_eq!(v, decision.remaining);
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_over_capacity_are_rejected() {
+        let store = InMemoryRateLimitStore::default();
+        let policy = test_policy();
+
+        for _ in 0..3 {
+            assert_ok!(store.take("key", &policy).await);
+        }
+
+        let decision = assert_ok!(store.take("key", &policy).await);
+
+        assert!(!decision.allowed);
+        assert!(decision.reset_after > 0);
+    }
+
+    #[tokio::test]
+    async fn buckets_are_tracked_per_key() {
+        let store = InMemoryRateLimitStore::default();
+        let policy = test_policy();
+
+        for _ in 0..3 {
+            assert_ok!(store.take("first", &policy).await);
+        }
+
+        let decision = assert_ok!(store.take("second", &policy).await);
+
+        assert!(decision.allowed);
+    }
+
+    #[tokio::test]
+    async fn eviction_refills_buckets_with_their_own_policy() {
+        let store = InMemoryRateLimitStore::default();
+        let policy = test_policy();
+
+        for v in 0..MAX_TRACKED_KEYS {
+            assert_ok!(store.take(&format!("key-{}", v), &policy).await);
+        }
+
+        // Two tokens left would count as full under this policy.
+        let smaller = RateLimitPolicy { capacity: 2, ..policy };
+        assert_ok!(store.take("other", &smaller).await);
+
+        let buckets = store.buckets.lock().unwrap();
+        assert_eq!(MAX_TRACKED_KEYS - EVICTION_BATCH + 1, buckets.len());
+    }
+
+    #[tokio::test]
+    async fn the_number_of_tracked_keys_is_bounded() {
+        let store = InMemoryRateLimitStore::default();
+        let policy = test_policy();
+
+        for v in 0..MAX_TRACKED_KEYS + 10 {
+            assert_ok!(store.take(&format!("key-{}", v), &policy).await);
+        }
+
+        let buckets = store.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_TRACKED_KEYS);
+        assert!(buckets.contains_key(&format!("key-{}", MAX_TRACKED_KEYS + 9)));
+        assert!(!buckets.contains_key("key-0"));
+    }
+}
@@ -0,0 +1,64 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+use crate::{
+    app_state::AppState,
+    errors::AppError,
+    utils::{
+        jwt::AuthUser,
+        scopes::{ensure_scopes, RequiredScopes},
+    },
+};
+
+/// A role a route can require. Each role is a marker type so guards are checked at compile
+/// time, e.g. `RequireRole<Admin, UsersRead>`.
+pub trait Role {
+    const NAME: &'static str;
+}
+
+pub struct Admin;
+
+impl Role for Admin {
+    const NAME: &'static str = "admin";
+}
+
+/// An `AuthUser` whose access token carries the role `R` and whose credential was granted every
+/// scope in `S`. The request is authenticated once for both checks. Rejects with 403 otherwise.
+pub struct RequireRole<R: Role, S: RequiredScopes> {
+    pub user: AuthUser,
+    role: PhantomData<(R, S)>,
+}
+
+#[async_trait]
+impl<St, R, S> FromRequestParts<St> for RequireRole<R, S>
+where
+    St: Send + Sync,
+    R: Role,
+    S: RequiredScopes,
+    Arc<AppState>: FromRef<St>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &St) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+
+        if !user.has_role(R::NAME) {
+            return Err(AppError::ForbiddenError(format!(
+                "The {} role is required.",
+                R::NAME
+            )));
+        }
+
+        ensure_scopes::<S>(&user)?;
+
+        Ok(Self {
+            user,
+            role: PhantomData,
+        })
+    }
+}
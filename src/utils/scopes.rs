@@ -0,0 +1,105 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+use crate::{app_state::AppState, errors::AppError, utils::jwt::AuthUser};
+
+/// Every scope the API knows about. Tokens issued by login carry all of them, while API keys
+/// can be restricted to a subset.
+pub const ALL_SCOPES: &[&str] = &[
+    "account:read",
+    "account:write",
+    "api_keys:read",
+    "api_keys:write",
+    "organizations:read",
+    "organizations:write",
+    "roles:read",
+    "roles:write",
+    "sessions:read",
+    "sessions:write",
+    "todos:read",
+    "todos:write",
+    "users:read",
+    "users:write",
+];
+
+/// The scopes a route requires. Implemented by the marker types below, e.g.
+/// `RequireScopes<SessionsWrite>`.
+pub trait RequiredScopes {
+    const SCOPES: &'static [&'static str];
+}
+
+macro_rules! required_scopes {
+    ($($name:ident => [$($scope:literal),+]),+ $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl RequiredScopes for $name {
+                const SCOPES: &'static [&'static str] = &[$($scope),+];
+            }
+        )+
+    };
+}
+
+required_scopes! {
+    AccountRead => ["account:read"],
+    AccountWrite => ["account:write"],
+    ApiKeysRead => ["api_keys:read"],
+    ApiKeysWrite => ["api_keys:write"],
+    OrganizationsRead => ["organizations:read"],
+    OrganizationsWrite => ["organizations:write"],
+    RolesRead => ["roles:read"],
+    RolesWrite => ["roles:write"],
+    SessionsRead => ["sessions:read"],
+    SessionsWrite => ["sessions:write"],
+    TodosRead => ["todos:read"],
+    TodosWrite => ["todos:write"],
+    UsersRead => ["users:read"],
+    UsersWrite => ["users:write"],
+}
+
+pub fn is_known_scope(scope: &str) -> bool {
+    ALL_SCOPES.contains(&scope)
+}
+
+/// An `AuthUser` whose credential was granted every scope in `S`. Rejects with 403 otherwise.
+pub struct RequireScopes<S: RequiredScopes> {
+    pub user: AuthUser,
+    scopes: PhantomData<S>,
+}
+
+#[async_trait]
+impl<St, S> FromRequestParts<St> for RequireScopes<S>
+where
+    St: Send + Sync,
+    S: RequiredScopes,
+    Arc<AppState>: FromRef<St>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &St) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+
+        ensure_scopes::<S>(&user)?;
+
+        Ok(Self {
+            user,
+            scopes: PhantomData,
+        })
+    }
+}
+
+/// Rejects with 403 unless `user`'s credential was granted every scope in `S`.
+pub(crate) fn ensure_scopes<S: RequiredScopes>(user: &AuthUser) -> Result<(), AppError> {
+    if !S::SCOPES.iter().all(|scope| user.has_scope(scope)) {
+        return Err(AppError::InsufficientScopeError(
+            S::SCOPES.iter().map(|s| s.to_string()).collect(),
+        ));
+    }
+
+    Ok(())
+}
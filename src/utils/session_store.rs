@@ -0,0 +1,375 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cookie::{time::Duration, Cookie};
+use sqlx::{FromRow, PgPool};
+use uuid::{NoContext, Timestamp, Uuid};
+
+use crate::{
+    configurations::{JwtSettings, SessionSettings, SessionStoreKind},
+    db::{DbContext, DbPool},
+    errors::AppError,
+    features::auth::models::{SessionData, SessionMetadata},
+    utils::{randomizer::generate_random_string, token_hash::hash_token},
+};
+
+const SESSION_ID_LENGTH: usize = 43;
+
+#[derive(FromRow, Clone, Debug)]
+pub struct SessionRecord {
+    /// Identifies the session in listings, unlike the cookie value it is safe to show.
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Server-side sessions behind an opaque cookie, used instead of access tokens in
+/// `SessionMode::Server`. Session ids are only ever stored hashed.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Returns the id to hand to the browser.
+    async fn create(
+        &self,
+        user_id: Uuid,
+        metadata: &SessionMetadata,
+        expires_at: DateTime<Utc>,
+    ) -> Result<String, AppError>;
+    /// `None` for unknown and expired sessions alike. Marks the session as used.
+    async fn get(&self, session_id: &str) -> Result<Option<SessionRecord>, AppError>;
+    async fn delete(&self, session_id: &str) -> Result<(), AppError>;
+    /// The user's unexpired sessions, most recently used first.
+    async fn list_user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionData>, AppError>;
+    /// Ends the session with the listed `id`, if it belongs to the user.
+    async fn delete_user_session(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError>;
+    /// Ends every session of the user except `keep`.
+    async fn delete_user_sessions(&self, user_id: Uuid, keep: Option<&str>) -> Result<(), AppError>;
+}
+
+pub fn session_store(settings: &SessionSettings, pool: &PgPool) -> Arc<dyn SessionStore> {
+    match settings.store {
+        SessionStoreKind::Postgres => Arc::new(PgSessionStore {
+            db: DbPool { pool: pool.clone() },
+        }),
+        SessionStoreKind::Memory => Arc::new(InMemorySessionStore::default()),
+    }
+}
+
+/// The session cookie shares the refresh cookie's attributes.
+pub fn session_cookie(
+    value: String,
+    max_age: Duration,
+    session_settings: &SessionSettings,
+    jwt_settings: &JwtSettings,
+) -> Cookie<'static> {
+    let settings = &jwt_settings.refresh_cookie;
+
+    let mut cookie = cookie::CookieBuilder::new(session_settings.cookie_name.to_string(), value)
+        .http_only(true)
+        .max_age(max_age)
+        .path(settings.path.to_string())
+        .same_site((&settings.same_site).into())
+        .secure(settings.secure);
+
+    if let Some(domain) = &settings.domain {
+        cookie = cookie.domain(domain.to_string());
+    }
+
+    cookie.build()
+}
+
+pub struct PgSessionStore {
+    db: DbPool,
+}
+
+#[async_trait]
+impl SessionStore for PgSessionStore {
+    #[tracing::instrument(name = "Creating server session", skip(self, metadata, expires_at))]
+    async fn create(
+        &self,
+        user_id: Uuid,
+        metadata: &SessionMetadata,
+        expires_at: DateTime<Utc>,
+    ) -> Result<String, AppError> {
+        let session_id = generate_random_string(SESSION_ID_LENGTH);
+
+        let query = sqlx::query!(
+            r#"
+                INSERT INTO server_sessions
+                (id_hash, id, user_id, user_agent, ip, device_label, created_at, last_used_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6, now(), now(), $7)
+            "#,
+            hash_token(&session_id),
+            Uuid::new_v7(Timestamp::now(NoContext)),
+            user_id,
+            metadata.user_agent,
+            metadata.ip,
+            metadata.device_label,
+            expires_at
+        );
+
+        self.db.execute_query(query).await?;
+
+        Ok(session_id)
+    }
+
+    #[tracing::instrument(name = "Fetching server session", skip(self, session_id))]
+    async fn get(&self, session_id: &str) -> Result<Option<SessionRecord>, AppError> {
+        let query = sqlx::query_as(
+            r#"
+                UPDATE server_sessions SET last_used_at = now()
+                WHERE id_hash = $1 AND expires_at > now()
+                RETURNING id, user_id, expires_at
+            "#,
+        )
+        .bind(hash_token(session_id));
+
+        Ok(self.db.fetch_all::<SessionRecord>(query).await?.pop())
+    }
+
+    #[tracing::instrument(name = "Deleting server session", skip(self, session_id))]
+    async fn delete(&self, session_id: &str) -> Result<(), AppError> {
+        let query = sqlx::query!(
+            r#"
+                DELETE FROM server_sessions WHERE id_hash = $1
+            "#,
+            hash_token(session_id)
+        );
+
+        self.db.execute_query(query).await
+    }
+
+    #[tracing::instrument(name = "Listing server sessions of User", skip(self))]
+    async fn list_user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionData>, AppError> {
+        let query = sqlx::query_as(
+            r#"
+                SELECT id, user_agent, ip, device_label, created_at, last_used_at, expires_at
+                FROM server_sessions
+                WHERE user_id = $1 AND expires_at > now()
+                ORDER BY last_used_at DESC
+            "#,
+        )
+        .bind(user_id);
+
+        self.db.fetch_all::<SessionData>(query).await
+    }
+
+    #[tracing::instrument(name = "Deleting server session by id", skip(self))]
+    async fn delete_user_session(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        let query = sqlx::query_as(
+            r#"
+                DELETE FROM server_sessions WHERE id = $1 AND user_id = $2 RETURNING id
+            "#,
+        )
+        .bind(id)
+        .bind(user_id);
+
+        match self.db.fetch_all::<(Uuid,)>(query).await?.pop() {
+            Some(_) => Ok(()),
+            None => Err(AppError::NotFoundError("Session was not found".into())),
+        }
+    }
+
+    #[tracing::instrument(name = "Deleting server sessions of User", skip(self, keep))]
+    async fn delete_user_sessions(&self, user_id: Uuid, keep: Option<&str>) -> Result<(), AppError> {
+        let query = sqlx::query!(
+            r#"
+                DELETE FROM server_sessions
+                WHERE user_id = $1 AND ($2::text IS NULL OR id_hash <> $2)
+            "#,
+            user_id,
+            keep.map(hash_token)
+        );
+
+        self.db.execute_query(query).await
+    }
+}
+
+struct StoredSession {
+    user_id: Uuid,
+    data: SessionData,
+}
+
+#[derive(Default)]
+pub struct InMemorySessionStore {
+    sessions: Mutex<HashMap<String, StoredSession>>,
+}
+
+impl InMemorySessionStore {
+    fn sessions(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, StoredSession>>, AppError> {
+        self.sessions
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        metadata: &SessionMetadata,
+        expires_at: DateTime<Utc>,
+    ) -> Result<String, AppError> {
+        let session_id = generate_random_string(SESSION_ID_LENGTH);
+        let mut sessions = self.sessions()?;
+
+        let now = Utc::now();
+        sessions.retain(|_, s| s.data.expires_at > now);
+        sessions.insert(
+            hash_token(&session_id),
+            StoredSession {
+                user_id,
+                data: SessionData {
+                    id: Uuid::new_v7(Timestamp::now(NoContext)),
+                    user_agent: metadata.user_agent.clone(),
+                    ip: Some(metadata.ip.clone()),
+                    device_label: metadata.device_label.clone(),
+                    created_at: now,
+                    last_used_at: now,
+                    expires_at,
+                },
+            },
+        );
+
+        Ok(session_id)
+    }
+
+    async fn get(&self, session_id: &str) -> Result<Option<SessionRecord>, AppError> {
+        let mut sessions = self.sessions()?;
+        let now = Utc::now();
+
+        Ok(sessions
+            .get_mut(&hash_token(session_id))
+            .filter(|s| s.data.expires_at > now)
+            .map(|s| {
+                s.data.last_used_at = now;
+                SessionRecord {
+                    id: s.data.id,
+                    user_id: s.user_id,
+                    expires_at: s.data.expires_at,
+                }
+            }))
+    }
+
+    async fn delete(&self, session_id: &str) -> Result<(), AppError> {
+        self.sessions()?.remove(&hash_token(session_id));
+
+        Ok(())
+    }
+
+    async fn list_user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionData>, AppError> {
+        let now = Utc::now();
+
+        let mut result = self
+            .sessions()?
+            .values()
+            .filter(|s| s.user_id == user_id && s.data.expires_at > now)
+            .map(|s| s.data.clone())
+            .collect::<Vec<_>>();
+        result.sort_by_key(|s| std::cmp::Reverse(s.last_used_at));
+
+        Ok(result)
+    }
+
+    async fn delete_user_session(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        let mut sessions = self.sessions()?;
+        let len = sessions.len();
+
+        sessions.retain(|_, s| s.user_id != user_id || s.data.id != id);
+
+        if sessions.len() == len {
+            return Err(AppError::NotFoundError("Session was not found".into()));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, user_id: Uuid, keep: Option<&str>) -> Result<(), AppError> {
+        let keep = keep.map(hash_token);
+
+        self.sessions()?
+            .retain(|id, s| s.user_id != user_id || Some(id) == keep.as_ref());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use claims::{assert_none, assert_some};
+    use uuid::Uuid;
+
+    use crate::features::auth::models::SessionMetadata;
+
+    use super::{InMemorySessionStore, SessionStore};
+
+    fn metadata() -> SessionMetadata {
+        SessionMetadata {
+            user_agent: Some("test".into()),
+            ip: "127.0.0.1".into(),
+            device_label: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn a_session_resolves_to_its_user_until_deleted() {
+        let store = InMemorySessionStore::default();
+        let user_id = Uuid::new_v4();
+
+        let session_id = store.create(user_id, &metadata(), Utc::now() + Duration::hours(1)).await.unwrap();
+
+        assert_eq!(user_id, store.get(&session_id).await.unwrap().unwrap().user_id);
+        store.delete(&session_id).await.unwrap();
+        assert_none!(store.get(&session_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn an_expired_session_is_not_returned() {
+        let store = InMemorySessionStore::default();
+
+        let session_id = store
+            .create(Uuid::new_v4(), &metadata(), Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+
+        assert_none!(store.get(&session_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn deleting_user_sessions_keeps_the_given_one() {
+        let store = InMemorySessionStore::default();
+        let user_id = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::hours(1);
+        let current = store.create(user_id, &metadata(), expires_at).await.unwrap();
+        let other = store.create(user_id, &metadata(), expires_at).await.unwrap();
+        let someone_else = store.create(Uuid::new_v4(), &metadata(), expires_at).await.unwrap();
+
+        store.delete_user_sessions(user_id, Some(&current)).await.unwrap();
+
+        assert_some!(store.get(&current).await.unwrap());
+        assert_none!(store.get(&other).await.unwrap());
+        assert_some!(store.get(&someone_else).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn a_listed_session_can_be_ended_by_its_owner_only() {
+        let store = InMemorySessionStore::default();
+        let user_id = Uuid::new_v4();
+        let session_id = store
+            .create(user_id, &metadata(), Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+        let sessions = store.list_user_sessions(user_id).await.unwrap();
+        assert_eq!(1, sessions.len());
+
+        assert!(store.delete_user_session(Uuid::new_v4(), sessions[0].id).await.is_err());
+        assert_some!(store.get(&session_id).await.unwrap());
+        store.delete_user_session(user_id, sessions[0].id).await.unwrap();
+        assert_none!(store.get(&session_id).await.unwrap());
+    }
+}
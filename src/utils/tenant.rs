@@ -0,0 +1,84 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, HeaderName},
+};
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::{
+    app_state::AppState,
+    errors::AppError,
+    features::organizations::{domain::OrganizationRole, repository::get_membership_role},
+    utils::{
+        jwt::AuthUser,
+        scopes::{RequireScopes, RequiredScopes},
+    },
+};
+
+/// Clients pick the organization a request acts on with this header. Switching organizations
+/// only changes the header, the access token stays the same.
+pub static ORGANIZATION_HEADER: HeaderName = HeaderName::from_static("x-organization-id");
+
+/// An organization the current user was verified to belong to. Only `Tenant` can create one,
+/// so tenant-scoped repository functions taking a `&TenantId` cannot be called with an
+/// unchecked id.
+#[derive(Debug, Clone, Copy)]
+pub struct TenantId(Uuid);
+
+impl TenantId {
+    pub fn id(&self) -> Uuid {
+        self.0
+    }
+}
+
+/// A `RequireScopes<S>` user acting on the organization named by `ORGANIZATION_HEADER`.
+/// Rejects with 400 when the header is missing or malformed and 403 when the user is not a
+/// member.
+pub struct Tenant<S: RequiredScopes> {
+    pub user: AuthUser,
+    pub id: TenantId,
+    pub role: OrganizationRole,
+    scopes: PhantomData<S>,
+}
+
+#[async_trait]
+impl<St, S> FromRequestParts<St> for Tenant<S>
+where
+    St: Send + Sync,
+    S: RequiredScopes,
+    Arc<AppState>: FromRef<St>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &St) -> Result<Self, Self::Rejection> {
+        let RequireScopes { user, .. } = RequireScopes::<S>::from_request_parts(parts, state).await?;
+        let app_state = Arc::from_ref(state);
+
+        let organization_id = parts
+            .headers
+            .get(&ORGANIZATION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| Uuid::parse_str(v.trim()).ok())
+            .ok_or_else(|| {
+                let mut errors = ValidationErrors::new();
+                errors.add("organization_id", ValidationError::new("invalid_organization"));
+                AppError::ValidationError(errors)
+            })?;
+
+        let role = get_membership_role(organization_id, user.id, &app_state.pool)
+            .await?
+            .ok_or(AppError::ForbiddenError(
+                "You are not a member of this organization.".into(),
+            ))?;
+
+        Ok(Self {
+            user,
+            id: TenantId(organization_id),
+            role,
+            scopes: PhantomData,
+        })
+    }
+}
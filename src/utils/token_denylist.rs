@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{db::DbContext, errors::AppError};
+
+const MAX_CACHED_TOKENS: usize = 10_000;
+const EVICTION_BATCH: usize = MAX_CACHED_TOKENS / 10;
+
+/// How long a "not revoked" answer is trusted before Postgres is asked again. Revocations made
+/// by this instance update the cache right away, so this only delays revocations made by
+/// other instances.
+const NOT_REVOKED_TTL: Duration = Duration::from_secs(30);
+
+struct CacheEntry {
+    revoked: bool,
+    cached_until: Instant,
+}
+
+#[derive(FromRow)]
+struct RevokedTokenData {
+    expires_at: DateTime<Utc>,
+}
+
+/// Access token ids revoked before their expiry, kept in Postgres with an in-memory cache in
+/// front so authenticated requests rarely hit the database.
+#[derive(Default)]
+pub struct TokenDenylist {
+    cache: Mutex<HashMap<Uuid, CacheEntry>>,
+}
+
+impl TokenDenylist {
+    #[tracing::instrument(name = "Revoking access token", skip(self, db))]
+    pub async fn revoke(
+        &self,
+        jti: Uuid,
+        expires_at: DateTime<Utc>,
+        db: &impl DbContext,
+    ) -> Result<(), AppError> {
+        let query = sqlx::query!(
+            r#"
+                INSERT INTO revoked_tokens (jti, expires_at)
+                VALUES ($1, $2)
+                ON CONFLICT (jti) DO NOTHING
+            "#,
+            jti,
+            expires_at
+        );
+
+        db.execute_query(query).await?;
+
+        self.cache_entry(jti, true, until(expires_at))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking access token denylist", skip(self, db))]
+    pub async fn is_revoked(
+        &self,
+        jti: Uuid,
+        expires_at: DateTime<Utc>,
+        db: &impl DbContext,
+    ) -> Result<bool, AppError> {
+        if let Some(revoked) = self.cached(jti)? {
+            return Ok(revoked);
+        }
+
+        let query = sqlx::query_as(
+            r#"
+                SELECT expires_at FROM revoked_tokens WHERE jti = $1
+            "#,
+        )
+        .bind(jti);
+
+        let (revoked, cached_until) = match db.fetch_optional::<RevokedTokenData>(query).await? {
+            Some(data) => (true, until(data.expires_at)),
+            None => (false, (Instant::now() + NOT_REVOKED_TTL).min(until(expires_at))),
+        };
+        self.cache_entry(jti, revoked, cached_until)?;
+
+        Ok(revoked)
+    }
+
+    fn cached(&self, jti: Uuid) -> Result<Option<bool>, AppError> {
+        let cache = self
+            .cache
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+        Ok(cache
+            .get(&jti)
+            .filter(|entry| entry.cached_until > Instant::now())
+            .map(|entry| entry.revoked))
+    }
+
+    fn cache_entry(&self, jti: Uuid, revoked: bool, cached_until: Instant) -> Result<(), AppError> {
+        let now = Instant::now();
+        let mut cache = self
+            .cache
+            .lock()
+            .map_err(|e| AppError::UnexpectedError(e.to_string()))?;
+
+        if !cache.contains_key(&jti) && cache.len() >= MAX_CACHED_TOKENS {
+            evict(&mut cache, now);
+        }
+
+        cache.insert(jti, CacheEntry { revoked, cached_until });
+
+        Ok(())
+    }
+}
+
+/// Drops expired entries, then the ones closest to expiring when every entry is still fresh.
+/// An evicted token is looked up in Postgres again, so this only costs a query.
+fn evict(cache: &mut HashMap<Uuid, CacheEntry>, now: Instant) {
+    cache.retain(|_, entry| entry.cached_until > now);
+
+    if cache.len() < MAX_CACHED_TOKENS {
+        return;
+    }
+
+    let mut oldest = cache
+        .iter()
+        .map(|(jti, entry)| (entry.cached_until, *jti))
+        .collect::<Vec<_>>();
+    oldest.sort_unstable();
+
+    for (_, jti) in oldest.into_iter().take(EVICTION_BATCH) {
+        cache.remove(&jti);
+    }
+}
+
+fn until(expires_at: DateTime<Utc>) -> Instant {
+    let remaining = (expires_at - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+
+    Instant::now() + remaining
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use claims::{assert_ok, assert_ok_eq};
+    use uuid::Uuid;
+
+    use crate::db::MockDbContext;
+
+    use super::{RevokedTokenData, TokenDenylist, MAX_CACHED_TOKENS};
+
+    #[tokio::test]
+    async fn revoked_tokens_are_answered_from_the_cache() {
+        let denylist = TokenDenylist::default();
+        let jti = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::minutes(5);
+        let mut db_mock = MockDbContext::new();
+
+        db_mock.expect_execute_query().times(1).returning(|_| Ok(()));
+        db_mock.expect_fetch_optional::<RevokedTokenData>().never();
+
+        assert_ok!(denylist.revoke(jti, expires_at, &db_mock).await);
+
+        assert_ok_eq!(denylist.is_revoked(jti, expires_at, &db_mock).await, true);
+    }
+
+    #[tokio::test]
+    async fn unknown_tokens_are_looked_up_once() {
+        let denylist = TokenDenylist::default();
+        let jti = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::minutes(5);
+        let mut db_mock = MockDbContext::new();
+
+        db_mock
+            .expect_fetch_optional::<RevokedTokenData>()
+            .times(1)
+            .returning(|_| Ok(None));
+
+        assert_ok_eq!(denylist.is_revoked(jti, expires_at, &db_mock).await, false);
+        assert_ok_eq!(denylist.is_revoked(jti, expires_at, &db_mock).await, false);
+    }
+
+    #[tokio::test]
+    async fn tokens_revoked_elsewhere_are_found_in_the_database() {
+        let denylist = TokenDenylist::default();
+        let jti = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::minutes(5);
+        let mut db_mock = MockDbContext::new();
+
+        db_mock
+            .expect_fetch_optional::<RevokedTokenData>()
+            .times(1)
+            .returning(move |_| Ok(Some(RevokedTokenData { expires_at })));
+
+        assert_ok_eq!(denylist.is_revoked(jti, expires_at, &db_mock).await, true);
+    }
+
+    #[test]
+    fn the_number_of_cached_tokens_is_bounded() {
+        let denylist = TokenDenylist::default();
+        let cached_until = std::time::Instant::now() + std::time::Duration::from_secs(60);
+
+        for _ in 0..=MAX_CACHED_TOKENS {
+            assert_ok!(denylist.cache_entry(Uuid::new_v4(), false, cached_until));
+        }
+
+        assert!(denylist.cache.lock().unwrap().len() <= MAX_CACHED_TOKENS);
+    }
+}
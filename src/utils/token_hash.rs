@@ -0,0 +1,36 @@
+use sha2::{Digest, Sha256};
+
+/// SHA-256 digest of a high-entropy token, hex encoded. Tokens are only ever stored and looked
+/// up by this digest so a database leak cannot be replayed.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::randomizer::generate_random_string;
+
+    use super::hash_token;
+
+    #[test]
+    fn hashing_is_deterministic() {
+        let token = generate_random_string(32);
+
+        assert_eq!(hash_token(&token), hash_token(&token));
+    }
+
+    #[test]
+    fn a_hash_is_a_hex_encoded_sha256_digest() {
+        let result = hash_token("abc");
+
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            result
+        );
+    }
+
+    #[test]
+    fn different_tokens_have_different_hashes() {
+        assert_ne!(hash_token("first"), hash_token("second"));
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::json;
+use test_rs::features::{admin::controller::UserRolesResponse, auth::controller::AuthResponse};
+
+use crate::helpers::{spawn_app, TestApp, TestUser};
+
+async fn login(app: &TestApp, user: &TestUser) -> AuthResponse {
+    app.login_user(&json!(user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .expect("Failed to parse login response.")
+}
+
+#[tokio::test]
+pub async fn non_admins_are_forbidden() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = login(&app, &app.test_user).await;
+
+    // act
+    let res = app
+        .http_client
+        .put(format!("{}/admin/users/{}/roles/admin", app.address, auth.id))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send grant request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn admins_can_grant_and_revoke_roles() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    app.test_user.grant_role(&app.pool, "admin").await;
+    let admin = login(&app, &app.test_user).await;
+
+    let other_user = TestUser::generate();
+    other_user.store_user(&app).await;
+    let other = login(&app, &other_user).await;
+    let roles_url = format!("{}/admin/users/{}/roles", app.address, other.id);
+
+    // act
+    let grant = app
+        .http_client
+        .put(format!("{}/admin", roles_url))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send grant request.");
+
+    // assert
+    assert_eq!(204, grant.status().as_u16());
+    let promoted = login(&app, &other_user).await;
+    let res = app
+        .http_client
+        .get(&roles_url)
+        .bearer_auth(&promoted.access_token)
+        .send()
+        .await
+        .expect("Failed to send roles request.");
+    assert_eq!(200, res.status().as_u16());
+    assert_eq!(vec!["admin"], res.json::<UserRolesResponse>().await.unwrap().roles);
+
+    let revoke = app
+        .http_client
+        .delete(format!("{}/admin", roles_url))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send revoke request.");
+    assert_eq!(204, revoke.status().as_u16());
+    let demoted = login(&app, &other_user).await;
+    let res = app
+        .http_client
+        .get(&roles_url)
+        .bearer_auth(&demoted.access_token)
+        .send()
+        .await
+        .expect("Failed to send roles request.");
+    assert_eq!(403, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn granting_an_unknown_role_returns_404() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    app.test_user.grant_role(&app.pool, "admin").await;
+    let admin = login(&app, &app.test_user).await;
+
+    // act
+    let res = app
+        .http_client
+        .put(format!("{}/admin/users/{}/roles/superuser", app.address, admin.id))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send grant request.");
+
+    // assert
+    assert_eq!(404, res.status().as_u16());
+}
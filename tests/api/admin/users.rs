@@ -0,0 +1,420 @@
+use serde_json::json;
+use test_rs::{
+    db::DbPool,
+    features::{
+        admin::controller::{ImpersonationResponse, UserPageResponse},
+        auth::{controller::AuthResponse, repository::revoke_role_from_user},
+        users::controller::UserProfileResponse,
+    },
+};
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_refresh_token, spawn_app, TestApp, TestUser};
+
+async fn login(app: &TestApp, user: &TestUser) -> AuthResponse {
+    app.login_user(&json!(user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .expect("Failed to parse login response.")
+}
+
+async fn login_admin(app: &TestApp) -> AuthResponse {
+    app.test_user.store_user(app).await;
+    app.test_user.grant_role(&app.pool, "admin").await;
+
+    login(app, &app.test_user).await
+}
+
+async fn count_audit_entries(app: &TestApp, action: &str) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM admin_audit_log WHERE action = $1")
+        .bind(action)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count audit entries.")
+}
+
+#[tokio::test]
+pub async fn non_admins_cannot_list_users() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = login(&app, &app.test_user).await;
+
+    // act
+    let res = app
+        .http_client
+        .get(format!("{}/admin/users", app.address))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send users request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn admins_can_search_and_paginate_users() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    for username in ["findme_1", "findme_2", "findme_3", "other"] {
+        TestUser { username: username.into(), password: "violet kettle".into() }
+            .store_user(&app)
+            .await;
+    }
+
+    // act
+    let res = app
+        .http_client
+        .get(format!("{}/admin/users?q=FINDME_&page=2&per_page=2", app.address))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send users request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let page = res.json::<UserPageResponse>().await.unwrap();
+    assert_eq!(3, page.total);
+    assert_eq!(vec!["findme_3"], page.users.iter().map(|u| u.username.as_str()).collect::<Vec<_>>());
+    assert_eq!(1, count_audit_entries(&app, "users.searched").await);
+}
+
+#[tokio::test]
+pub async fn a_disabled_user_is_signed_out_and_cannot_log_in() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let res = app.login_user(&json!(user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+    let user_url = format!("{}/admin/users/{}", app.address, auth.id);
+
+    // act
+    let res = app
+        .http_client
+        .post(format!("{}/disable", user_url))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send disable request.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    assert_eq!(401, app.refresh_token(&rt).await.status().as_u16());
+    assert_eq!(403, app.login_user(&json!(user)).await.status().as_u16());
+    assert_eq!(1, count_audit_entries(&app, "user.disabled").await);
+
+    let res = app
+        .http_client
+        .post(format!("{}/enable", user_url))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send enable request.");
+    assert_eq!(204, res.status().as_u16());
+    assert_eq!(200, app.login_user(&json!(user)).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn admins_cannot_disable_themselves() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+
+    // act
+    let res = app
+        .http_client
+        .post(format!("{}/admin/users/{}/disable", app.address, admin.id))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send disable request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_banned_user_is_rejected_with_the_ban_details() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let res = app.login_user(&json!(user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app
+        .http_client
+        .put(format!("{}/admin/users/{}/status", app.address, auth.id))
+        .bearer_auth(&admin.access_token)
+        .json(&json!({ "status": "banned", "reason": "spam", "until": "2999-01-01T00:00:00Z" }))
+        .send()
+        .await
+        .expect("Failed to send status request.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let res = app
+        .http_client
+        .get(format!("{}/users/me", app.address))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send profile request.");
+    assert_eq!(403, res.status().as_u16());
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!("AccountInactiveError", body["error_type"]);
+    assert_eq!("banned", body["status"]);
+    assert_eq!("spam", body["reason"]);
+    assert_eq!(401, app.refresh_token(&rt).await.status().as_u16());
+    let res = app.login_user(&json!(user)).await;
+    assert_eq!(403, res.status().as_u16());
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!("AccountInactiveError", body["error_type"]);
+    assert_eq!(1, count_audit_entries(&app, "user.status_changed").await);
+}
+
+#[tokio::test]
+pub async fn a_lapsed_ban_no_longer_applies() {
+    // arrange
+    let app = spawn_app().await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    sqlx::query(
+        "UPDATE users SET status = 'banned', status_until = now() - interval '1 minute' WHERE username = $1",
+    )
+    .bind(&user.username)
+    .execute(&app.pool)
+    .await
+    .expect("Failed to ban user.");
+
+    // act
+    let res = app.login_user(&json!(user)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_inconsistent_status_change_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let auth = login(&app, &user).await;
+
+    // act
+    let res = app
+        .http_client
+        .put(format!("{}/admin/users/{}/status", app.address, auth.id))
+        .bearer_auth(&admin.access_token)
+        .json(&json!({ "status": "banned", "until": "2000-01-01T00:00:00Z" }))
+        .send()
+        .await
+        .expect("Failed to send status request.");
+
+    // assert
+    assert_eq!(400, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_password_reset_link_sets_a_new_password() {
+    // arrange
+    let app = spawn_app().await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    let admin = login_admin(&app).await;
+    let auth = app
+        .register_confirmed_user(&json!({
+            "username": "ada",
+            "email": "ada@example.com",
+            "password": "violet kettle marching under rain"
+        }))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    let res = app
+        .http_client
+        .post(format!("{}/admin/users/{}/password-reset", app.address, auth.id))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send password reset request.");
+    assert_eq!(202, res.status().as_u16());
+    let token = app.get_email_token().await;
+    let reset = json!({ "token": token, "password": "amber lantern drifting over hills" });
+
+    // act
+    let res = app
+        .http_client
+        .post(format!("{}/auth/password-reset", app.address))
+        .json(&reset)
+        .send()
+        .await
+        .expect("Failed to send password reset.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let login = json!({ "username": "ada", "password": "amber lantern drifting over hills" });
+    assert_eq!(200, app.login_user(&login).await.status().as_u16());
+    let res = app
+        .http_client
+        .post(format!("{}/auth/password-reset", app.address))
+        .json(&reset)
+        .send()
+        .await
+        .expect("Failed to send password reset.");
+    assert_eq!(401, res.status().as_u16());
+    assert_eq!(1, count_audit_entries(&app, "user.password_reset_requested").await);
+}
+
+async fn impersonate(app: &TestApp, admin: &AuthResponse, user_id: Uuid) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}/admin/users/{}/impersonate", app.address, user_id))
+        .bearer_auth(&admin.access_token)
+        .send()
+        .await
+        .expect("Failed to send impersonate request.")
+}
+
+#[tokio::test]
+pub async fn an_impersonated_session_is_flagged_audited_and_restricted() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let auth = login(&app, &user).await;
+    let res = impersonate(&app, &admin, auth.id).await;
+    assert_eq!(200, res.status().as_u16());
+    let impersonation = res.json::<ImpersonationResponse>().await.unwrap();
+
+    // act
+    let profile = app
+        .http_client
+        .get(format!("{}/users/me", app.address))
+        .bearer_auth(&impersonation.access_token)
+        .send()
+        .await
+        .expect("Failed to send profile request.")
+        .json::<UserProfileResponse>()
+        .await
+        .unwrap();
+    let res = app
+        .change_password(
+            &impersonation.access_token,
+            "",
+            &json!({ "current_password": user.password, "new_password": "amber lantern drifting over hills" }),
+        )
+        .await;
+
+    // assert
+    assert_eq!(auth.id, profile.id);
+    assert_eq!(Some(admin.id), profile.impersonator_id);
+    assert_eq!(403, res.status().as_u16());
+    assert_eq!(1, count_audit_entries(&app, "user.impersonation_started").await);
+    assert_eq!(2, count_audit_entries(&app, "impersonation.request").await);
+}
+
+#[tokio::test]
+pub async fn an_impersonation_cannot_revoke_sessions_or_api_keys() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let auth = login(&app, &user).await;
+    let impersonation = impersonate(&app, &admin, auth.id)
+        .await
+        .json::<ImpersonationResponse>()
+        .await
+        .unwrap();
+
+    // act
+    let paths = [
+        "auth/sessions".to_string(),
+        format!("auth/sessions/{}", Uuid::new_v4()),
+        format!("api-keys/{}", Uuid::new_v4()),
+    ];
+    let mut statuses = Vec::new();
+    for path in paths {
+        let res = app
+            .http_client
+            .delete(format!("{}/{}", app.address, path))
+            .bearer_auth(&impersonation.access_token)
+            .send()
+            .await
+            .expect("Failed to send revoke request.");
+        statuses.push(res.status().as_u16());
+    }
+
+    // assert
+    assert_eq!(vec![403, 403, 403], statuses);
+}
+
+#[tokio::test]
+pub async fn an_impersonation_ends_when_the_admin_loses_the_role() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let auth = login(&app, &user).await;
+    let impersonation = impersonate(&app, &admin, auth.id)
+        .await
+        .json::<ImpersonationResponse>()
+        .await
+        .unwrap();
+    revoke_role_from_user(admin.id, "admin", &DbPool { pool: app.pool.clone() })
+        .await
+        .expect("Failed to revoke admin role.");
+
+    // act
+    let res = app
+        .http_client
+        .get(format!("{}/users/me", app.address))
+        .bearer_auth(&impersonation.access_token)
+        .send()
+        .await
+        .expect("Failed to send profile request.");
+
+    // assert
+    assert_eq!(401, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn administrators_cannot_be_impersonated() {
+    // arrange
+    let app = spawn_app().await;
+    let admin = login_admin(&app).await;
+    let other = TestUser::generate();
+    other.store_user(&app).await;
+    other.grant_role(&app.pool, "admin").await;
+    let other_auth = login(&app, &other).await;
+
+    // act
+    let other_admin = impersonate(&app, &admin, other_auth.id).await;
+    let itself = impersonate(&app, &admin, admin.id).await;
+
+    // assert
+    assert_eq!(403, other_admin.status().as_u16());
+    assert_eq!(403, itself.status().as_u16());
+}
@@ -0,0 +1,111 @@
+use serde_json::json;
+use test_rs::features::{
+    api_keys::controller::CreatedApiKeyResponse, auth::controller::AuthResponse,
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn create_key(app: &TestApp) -> (AuthResponse, CreatedApiKeyResponse) {
+    app.test_user.store_user(app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    let res = app
+        .create_api_key(&auth.access_token, &json!({ "name": "ci", "expires_in_days": 30 }))
+        .await;
+    assert_eq!(201, res.status().as_u16());
+
+    (auth, res.json::<CreatedApiKeyResponse>().await.unwrap())
+}
+
+async fn get_sessions_with_key(app: &TestApp, key: &str) -> reqwest::Response {
+    app.http_client
+        .get(format!("{}/auth/sessions", app.address))
+        .header("X-Api-Key", key)
+        .send()
+        .await
+        .expect("Failed to send sessions request.")
+}
+
+#[tokio::test]
+pub async fn an_api_key_authenticates_its_owner() {
+    // arrange
+    let app = spawn_app().await;
+    let (_, api_key) = create_key(&app).await;
+
+    // act
+    let res = get_sessions_with_key(&app, &api_key.key).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let (key_hash, last_used_at): (String, Option<chrono::DateTime<chrono::Utc>>) =
+        sqlx::query_as("SELECT key_hash, last_used_at FROM api_keys WHERE id = $1")
+            .bind(api_key.id)
+            .fetch_one(&app.pool)
+            .await
+            .expect("Failed to fetch API key.");
+    assert_ne!(api_key.key, key_hash);
+    assert!(last_used_at.is_some());
+}
+
+#[tokio::test]
+pub async fn a_revoked_api_key_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+    let (auth, api_key) = create_key(&app).await;
+
+    // act
+    let res = app
+        .http_client
+        .delete(format!("{}/api-keys/{}", app.address, api_key.id))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send revoke request.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    assert_eq!(401, get_sessions_with_key(&app, &api_key.key).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_expired_api_key_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+    let (_, api_key) = create_key(&app).await;
+    sqlx::query("UPDATE api_keys SET expires_at = now() - interval '1 minute' WHERE id = $1")
+        .bind(api_key.id)
+        .execute(&app.pool)
+        .await
+        .expect("Failed to expire API key.");
+
+    // act
+    let res = get_sessions_with_key(&app, &api_key.key).await;
+
+    // assert
+    assert_eq!(401, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_api_key_cannot_create_api_keys() {
+    // arrange
+    let app = spawn_app().await;
+    let (_, api_key) = create_key(&app).await;
+
+    // act
+    let res = app
+        .http_client
+        .post(format!("{}/api-keys", app.address))
+        .header("X-Api-Key", &api_key.key)
+        .json(&json!({ "name": "escalation", "expires_in_days": 365 }))
+        .send()
+        .await
+        .expect("Failed to send create API key request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
@@ -1,16 +1,28 @@
 use claims::assert_ok;
+use secrecy::Secret;
 use serde_json::json;
 use test_rs::{
-    features::auth::controller::AuthResponse, utils::randomizer::generate_random_string,
+    configurations::{get_config, CookieSameSite, PasswordHashingSettings},
+    db::DbPool,
+    features::auth::{controller::AuthResponse, domain::Credentials, repository::create_user},
+    utils::{
+        password_hasher::{PwdHasher, ServerPwdHasher}, randomizer::generate_random_string,
+        token_hash::hash_token,
+    },
 };
 
-use crate::helpers::{spawn_app, TestUser};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_refresh_token, spawn_app, spawn_app_with, TestUser};
 
 #[tokio::test]
 pub async fn a_valid_credentials_is_accepted() {
     // arrange
     let app = spawn_app().await;
-    app.test_user.store_user(&app.pool).await;
+    app.test_user.store_user(&app).await;
 
     // act
     let res = app.login_user(&json!(app.test_user)).await;
@@ -19,7 +31,7 @@ pub async fn a_valid_credentials_is_accepted() {
     // assert
     assert_eq!(200, res.status().as_u16());
     assert_ok!(res.json::<AuthResponse>().await);
-    assert_eq!(true, refresh_token);
+    assert!(refresh_token);
 }
 
 #[tokio::test]
@@ -27,7 +39,7 @@ pub async fn an_invalid_username_is_rejected_and_would_return_401() {
     // arrange
     let app = spawn_app().await;
     let mut test_user = TestUser::generate();
-    test_user.store_user(&app.pool).await;
+    test_user.store_user(&app).await;
 
     test_user.username = generate_random_string(12);
 
@@ -43,7 +55,7 @@ pub async fn an_invalid_password_is_rejected_and_would_return_401() {
     // arrange
     let app = spawn_app().await;
     let mut test_user = TestUser::generate();
-    test_user.store_user(&app.pool).await;
+    test_user.store_user(&app).await;
 
     test_user.password = generate_random_string(12);
 
@@ -79,7 +91,7 @@ pub async fn login_returns_400_when_credentials_are_present_but_invalid() {
     let app = spawn_app().await;
     let credentials = vec![
         json!({"username": generate_random_string(24), "password": generate_random_string(12)}),
-        json!({"username": generate_random_string(12), "password": generate_random_string(24)}),
+        json!({"username": generate_random_string(12), "password": generate_random_string(1025)}),
         json!({"username": "", "password": generate_random_string(12)}),
         json!({"username": generate_random_string(12), "password": ""}),
     ];
@@ -92,3 +104,208 @@ pub async fn login_returns_400_when_credentials_are_present_but_invalid() {
         assert_eq!(400, res.status().as_u16());
     }
 }
+
+#[tokio::test]
+pub async fn repeated_failed_logins_lock_the_account_and_return_423() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let wrong_credentials = json!({
+        "username": app.test_user.username,
+        "password": generate_random_string(12)
+    });
+
+    for _ in 0..5 {
+        let res = app.login_user(&wrong_credentials).await;
+        assert_eq!(401, res.status().as_u16());
+    }
+
+    // Act
+    let res = app.login_user(&json!(app.test_user)).await;
+
+    // Assert
+    assert_eq!(423, res.status().as_u16());
+    assert!(res.headers().get("retry-after").is_some());
+}
+
+#[tokio::test]
+pub async fn failed_logins_by_email_and_username_share_a_lockout() {
+    // arrange
+    let app = spawn_app_with(|c| c.login_throttle.max_attempts = 2).await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.register_confirmed_user(&json!({
+        "username": app.test_user.username,
+        "email": "ada@example.com",
+        "password": app.test_user.password
+    }))
+    .await;
+
+    for identifier in ["Ada@Example.com", app.test_user.username.as_str()] {
+        let res = app
+            .login_user(&json!({
+                "username": identifier,
+                "password": generate_random_string(12)
+            }))
+            .await;
+        assert_eq!(401, res.status().as_u16());
+    }
+    // Only the account's lockout is under test, not the one for the address.
+    sqlx::query("DELETE FROM login_attempts WHERE key_type = 'ip'")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to clear the address lockout.");
+
+    // act
+    let by_email = app
+        .login_user(&json!({
+            "username": "ada@example.com",
+            "password": app.test_user.password
+        }))
+        .await;
+    let by_username = app.login_user(&json!(app.test_user)).await;
+
+    // assert
+    assert_eq!(423, by_email.status().as_u16());
+    assert_eq!(423, by_username.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn refresh_tokens_are_stored_hashed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    // Act
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+
+    // Assert
+    let stored: String = sqlx::query_scalar("SELECT token_hash FROM user_tokens")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch stored refresh token.");
+    assert_ne!(rt, stored);
+    assert_eq!(hash_token(&rt), stored);
+}
+
+#[tokio::test]
+pub async fn refresh_cookie_follows_the_configured_settings() {
+    // arrange
+    let app = spawn_app_with(|c| {
+        c.jwt.refresh_token_ttl_seconds = 3600;
+        c.jwt.refresh_cookie.name = "__Host-refresh".into();
+        c.jwt.refresh_cookie.same_site = CookieSameSite::Strict;
+    })
+    .await;
+    app.test_user.store_user(&app).await;
+
+    // act
+    let res = app.login_user(&json!(app.test_user)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let cookie = res
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| cookie::Cookie::parse(v.to_str().ok()?.to_string()).ok())
+        .find(|c| c.name() == "__Host-refresh")
+        .expect("Refresh token cookie was not set.");
+    let max_age = cookie.max_age().expect("Cookie max-age was not set.");
+    assert!((3590..=3600).contains(&max_age.whole_seconds()));
+    assert_eq!(Some(cookie::SameSite::Strict), cookie.same_site());
+}
+
+#[tokio::test]
+pub async fn an_outdated_password_hash_is_upgraded_on_login() {
+    // arrange
+    let app = spawn_app().await;
+    let old_hasher = ServerPwdHasher::new(&PasswordHashingSettings {
+        memory_cost_kib: 1024,
+        time_cost: 1,
+        parallelism: 1,
+        current_pepper_id: None,
+        peppers: Default::default(),
+    })
+    .unwrap();
+    let credentials = Credentials {
+        username: app.test_user.username.to_string(),
+        password: app.test_user.password.to_string(),
+        email: None,
+    };
+    create_user(&credentials, &DbPool { pool: app.pool.clone() }, &old_hasher)
+        .await
+        .expect("Failed to create test user.");
+
+    // act
+    let res = app.login_user(&json!(app.test_user)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let hash: String = sqlx::query_scalar("SELECT password FROM users WHERE username = $1")
+        .bind(&app.test_user.username)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch password hash.");
+    assert!(hash.contains("m=15000,t=2,p=1"));
+    assert_eq!(200, app.login_user(&json!(app.test_user)).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_hash_with_a_retired_pepper_is_upgraded_on_login() {
+    // arrange
+    let old_pepper = ("old".to_string(), Secret::new("old-pepper".to_string()));
+    let app = spawn_app_with(|c| {
+        c.password_hashing.peppers.insert(old_pepper.0.clone(), old_pepper.1.clone());
+    })
+    .await;
+    let config = get_config().expect("Failed to parse configuration.");
+    let old_hasher = ServerPwdHasher::new(&PasswordHashingSettings {
+        current_pepper_id: Some(old_pepper.0.clone()),
+        peppers: [old_pepper].into(),
+        ..config.password_hashing.clone()
+    })
+    .unwrap();
+    let credentials = Credentials {
+        username: app.test_user.username.to_string(),
+        password: app.test_user.password.to_string(),
+        email: None,
+    };
+    create_user(&credentials, &DbPool { pool: app.pool.clone() }, &old_hasher)
+        .await
+        .expect("Failed to create test user.");
+
+    // act
+    let res = app.login_user(&json!(app.test_user)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let hash: String = sqlx::query_scalar("SELECT password FROM users WHERE username = $1")
+        .bind(&app.test_user.username)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch password hash.");
+    let current_hasher = ServerPwdHasher::new(&config.password_hashing).unwrap();
+    assert!(!current_hasher.needs_rehash(&hash));
+}
+
+#[tokio::test]
+pub async fn test_users_are_hashed_with_the_apps_settings() {
+    // arrange
+    let app = spawn_app_with(|c| {
+        c.password_hashing.peppers = [("other".to_string(), Secret::new("other-pepper".to_string()))].into();
+        c.password_hashing.current_pepper_id = Some("other".into());
+    })
+    .await;
+    app.test_user.store_user(&app).await;
+
+    // act
+    let res = app.login_user(&json!(app.test_user)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+}
@@ -0,0 +1,48 @@
+use serde_json::json;
+use test_rs::features::auth::controller::AuthResponse;
+
+use crate::helpers::{get_refresh_token, spawn_app};
+
+#[tokio::test]
+pub async fn access_token_is_rejected_after_logout() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app.logout_user(&auth.access_token, &rt).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    assert_eq!(401, app.get_sessions(&auth.access_token, None).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn logout_only_revokes_the_presented_access_token() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let first = app.login_user(&json!(app.test_user)).await;
+    let first_rt = get_refresh_token(&first);
+    let first_auth = first.json::<AuthResponse>().await.unwrap();
+    let second_auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    // act
+    app.logout_user(&first_auth.access_token, &first_rt).await;
+
+    // assert
+    assert_eq!(200, app.get_sessions(&second_auth.access_token, None).await.status().as_u16());
+    let revoked: i64 = sqlx::query_scalar("SELECT count(*) FROM revoked_tokens")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count revoked tokens.");
+    assert_eq!(1, revoked);
+}
@@ -0,0 +1,111 @@
+use serde_json::json;
+use test_rs::{features::auth::controller::AuthResponse, utils::randomizer::generate_random_string};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_cookie, get_refresh_token, spawn_app, TestApp};
+
+async fn register_with_email(app: &TestApp) -> AuthResponse {
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.register_confirmed_user(&json!({
+        "username": generate_random_string(12),
+        "email": "ada@example.com",
+        "password": "violet kettle marching under rain"
+    }))
+    .await
+    .json::<AuthResponse>()
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+pub async fn a_magic_link_logs_in_the_browser_that_requested_it_once() {
+    // arrange
+    let app = spawn_app().await;
+    let user = register_with_email(&app).await;
+    let res = app.request_magic_link(&json!({ "email": "Ada@example.com" })).await;
+    assert_eq!(202, res.status().as_u16());
+    let nonce = get_cookie(&res, "magic_link_nonce").expect("Nonce cookie was not set.");
+    app.wait_for_emails(2).await;
+    let token = app.get_email_token().await;
+
+    // act
+    let res = app.consume_magic_link(&token, Some(&nonce)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let rt = get_refresh_token(&res);
+    assert_eq!(Some(String::new()), get_cookie(&res, "magic_link_nonce"));
+    assert_eq!(user.id, res.json::<AuthResponse>().await.unwrap().id);
+    assert_eq!(200, app.refresh_token(&rt).await.status().as_u16());
+    assert_eq!(401, app.consume_magic_link(&token, Some(&nonce)).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_forwarded_magic_link_is_rejected_without_burning_it() {
+    // arrange
+    let app = spawn_app().await;
+    register_with_email(&app).await;
+    let res = app.request_magic_link(&json!({ "email": "ada@example.com" })).await;
+    let nonce = get_cookie(&res, "magic_link_nonce").expect("Nonce cookie was not set.");
+    let other_nonce = get_cookie(
+        &app.request_magic_link(&json!({ "email": "nobody@example.com" })).await,
+        "magic_link_nonce",
+    )
+    .expect("Nonce cookie was not set.");
+    app.wait_for_emails(2).await;
+    let token = app.get_email_token().await;
+
+    // act
+    let without_nonce = app.consume_magic_link(&token, None).await;
+    let other_browser = app.consume_magic_link(&token, Some(&other_nonce)).await;
+
+    // assert
+    assert_eq!(401, without_nonce.status().as_u16());
+    assert_eq!(401, other_browser.status().as_u16());
+    assert_eq!(200, app.consume_magic_link(&token, Some(&nonce)).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_unknown_email_is_answered_like_a_known_one() {
+    // arrange
+    let app = spawn_app().await;
+    register_with_email(&app).await;
+    let sent = app.email_server.received_requests().await.unwrap().len();
+
+    // act
+    let res = app.request_magic_link(&json!({ "email": "nobody@example.com" })).await;
+
+    // assert
+    assert_eq!(202, res.status().as_u16());
+    assert!(get_cookie(&res, "magic_link_nonce").is_some());
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert_eq!(sent, app.email_server.received_requests().await.unwrap().len());
+}
+
+#[tokio::test]
+pub async fn a_failed_send_is_answered_like_a_sent_link() {
+    // arrange
+    let app = spawn_app().await;
+    register_with_email(&app).await;
+    app.email_server.reset().await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    // act
+    let res = app.request_magic_link(&json!({ "email": "ada@example.com" })).await;
+
+    // assert
+    assert_eq!(202, res.status().as_u16());
+    assert!(get_cookie(&res, "magic_link_nonce").is_some());
+}
@@ -1 +1,7 @@
-pub mod login;
\ No newline at end of file
+pub mod login;
+pub mod logout;
+pub mod magic_link;
+pub mod refresh;
+pub mod register;
+pub mod server_sessions;
+pub mod sessions;
\ No newline at end of file
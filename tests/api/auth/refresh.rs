@@ -0,0 +1,129 @@
+use serde_json::json;
+
+use crate::helpers::{get_refresh_token, spawn_app};
+
+#[tokio::test]
+pub async fn a_valid_refresh_token_is_rotated() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+
+    // act
+    let res = app.refresh_token(&rt).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    assert_ne!(rt, get_refresh_token(&res));
+}
+
+#[tokio::test]
+pub async fn a_missing_refresh_token_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+
+    // act
+    let res = app.refresh_token("").await;
+
+    // assert
+    assert_eq!(401, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn reusing_a_rotated_token_revokes_only_its_family() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    let first_device_rt = get_refresh_token(&app.login_user(&json!(app.test_user)).await);
+    let second_device_rt = get_refresh_token(&app.login_user(&json!(app.test_user)).await);
+
+    let rotated_rt = get_refresh_token(&app.refresh_token(&first_device_rt).await);
+
+    // act
+    let reuse = app.refresh_token(&first_device_rt).await;
+
+    // assert
+    assert_eq!(401, reuse.status().as_u16());
+    assert_eq!(401, app.refresh_token(&rotated_rt).await.status().as_u16());
+    assert_eq!(200, app.refresh_token(&second_device_rt).await.status().as_u16());
+
+    let incidents: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM security_incidents WHERE kind = 'refresh_token_reuse'",
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to fetch security incidents.");
+    assert_eq!(1, incidents);
+}
+
+#[tokio::test]
+pub async fn a_refresh_without_a_csrf_token_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let rt = get_refresh_token(&app.login_user(&json!(app.test_user)).await);
+    let url = format!("{}/auth/refresh", app.address);
+
+    // act
+    let by_get = app.http_client
+        .get(&url)
+        .header("Cookie", format!("rt={}", rt))
+        .send()
+        .await
+        .expect("Failed to send refresh request.");
+    let without_token = app.http_client
+        .post(&url)
+        .header("Cookie", format!("rt={}", rt))
+        .send()
+        .await
+        .expect("Failed to send refresh request.");
+
+    // assert
+    assert_eq!(405, by_get.status().as_u16());
+    assert_eq!(403, without_token.status().as_u16());
+    assert_eq!(200, app.refresh_token(&rt).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_refresh_from_another_origin_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let rt = get_refresh_token(&app.login_user(&json!(app.test_user)).await);
+    let csrf_token = app.get_csrf_token().await;
+
+    // act
+    let res = app.http_client
+        .post(format!("{}/auth/refresh", app.address))
+        .header("Cookie", format!("rt={}; csrf_token={}", rt, csrf_token))
+        .header("X-CSRF-Token", &csrf_token)
+        .header("Origin", "https://evil.example")
+        .send()
+        .await
+        .expect("Failed to send refresh request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_cookie_only_logout_requires_a_csrf_token() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let rt = get_refresh_token(&app.login_user(&json!(app.test_user)).await);
+
+    // act
+    let res = app.http_client
+        .post(format!("{}/auth/logout", app.address))
+        .header("Cookie", format!("rt={}", rt))
+        .send()
+        .await
+        .expect("Failed to send logout request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+    assert_eq!(200, app.refresh_token(&rt).await.status().as_u16());
+}
@@ -0,0 +1,220 @@
+use serde_json::json;
+use test_rs::utils::randomizer::generate_random_string;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+#[tokio::test]
+pub async fn a_passphrase_is_accepted() {
+    // arrange
+    let app = spawn_app().await;
+
+    // act
+    let res = app
+        .register_user(&json!({
+            "username": generate_random_string(12),
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_password_breaking_the_policy_returns_field_error_codes() {
+    // arrange
+    let app = spawn_app().await;
+    let cases = [
+        ("short1", "password_too_short"),
+        ("passwordpassword", "password_breached"),
+        ("aaaaaaaaaaaaaaaa", "password_too_weak"),
+    ];
+
+    for (password, code) in cases {
+        // act
+        let res = app
+            .register_user(&json!({
+                "username": generate_random_string(12),
+                "password": password
+            }))
+            .await;
+
+        // assert
+        assert_eq!(400, res.status().as_u16());
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        let codes = body["fields"]["password"]
+            .as_array()
+            .expect("Password errors were not listed.");
+        assert!(codes.iter().any(|c| c == code), "{} was not reported", code);
+    }
+}
+
+#[tokio::test]
+pub async fn an_invalid_username_is_reported_under_its_own_field() {
+    // arrange
+    let app = spawn_app().await;
+
+    // act
+    let res = app
+        .register_user(&json!({
+            "username": "",
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(400, res.status().as_u16());
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(json!(["invalid_username"]), body["fields"]["username"]);
+    assert!(body["fields"].get("password").is_none());
+}
+
+#[tokio::test]
+pub async fn a_username_taken_in_another_case_returns_409() {
+    // arrange
+    let app = spawn_app().await;
+    let username = generate_random_string(12);
+    app.register_user(&json!({
+        "username": username.to_lowercase(),
+        "password": "violet kettle marching under rain"
+    }))
+    .await;
+
+    // act
+    let res = app
+        .register_user(&json!({
+            "username": username.to_uppercase(),
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(409, res.status().as_u16());
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!("ConflictError", body["error_type"]);
+}
+
+#[tokio::test]
+pub async fn a_fullwidth_username_logs_in_to_the_same_account() {
+    // arrange
+    let app = spawn_app().await;
+    app.register_user(&json!({
+        "username": "tester",
+        "password": "violet kettle marching under rain"
+    }))
+    .await;
+
+    // act
+    let res = app
+        .login_user(&json!({
+            "username": "ＴＥＳＴＥＲ",
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_user_registered_with_an_email_can_log_in_with_it() {
+    // arrange
+    let app = spawn_app().await;
+    mount_email_server(&app).await;
+    app.register_confirmed_user(&json!({
+        "username": generate_random_string(12),
+        "email": "ada@example.com",
+        "password": "violet kettle marching under rain"
+    }))
+    .await;
+
+    // act
+    let res = app
+        .login_user(&json!({
+            "username": "ADA@example.com",
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_invalid_or_taken_email_is_rejected() {
+    // arrange
+    let app = spawn_app().await;
+    mount_email_server(&app).await;
+    app.register_confirmed_user(&json!({
+        "username": generate_random_string(12),
+        "email": "ada@example.com",
+        "password": "violet kettle marching under rain"
+    }))
+    .await;
+
+    // act
+    let invalid = app
+        .register_user(&json!({
+            "username": generate_random_string(12),
+            "email": "ada",
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+    let taken = app
+        .register_user(&json!({
+            "username": generate_random_string(12),
+            "email": "Ada@Example.com",
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(400, invalid.status().as_u16());
+    let body = invalid.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(json!(["invalid_email"]), body["fields"]["email"]);
+    assert_eq!(409, taken.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_unconfirmed_email_neither_logs_in_nor_blocks_its_owner() {
+    // arrange
+    let app = spawn_app().await;
+    mount_email_server(&app).await;
+    app.register_user(&json!({
+        "username": generate_random_string(12),
+        "email": "ada@example.com",
+        "password": "violet kettle marching under rain"
+    }))
+    .await;
+
+    // act
+    let login = app
+        .login_user(&json!({
+            "username": "ada@example.com",
+            "password": "violet kettle marching under rain"
+        }))
+        .await;
+    let owner = app
+        .register_user(&json!({
+            "username": generate_random_string(12),
+            "email": "Ada@Example.com",
+            "password": "amber lantern drifting over hills"
+        }))
+        .await;
+
+    // assert
+    assert_eq!(401, login.status().as_u16());
+    assert_eq!(200, owner.status().as_u16());
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
@@ -0,0 +1,233 @@
+use serde_json::json;
+use test_rs::{
+    configurations::{SessionMode, SessionStoreKind},
+    db::DbPool,
+    features::{
+        auth::{
+            controller::{SessionLoginResponse, SessionResponse},
+            repository::get_user_by_username,
+        },
+        users::controller::UserProfileResponse,
+    },
+};
+
+use crate::helpers::{get_cookie, spawn_app_with, TestApp, TestUser};
+
+async fn spawn_server_session_app(store: SessionStoreKind) -> TestApp {
+    spawn_app_with(|c| {
+        c.session.mode = SessionMode::Server;
+        c.session.store = store;
+    })
+    .await
+}
+
+async fn get_profile(app: &TestApp, session_id: &str) -> reqwest::Response {
+    app.http_client
+        .get(format!("{}/users/me", app.address))
+        .header("Cookie", format!("sid={}", session_id))
+        .send()
+        .await
+        .expect("Failed to send profile request.")
+}
+
+/// Sends a write authenticated by the session cookie, with the CSRF token it needs.
+async fn send_with_session(
+    app: &TestApp,
+    request: reqwest::RequestBuilder,
+    session_id: &str,
+) -> reqwest::Response {
+    let csrf_token = app.get_csrf_token().await;
+
+    request
+        .header("Cookie", format!("sid={}; csrf_token={}", session_id, csrf_token))
+        .header("X-CSRF-Token", &csrf_token)
+        .send()
+        .await
+        .expect("Failed to send request.")
+}
+
+async fn login_session(app: &TestApp) -> String {
+    get_cookie(&app.login_user(&json!(app.test_user)).await, "sid")
+        .expect("Session cookie was not set.")
+}
+
+#[tokio::test]
+pub async fn a_session_cookie_authenticates_like_an_access_token() {
+    for store in [SessionStoreKind::Postgres, SessionStoreKind::Memory] {
+        // arrange
+        let app = spawn_server_session_app(store).await;
+        app.test_user.store_user(&app).await;
+        let res = app.login_user(&json!(app.test_user)).await;
+        assert_eq!(200, res.status().as_u16());
+        assert!(get_cookie(&res, "rt").is_none());
+        let session_id = get_cookie(&res, "sid").expect("Session cookie was not set.");
+        let login = res.json::<SessionLoginResponse>().await.unwrap();
+
+        // act
+        let res = get_profile(&app, &session_id).await;
+
+        // assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(login.id, res.json::<UserProfileResponse>().await.unwrap().id);
+    }
+}
+
+#[tokio::test]
+pub async fn logging_out_ends_the_server_session() {
+    // arrange
+    let app = spawn_server_session_app(SessionStoreKind::Postgres).await;
+    app.test_user.store_user(&app).await;
+    let session_id = get_cookie(&app.login_user(&json!(app.test_user)).await, "sid").unwrap();
+    let csrf_token = app.get_csrf_token().await;
+
+    // act
+    let res = app
+        .http_client
+        .post(format!("{}/auth/logout", app.address))
+        .header("Cookie", format!("sid={}; csrf_token={}", session_id, csrf_token))
+        .header("X-CSRF-Token", &csrf_token)
+        .send()
+        .await
+        .expect("Failed to send logout request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    assert_eq!(Some(String::new()), get_cookie(&res, "sid"));
+    assert_eq!(401, get_profile(&app, &session_id).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn session_authenticated_writes_require_a_csrf_token() {
+    // arrange
+    let app = spawn_server_session_app(SessionStoreKind::Memory).await;
+    app.test_user.store_user(&app).await;
+    let session_id = get_cookie(&app.login_user(&json!(app.test_user)).await, "sid").unwrap();
+
+    // act
+    let res = app
+        .http_client
+        .patch(format!("{}/users/me", app.address))
+        .header("Cookie", format!("sid={}", session_id))
+        .json(&json!({ "display_name": "Ada" }))
+        .send()
+        .await
+        .expect("Failed to send update profile request.");
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn bearer_tokens_are_not_accepted_in_server_session_mode() {
+    // arrange
+    let app = spawn_server_session_app(SessionStoreKind::Memory).await;
+
+    // act
+    let res = app
+        .http_client
+        .get(format!("{}/users/me", app.address))
+        .bearer_auth("not-a-session")
+        .send()
+        .await
+        .expect("Failed to send profile request.");
+
+    // assert
+    assert_eq!(401, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn server_sessions_are_listed_and_revoked() {
+    for store in [SessionStoreKind::Postgres, SessionStoreKind::Memory] {
+        // arrange
+        let app = spawn_server_session_app(store).await;
+        app.test_user.store_user(&app).await;
+        let current = login_session(&app).await;
+        let other = login_session(&app).await;
+        let last = login_session(&app).await;
+
+        // act
+        let sessions = app
+            .http_client
+            .get(format!("{}/auth/sessions", app.address))
+            .header("Cookie", format!("sid={}", current))
+            .send()
+            .await
+            .expect("Failed to send sessions request.")
+            .json::<Vec<SessionResponse>>()
+            .await
+            .unwrap();
+        let other_id = sessions.iter().find(|s| !s.current).unwrap().id;
+        let revoked = send_with_session(
+            &app,
+            app.http_client
+                .delete(format!("{}/auth/sessions/{}", app.address, other_id)),
+            &current,
+        )
+        .await;
+        let remaining = send_with_session(
+            &app,
+            app.http_client.delete(format!("{}/auth/sessions", app.address)),
+            &current,
+        )
+        .await;
+
+        // assert
+        assert_eq!(3, sessions.len());
+        assert_eq!(1, sessions.iter().filter(|s| s.current).count());
+        assert_eq!(204, revoked.status().as_u16());
+        assert_eq!(204, remaining.status().as_u16());
+        assert_eq!(200, get_profile(&app, &current).await.status().as_u16());
+        assert_eq!(401, get_profile(&app, &other).await.status().as_u16());
+        assert_eq!(401, get_profile(&app, &last).await.status().as_u16());
+    }
+}
+
+#[tokio::test]
+pub async fn deleting_the_account_clears_the_session_cookie() {
+    // arrange
+    let app = spawn_server_session_app(SessionStoreKind::Memory).await;
+    app.test_user.store_user(&app).await;
+    let session_id = login_session(&app).await;
+
+    // act
+    let res = send_with_session(
+        &app,
+        app.http_client
+            .delete(format!("{}/users/me", app.address))
+            .json(&json!({ "password": app.test_user.password })),
+        &session_id,
+    )
+    .await;
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    assert_eq!(Some(String::new()), get_cookie(&res, "sid"));
+    assert_eq!(401, get_profile(&app, &session_id).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn impersonation_is_rejected_in_server_session_mode() {
+    // arrange
+    let app = spawn_server_session_app(SessionStoreKind::Memory).await;
+    app.test_user.store_user(&app).await;
+    app.test_user.grant_role(&app.pool, "admin").await;
+    let session_id = login_session(&app).await;
+    let user = TestUser::generate();
+    user.store_user(&app).await;
+    let user_id = get_user_by_username(&user.username, &DbPool { pool: app.pool.clone() })
+        .await
+        .unwrap()
+        .id;
+
+    // act
+    let res = send_with_session(
+        &app,
+        app.http_client
+            .post(format!("{}/admin/users/{}/impersonate", app.address, user_id)),
+        &session_id,
+    )
+    .await;
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
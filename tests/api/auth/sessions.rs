@@ -0,0 +1,132 @@
+use serde_json::json;
+use test_rs::features::auth::controller::{AuthResponse, SessionResponse};
+use uuid::Uuid;
+
+use crate::helpers::{get_refresh_token, spawn_app};
+
+#[tokio::test]
+pub async fn sessions_are_listed_with_the_current_one_flagged() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    app.login_user(&json!({
+        "username": app.test_user.username,
+        "password": app.test_user.password,
+        "device_label": "Work laptop"
+    }))
+    .await;
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app.get_sessions(&auth.access_token, Some(&rt)).await;
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let sessions = res.json::<Vec<SessionResponse>>().await.unwrap();
+    assert_eq!(2, sessions.len());
+    assert_eq!(1, sessions.iter().filter(|s| s.current).count());
+    assert!(sessions.iter().any(|s| s.device_label.as_deref() == Some("Work laptop")));
+}
+
+#[tokio::test]
+pub async fn revoking_other_sessions_keeps_the_current_one() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    app.login_user(&json!(app.test_user)).await;
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app.http_client
+        .delete(format!("{}/auth/sessions", app.address))
+        .bearer_auth(&auth.access_token)
+        .header("Cookie", format!("rt={}", rt))
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let sessions = app.get_sessions(&auth.access_token, Some(&rt))
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .unwrap();
+    assert_eq!(1, sessions.len());
+    assert!(sessions[0].current);
+}
+
+#[tokio::test]
+pub async fn revoking_a_single_session_removes_it() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    let res = app.login_user(&json!(app.test_user)).await;
+    let auth = res.json::<AuthResponse>().await.unwrap();
+    let sessions = app.get_sessions(&auth.access_token, None)
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .unwrap();
+
+    // act
+    let res = app.http_client
+        .delete(format!("{}/auth/sessions/{}", app.address, sessions[0].id))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let sessions = app.get_sessions(&auth.access_token, None)
+        .await
+        .json::<Vec<SessionResponse>>()
+        .await
+        .unwrap();
+    assert!(sessions.is_empty());
+}
+
+#[tokio::test]
+pub async fn revoking_an_unknown_session_returns_404() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    let res = app.login_user(&json!(app.test_user)).await;
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app.http_client
+        .delete(format!("{}/auth/sessions/{}", app.address, Uuid::new_v4()))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(404, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn listing_sessions_requires_authentication() {
+    // arrange
+    let app = spawn_app().await;
+
+    // act
+    let res = app.http_client
+        .get(format!("{}/auth/sessions", app.address))
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(401, res.status().as_u16());
+}
@@ -2,8 +2,9 @@ use std::sync::LazyLock;
 
 use serde::Serialize;
 use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
-use test_rs::{configurations::{get_config, DatabaseSettings}, db::DbPool, features::auth::{domain::Credentials, repository::create_user}, startup::{get_db_pool, Application}, telemetry::{get_subscriber, init_subscriber}, utils::{password_hasher::ServerPwdHasher, randomizer::generate_random_string}};
+use test_rs::{configurations::{get_config, DatabaseSettings, Settings}, db::DbPool, features::auth::{domain::Credentials, repository::{create_user, get_user_by_username, grant_role_to_user}}, startup::{get_db_pool, Application}, telemetry::{get_subscriber, init_subscriber}, utils::{password_hasher::ServerPwdHasher, randomizer::generate_random_string}};
 use uuid::Uuid;
+use wiremock::MockServer;
 
 static TRACING : LazyLock<()> = LazyLock::new(|| {
     let app_name = "test";
@@ -23,6 +24,9 @@ pub struct TestApp {
     pub pool : PgPool,
     pub address : String,
     pub test_user : TestUser,
+    pub email_server : MockServer,
+    /// Built from the same settings as the app's, so stored test users can log in.
+    pub pwd_hasher : ServerPwdHasher,
     pub port : u16 
 }
 
@@ -35,33 +39,223 @@ impl TestApp {
             .await
             .expect("Failed to send login request.")
     }
+
+    pub async fn get_sessions(&self, access_token : &str, refresh_token : Option<&str>) -> reqwest::Response {
+        let mut req = self.http_client
+            .get(format!("{}/auth/sessions", self.address))
+            .bearer_auth(access_token);
+
+        if let Some(rt) = refresh_token {
+            req = req.header("Cookie", format!("rt={}", rt));
+        }
+
+        req.send()
+            .await
+            .expect("Failed to send sessions request.")
+    }
+
+    pub async fn register_user<T : serde::Serialize>(&self, body : T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/auth/register", self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to send register request.")
+    }
+
+    /// Registers a user with an email and opens the confirmation link sent to it. Expects the
+    /// email server to be mocked.
+    pub async fn register_confirmed_user<T : serde::Serialize>(&self, body : T) -> reqwest::Response {
+        let res = self.register_user(body).await;
+        let token = self.get_email_token().await;
+        assert_eq!(204, self.confirm_email(&token).await.status().as_u16());
+        res
+    }
+
+    pub async fn logout_user(&self, access_token : &str, refresh_token : &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/auth/logout", self.address))
+            .bearer_auth(access_token)
+            .header("Cookie", format!("rt={}", refresh_token))
+            .send()
+            .await
+            .expect("Failed to send logout request.")
+    }
+
+    pub async fn create_api_key<T : serde::Serialize>(&self, access_token : &str, body : T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/api-keys", self.address))
+            .bearer_auth(access_token)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to send create API key request.")
+    }
+
+    pub async fn change_password<T : serde::Serialize>(&self, access_token : &str, refresh_token : &str, body : T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/users/me/password", self.address))
+            .bearer_auth(access_token)
+            .header("Cookie", format!("rt={}", refresh_token))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to send change password request.")
+    }
+
+    pub async fn delete_account<T : serde::Serialize>(&self, access_token : &str, body : T) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/users/me", self.address))
+            .bearer_auth(access_token)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to send delete account request.")
+    }
+
+    pub async fn change_email<T : serde::Serialize>(&self, access_token : &str, body : T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/users/me/email", self.address))
+            .bearer_auth(access_token)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to send change email request.")
+    }
+
+    pub async fn confirm_email(&self, token : &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/users/email/confirm", self.address))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .expect("Failed to send confirm email request.")
+    }
+
+    /// Emails sent from background tasks arrive after the response, so wait until `count` were
+    /// received.
+    pub async fn wait_for_emails(&self, count : usize) {
+        for _ in 0..100 {
+            if self.email_server.received_requests().await.unwrap().len() >= count {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        panic!("Expected {} emails to be sent.", count);
+    }
+
+    /// The token from the confirmation link in the last email sent.
+    pub async fn get_email_token(&self) -> String {
+        let requests = self.email_server.received_requests().await.unwrap();
+        let body : serde_json::Value = serde_json::from_slice(&requests.last().expect("No email was sent.").body).unwrap();
+        let text = body["TextBody"].as_str().unwrap();
+
+        text.split("token=")
+            .nth(1)
+            .expect("Email has no confirmation link.")
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect()
+    }
+
+    pub async fn request_magic_link<T : serde::Serialize>(&self, body : T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/auth/magic-link", self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to send magic link request.")
+    }
+
+    pub async fn consume_magic_link(&self, token : &str, nonce : Option<&str>) -> reqwest::Response {
+        let mut req = self.http_client
+            .post(format!("{}/auth/magic-link/consume", self.address))
+            .json(&serde_json::json!({ "token": token }));
+
+        if let Some(nonce) = nonce {
+            req = req.header("Cookie", format!("magic_link_nonce={}", nonce));
+        }
+
+        req.send()
+            .await
+            .expect("Failed to send consume magic link request.")
+    }
+
+    pub async fn refresh_token(&self, refresh_token : &str) -> reqwest::Response {
+        let csrf_token = self.get_csrf_token().await;
+
+        self.http_client
+            .post(format!("{}/auth/refresh", self.address))
+            .header("Cookie", format!("rt={}; csrf_token={}", refresh_token, csrf_token))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to send refresh request.")
+    }
+
+    pub async fn get_csrf_token(&self) -> String {
+        let res = self.http_client
+            .get(format!("{}/auth/csrf", self.address))
+            .send()
+            .await
+            .expect("Failed to send CSRF token request.");
+        let body : serde_json::Value = res.json().await.unwrap();
+
+        body["csrf_token"].as_str().expect("CSRF token was not returned.").to_string()
+    }
+}
+
+pub fn get_refresh_token(res : &reqwest::Response) -> String {
+    get_cookie(res, "rt").expect("Refresh token cookie was not set.")
+}
+
+pub fn get_cookie(res : &reqwest::Response, name : &str) -> Option<String> {
+    res.headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| cookie::Cookie::parse(v.to_str().ok()?.to_string()).ok())
+        .find(|c| c.name() == name)
+        .map(|c| c.value().to_string())
 }
 
 pub async fn spawn_app () -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+pub async fn spawn_app_with (customize : impl FnOnce(&mut Settings)) -> TestApp {
     LazyLock::force(&TRACING);
 
+    let email_server = MockServer::start().await;
+
     let config = {
         let mut c = get_config()
             .expect("Failed to parse configuraiton.");
         c.app.port = 0;
         c.database.database_name = Uuid::new_v4().to_string();
+        c.email_client.base_url = email_server.uri();
+        customize(&mut c);
 
         c
     };
 
     configure_db(&config.database).await;
     let pool = get_db_pool(&config.database);
+    let pwd_hasher = ServerPwdHasher::new(&config.password_hashing)
+        .expect("Failed to build password hasher.");
     let http_client = reqwest::Client::new();
     let app = Application::build(config)
         .await
         .expect("Failed to build application.");
     let port = app.get_port();
-    let _ = tokio::spawn(app.run_until_stopped());
+    tokio::spawn(app.run_until_stopped());
 
     TestApp {
         http_client,
         pool,
         test_user : TestUser::generate(),
+        email_server,
+        pwd_hasher,
         address : format!("http://localhost:{}/api", port),
         port
     }
@@ -82,16 +276,27 @@ impl TestUser {
         }
     }
 
-    pub async fn store_user(&self, pool : &PgPool) {
-        let pwd_hasher = ServerPwdHasher;
-        let db_ctx = DbPool { pool : pool.clone() };
+    pub async fn store_user(&self, app : &TestApp) {
+        let db_ctx = DbPool { pool : app.pool.clone() };
 
-        let credentials = Credentials { username : self.username.to_string(), password: self.password.to_string() };
+        let credentials = Credentials { username : self.username.to_string(), password: self.password.to_string(), email : None };
 
-        create_user(&credentials, &db_ctx, &pwd_hasher)
+        create_user(&credentials, &db_ctx, &app.pwd_hasher)
             .await
             .expect("Failed to create test user.");
     }
+
+    pub async fn grant_role(&self, pool : &PgPool, role : &str) {
+        let db_ctx = DbPool { pool : pool.clone() };
+
+        let user = get_user_by_username(&self.username, &db_ctx)
+            .await
+            .expect("Failed to fetch test user.");
+
+        grant_role_to_user(user.id, role, &db_ctx)
+            .await
+            .expect("Failed to grant test user role.");
+    }
 }
 
 async fn configure_db(config : &DatabaseSettings) {
@@ -0,0 +1,57 @@
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, DecodingKey, Validation};
+use serde_json::json;
+use test_rs::{features::auth::controller::AuthResponse, utils::jwt::Claims};
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+pub async fn jwks_endpoint_publishes_the_verification_keys() {
+    // arrange
+    let app = spawn_app().await;
+
+    // act
+    let res = app.http_client
+        .get(format!("http://localhost:{}/.well-known/jwks.json", app.port))
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let jwks = res.json::<JwkSet>().await.unwrap();
+    assert!(jwks.find("dev-signing").is_some());
+}
+
+#[tokio::test]
+pub async fn access_tokens_can_be_verified_with_the_published_keys() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = app.login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    let jwks = app.http_client
+        .get(format!("http://localhost:{}/.well-known/jwks.json", app.port))
+        .send()
+        .await
+        .expect("Failed to send request.")
+        .json::<JwkSet>()
+        .await
+        .unwrap();
+
+    // act
+    let header = decode_header(&auth.access_token).unwrap();
+    let jwk = jwks.find(&header.kid.unwrap()).expect("Signing key was not published.");
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&["test"]);
+    let result = decode::<Claims>(
+        &auth.access_token,
+        &DecodingKey::from_jwk(jwk).unwrap(),
+        &validation,
+    );
+
+    // assert
+    assert_eq!(auth.id, result.unwrap().claims.id);
+}
@@ -1,3 +1,12 @@
+pub mod admin;
+pub mod api_keys;
 pub mod auth;
 pub mod health_check;
-pub mod helpers;
\ No newline at end of file
+pub mod helpers;
+pub mod jwks;
+pub mod maintenance;
+pub mod organizations;
+pub mod rate_limit;
+pub mod scopes;
+pub mod todos;
+pub mod users;
\ No newline at end of file
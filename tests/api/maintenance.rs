@@ -0,0 +1,155 @@
+use serde_json::json;
+use test_rs::{
+    configurations::get_config,
+    features::{
+        admin::controller::MaintenanceResponse, api_keys::controller::CreatedApiKeyResponse,
+        auth::controller::AuthResponse,
+    },
+    maintenance::MaintenanceWorker,
+};
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+pub async fn expired_refresh_tokens_are_purged_in_batches() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    for _ in 0..4 {
+        app.login_user(&json!(app.test_user)).await;
+    }
+    sqlx::query(
+        "UPDATE user_tokens SET expires_at = now() - interval '1 day' \
+         WHERE id NOT IN (SELECT id FROM user_tokens ORDER BY created_at DESC LIMIT 1)",
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to expire refresh tokens.");
+
+    let mut config = get_config().expect("Failed to parse configuration.");
+    config.maintenance.batch_size = 2;
+    let worker = MaintenanceWorker::new(app.pool.clone(), config.maintenance, &config.login_throttle);
+
+    // act
+    let result = worker.run_once().await;
+
+    // assert
+    assert!(result.is_ok());
+    let remaining: i64 = sqlx::query_scalar("SELECT count(*) FROM user_tokens")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count refresh tokens.");
+    assert_eq!(1, remaining);
+    assert_eq!(3, worker.metrics().rows_purged("expired_refresh_tokens"));
+}
+
+#[tokio::test]
+pub async fn a_failing_job_does_not_stop_the_remaining_jobs() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+
+    for _ in 0..2 {
+        app.login_user(&json!(app.test_user)).await;
+    }
+    sqlx::query("UPDATE user_tokens SET expires_at = now() - interval '1 day'")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to expire refresh tokens.");
+    sqlx::query("DROP TABLE revoked_tokens")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to drop the revoked tokens.");
+
+    let config = get_config().expect("Failed to parse configuration.");
+    let worker = MaintenanceWorker::new(app.pool.clone(), config.maintenance, &config.login_throttle);
+
+    // act
+    let result = worker.run_once().await;
+
+    // assert
+    assert!(result.is_err());
+    assert_eq!(1, worker.metrics().failures("expired_revoked_tokens"));
+    assert_eq!(2, worker.metrics().rows_purged("expired_refresh_tokens"));
+    assert_eq!(0, worker.metrics().failures("old_security_incidents"));
+    assert!(worker.metrics().snapshot().contains_key("old_security_incidents"));
+}
+
+#[tokio::test]
+pub async fn admins_can_read_the_running_workers_metrics() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    app.test_user.grant_role(&app.pool, "admin").await;
+    let admin = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    // act
+    // The worker runs once right after startup.
+    let mut metrics = MaintenanceResponse {
+        rows_purged: Default::default(),
+        failures: Default::default(),
+    };
+    for _ in 0..50 {
+        let res = app
+            .http_client
+            .get(format!("{}/admin/maintenance", app.address))
+            .bearer_auth(&admin.access_token)
+            .send()
+            .await
+            .expect("Failed to send maintenance request.");
+        assert_eq!(200, res.status().as_u16());
+
+        metrics = res.json::<MaintenanceResponse>().await.unwrap();
+        if metrics.rows_purged.contains_key("old_security_incidents") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    // assert
+    assert!(metrics.rows_purged.contains_key("expired_refresh_tokens"));
+    assert!(metrics.rows_purged.contains_key("old_security_incidents"));
+}
+
+#[tokio::test]
+pub async fn reading_the_metrics_requires_the_users_read_scope() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    app.test_user.grant_role(&app.pool, "admin").await;
+    let admin = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    for (scope, status) in [("roles:read", 403), ("users:read", 200)] {
+        let api_key = app
+            .create_api_key(
+                &admin.access_token,
+                &json!({ "name": scope, "expires_in_days": 1, "scopes": [scope] }),
+            )
+            .await
+            .json::<CreatedApiKeyResponse>()
+            .await
+            .unwrap();
+
+        // act
+        let res = app
+            .http_client
+            .get(format!("{}/admin/maintenance", app.address))
+            .header("X-Api-Key", &api_key.key)
+            .send()
+            .await
+            .expect("Failed to send maintenance request.");
+
+        // assert
+        assert_eq!(status, res.status().as_u16());
+    }
+}
@@ -0,0 +1,423 @@
+use serde_json::json;
+use test_rs::features::{
+    auth::controller::AuthResponse,
+    organizations::{
+        controller::{InvitationResponse, MemberResponse, OrganizationResponse},
+        domain::OrganizationRole,
+    },
+};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp, TestUser};
+
+pub async fn login(app: &TestApp, user: &TestUser) -> (Uuid, String) {
+    let auth = app
+        .login_user(&json!(user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    let (id,): (Uuid,) = sqlx::query_as("SELECT id FROM users WHERE username = $1")
+        .bind(&user.username)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch user id.");
+
+    (id, auth.access_token)
+}
+
+pub async fn create_organization(app: &TestApp, access_token: &str, name: &str) -> Uuid {
+    let res = app
+        .http_client
+        .post(format!("{}/organizations", app.address))
+        .bearer_auth(access_token)
+        .json(&json!({ "name": name }))
+        .send()
+        .await
+        .expect("Failed to send create organization request.");
+    assert_eq!(201, res.status().as_u16());
+
+    res.json::<OrganizationResponse>().await.unwrap().id
+}
+
+async fn set_member_role(
+    app: &TestApp,
+    access_token: &str,
+    organization_id: Uuid,
+    user_id: Uuid,
+    role: &str,
+) -> reqwest::Response {
+    app.http_client
+        .put(format!("{}/organizations/current/members/{}", app.address, user_id))
+        .bearer_auth(access_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .json(&json!({ "role": role }))
+        .send()
+        .await
+        .expect("Failed to send member role request.")
+}
+
+async fn invite_member(
+    app: &TestApp,
+    access_token: &str,
+    organization_id: Uuid,
+    username: &str,
+    role: &str,
+) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}/organizations/current/invitations", app.address))
+        .bearer_auth(access_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .json(&json!({ "username": username, "role": role }))
+        .send()
+        .await
+        .expect("Failed to send invite member request.")
+}
+
+async fn get_invitations(app: &TestApp, access_token: &str) -> Vec<InvitationResponse> {
+    app.http_client
+        .get(format!("{}/organizations/invitations", app.address))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .expect("Failed to send list invitations request.")
+        .json::<Vec<InvitationResponse>>()
+        .await
+        .unwrap()
+}
+
+async fn accept_invitation(app: &TestApp, access_token: &str, invitation_id: Uuid) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}/organizations/invitations/{}/accept", app.address, invitation_id))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .expect("Failed to send accept invitation request.")
+}
+
+/// Invites `user` and accepts on their behalf, returning their id and access token.
+async fn add_member(
+    app: &TestApp,
+    owner_token: &str,
+    organization_id: Uuid,
+    user: &TestUser,
+    role: &str,
+) -> (Uuid, String) {
+    user.store_user(app).await;
+    let (user_id, access_token) = login(app, user).await;
+
+    let res = invite_member(app, owner_token, organization_id, &user.username, role).await;
+    assert_eq!(202, res.status().as_u16());
+    let invitation = get_invitations(app, &access_token).await.pop().expect("No invitation.");
+    assert_eq!(204, accept_invitation(app, &access_token, invitation.id).await.status().as_u16());
+
+    (user_id, access_token)
+}
+
+async fn get_current_members(
+    app: &TestApp,
+    access_token: &str,
+    organization_id: Uuid,
+) -> Vec<MemberResponse> {
+    app.http_client
+        .get(format!("{}/organizations/current/members", app.address))
+        .bearer_auth(access_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .send()
+        .await
+        .expect("Failed to send list members request.")
+        .json::<Vec<MemberResponse>>()
+        .await
+        .unwrap()
+}
+
+async fn count_owners(app: &TestApp, organization_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM memberships WHERE organization_id = $1 AND role = 'owner'")
+        .bind(organization_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count owners.")
+}
+
+async fn remove_member(
+    app: &TestApp,
+    access_token: &str,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> reqwest::Response {
+    app.http_client
+        .delete(format!("{}/organizations/current/members/{}", app.address, user_id))
+        .bearer_auth(access_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .send()
+        .await
+        .expect("Failed to send remove member request.")
+}
+
+#[tokio::test]
+pub async fn a_user_lists_every_organization_they_belong_to() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, access_token) = login(&app, &app.test_user).await;
+    let first = create_organization(&app, &access_token, "First").await;
+    let second = create_organization(&app, &access_token, "Second").await;
+
+    // act
+    let res = app
+        .http_client
+        .get(format!("{}/organizations", app.address))
+        .bearer_auth(&access_token)
+        .send()
+        .await
+        .expect("Failed to send list organizations request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let organizations = res.json::<Vec<OrganizationResponse>>().await.unwrap();
+    assert_eq!(vec![first, second], organizations.iter().map(|o| o.id).collect::<Vec<_>>());
+    assert!(organizations.iter().all(|o| o.role == OrganizationRole::Owner));
+}
+
+#[tokio::test]
+pub async fn the_current_organization_requires_a_membership() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, access_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &access_token, "Acme").await;
+    let outsider = TestUser::generate();
+    outsider.store_user(&app).await;
+    let (_, outsider_token) = login(&app, &outsider).await;
+    let get_current = |token: String, header: Option<String>| {
+        let mut req = app
+            .http_client
+            .get(format!("{}/organizations/current", app.address))
+            .bearer_auth(token);
+        if let Some(header) = header {
+            req = req.header("X-Organization-Id", header);
+        }
+        req.send()
+    };
+
+    // act
+    let member = get_current(access_token.clone(), Some(organization_id.to_string())).await.unwrap();
+    let non_member = get_current(outsider_token, Some(organization_id.to_string())).await.unwrap();
+    let missing = get_current(access_token.clone(), None).await.unwrap();
+    let malformed = get_current(access_token, Some("acme".into())).await.unwrap();
+
+    // assert
+    assert_eq!(200, member.status().as_u16());
+    assert_eq!("Acme", member.json::<OrganizationResponse>().await.unwrap().name);
+    assert_eq!(403, non_member.status().as_u16());
+    assert_eq!(400, missing.status().as_u16());
+    assert_eq!(400, malformed.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_invited_user_joins_once_they_accept() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let invitee = TestUser::generate();
+    invitee.store_user(&app).await;
+    let (invitee_id, invitee_token) = login(&app, &invitee).await;
+
+    // act
+    let invited = invite_member(&app, &owner_token, organization_id, &invitee.username, "member").await;
+    let before = get_current_members(&app, &owner_token, organization_id).await;
+    let invitations = get_invitations(&app, &invitee_token).await;
+    let accepted = accept_invitation(&app, &invitee_token, invitations[0].id).await;
+
+    // assert
+    assert_eq!(202, invited.status().as_u16());
+    assert_eq!(1, before.len());
+    assert_eq!(1, invitations.len());
+    assert_eq!(organization_id, invitations[0].organization_id);
+    assert_eq!("Acme", invitations[0].organization_name);
+    assert_eq!(204, accepted.status().as_u16());
+    let members = get_current_members(&app, &invitee_token, organization_id).await;
+    assert_eq!(2, members.len());
+    assert!(members.iter().any(|m| m.user_id == invitee_id && m.role == OrganizationRole::Member));
+    assert!(get_invitations(&app, &invitee_token).await.is_empty());
+}
+
+#[tokio::test]
+pub async fn only_managers_can_invite_and_unknown_users_are_not_revealed() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let (_, member_token) =
+        add_member(&app, &owner_token, organization_id, &TestUser::generate(), "member").await;
+    let other = TestUser::generate();
+    other.store_user(&app).await;
+
+    // act
+    let rejected = invite_member(&app, &member_token, organization_id, &other.username, "member").await;
+    let unknown = invite_member(&app, &owner_token, organization_id, "nobody-here", "member").await;
+
+    // assert
+    assert_eq!(403, rejected.status().as_u16());
+    assert_eq!(202, unknown.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn roles_can_only_be_changed_for_members() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let outsider = TestUser::generate();
+    outsider.store_user(&app).await;
+    let (outsider_id, _) = login(&app, &outsider).await;
+    let (member_id, _) =
+        add_member(&app, &owner_token, organization_id, &TestUser::generate(), "member").await;
+
+    // act
+    let existing_user = set_member_role(&app, &owner_token, organization_id, outsider_id, "member").await;
+    let unknown_user = set_member_role(&app, &owner_token, organization_id, Uuid::new_v4(), "member").await;
+    let member = set_member_role(&app, &owner_token, organization_id, member_id, "admin").await;
+
+    // assert
+    assert_eq!(404, existing_user.status().as_u16());
+    assert_eq!(404, unknown_user.status().as_u16());
+    assert_eq!(204, member.status().as_u16());
+    assert_eq!(2, get_current_members(&app, &owner_token, organization_id).await.len());
+}
+
+#[tokio::test]
+pub async fn admins_cannot_grant_ownership() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let (admin_id, admin_token) =
+        add_member(&app, &owner_token, organization_id, &TestUser::generate(), "admin").await;
+    let other = TestUser::generate();
+    other.store_user(&app).await;
+
+    // act
+    let promoted = set_member_role(&app, &admin_token, organization_id, admin_id, "owner").await;
+    let invited = invite_member(&app, &admin_token, organization_id, &other.username, "owner").await;
+
+    // assert
+    assert_eq!(403, promoted.status().as_u16());
+    assert_eq!(403, invited.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn the_last_owner_cannot_leave_or_be_demoted() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (owner_id, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+
+    // act
+    let demoted = set_member_role(&app, &owner_token, organization_id, owner_id, "member").await;
+    let removed = remove_member(&app, &owner_token, organization_id, owner_id).await;
+
+    // assert
+    assert_eq!(409, demoted.status().as_u16());
+    assert_eq!(409, removed.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn two_owners_stepping_down_at_once_leave_one_owner() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (owner_id, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let (other_id, other_token) =
+        add_member(&app, &owner_token, organization_id, &TestUser::generate(), "owner").await;
+
+    // act
+    let (first, second) = tokio::join!(
+        set_member_role(&app, &owner_token, organization_id, owner_id, "member"),
+        remove_member(&app, &other_token, organization_id, other_id),
+    );
+
+    // assert
+    let mut statuses = vec![first.status().as_u16(), second.status().as_u16()];
+    statuses.sort();
+    assert_eq!(vec![204, 409], statuses);
+    assert_eq!(1, count_owners(&app, organization_id).await);
+}
+
+#[tokio::test]
+pub async fn a_member_can_leave_an_organization() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let (member_id, member_token) =
+        add_member(&app, &owner_token, organization_id, &TestUser::generate(), "member").await;
+
+    // act
+    let res = remove_member(&app, &member_token, organization_id, member_id).await;
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let current = app
+        .http_client
+        .get(format!("{}/organizations/current", app.address))
+        .bearer_auth(&member_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, current.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn the_last_owner_of_a_shared_organization_cannot_delete_their_account() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+    let (member_id, _) =
+        add_member(&app, &owner_token, organization_id, &TestUser::generate(), "member").await;
+    let password = json!({ "password": app.test_user.password });
+
+    // act
+    let refused = app.delete_account(&owner_token, &password).await;
+    set_member_role(&app, &owner_token, organization_id, member_id, "owner").await;
+    let deleted = app.delete_account(&owner_token, &password).await;
+
+    // assert
+    assert_eq!(409, refused.status().as_u16());
+    assert_eq!(204, deleted.status().as_u16());
+    assert_eq!(1, count_owners(&app, organization_id).await);
+}
+
+#[tokio::test]
+pub async fn organizations_without_other_members_are_deleted_with_the_account() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, owner_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &owner_token, "Acme").await;
+
+    // act
+    let res = app
+        .delete_account(&owner_token, &json!({ "password": app.test_user.password }))
+        .await;
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let organizations: i64 = sqlx::query_scalar("SELECT count(*) FROM organizations WHERE id = $1")
+        .bind(organization_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count organizations.");
+    assert_eq!(0, organizations);
+}
@@ -0,0 +1,67 @@
+use serde_json::json;
+
+use crate::helpers::spawn_app_with;
+
+#[tokio::test]
+pub async fn responses_carry_rate_limit_headers() {
+    // arrange
+    let app = spawn_app_with(|_| {}).await;
+
+    // act
+    let res = app.http_client.get(format!("{}/health_check", app.address))
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    assert!(res.headers().get("ratelimit-limit").is_some());
+    assert!(res.headers().get("ratelimit-remaining").is_some());
+    assert!(res.headers().get("ratelimit-reset").is_some());
+}
+
+#[tokio::test]
+pub async fn exceeding_the_auth_policy_returns_429() {
+    // arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limit.auth.capacity = 2;
+        c.rate_limit.auth.refill_per_second = 0.01;
+    })
+    .await;
+
+    for _ in 0..2 {
+        let res = app.login_user(&json!(app.test_user)).await;
+        assert_ne!(429, res.status().as_u16());
+    }
+
+    // act
+    let res = app.login_user(&json!(app.test_user)).await;
+
+    // assert
+    assert_eq!(429, res.status().as_u16());
+    assert!(res.headers().get("retry-after").is_some());
+    assert_eq!("0", res.headers().get("ratelimit-remaining").unwrap());
+}
+
+#[tokio::test]
+pub async fn the_auth_policy_does_not_limit_other_routes() {
+    // arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limit.auth.capacity = 1;
+        c.rate_limit.auth.refill_per_second = 0.01;
+    })
+    .await;
+
+    for _ in 0..2 {
+        app.login_user(&json!(app.test_user)).await;
+    }
+
+    // act
+    let res = app.http_client.get(format!("{}/health_check", app.address))
+        .send()
+        .await
+        .expect("Failed to send request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+}
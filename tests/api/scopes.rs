@@ -0,0 +1,81 @@
+use serde_json::json;
+use test_rs::features::{
+    api_keys::controller::CreatedApiKeyResponse, auth::controller::AuthResponse,
+};
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+pub async fn a_missing_scope_is_rejected_with_the_required_scopes() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    let api_key = app
+        .create_api_key(
+            &auth.access_token,
+            &json!({ "name": "read only", "expires_in_days": 1, "scopes": ["sessions:read"] }),
+        )
+        .await
+        .json::<CreatedApiKeyResponse>()
+        .await
+        .unwrap();
+
+    // act
+    let read = app
+        .http_client
+        .get(format!("{}/auth/sessions", app.address))
+        .header("X-Api-Key", &api_key.key)
+        .send()
+        .await
+        .expect("Failed to send sessions request.");
+    let write = app
+        .http_client
+        .delete(format!("{}/auth/sessions", app.address))
+        .header("X-Api-Key", &api_key.key)
+        .send()
+        .await
+        .expect("Failed to send sessions request.");
+
+    // assert
+    assert_eq!(200, read.status().as_u16());
+    assert_eq!(403, write.status().as_u16());
+    let challenge = write
+        .headers()
+        .get("www-authenticate")
+        .and_then(|v| v.to_str().ok())
+        .expect("WWW-Authenticate header was not set.");
+    assert!(challenge.contains(r#"scope="sessions:write""#));
+    let body = write.json::<serde_json::Value>().await.unwrap();
+    assert!(body["details"].as_str().unwrap().contains("sessions:write"));
+}
+
+#[tokio::test]
+pub async fn login_tokens_carry_every_scope() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    // act
+    let res = app
+        .http_client
+        .delete(format!("{}/auth/sessions", app.address))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send sessions request.");
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+}
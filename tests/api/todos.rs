@@ -0,0 +1,108 @@
+use serde_json::json;
+use test_rs::features::todos::{controller::TodoResponse, domain::TodoStatus};
+use uuid::Uuid;
+
+use crate::{
+    helpers::{spawn_app, TestApp, TestUser},
+    organizations::{create_organization, login},
+};
+
+async fn create_todo(
+    app: &TestApp,
+    access_token: &str,
+    organization_id: Uuid,
+    name: &str,
+) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}/todos", app.address))
+        .bearer_auth(access_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .json(&json!({ "name": name }))
+        .send()
+        .await
+        .expect("Failed to send create todo request.")
+}
+
+async fn get_todos(app: &TestApp, access_token: &str, organization_id: Uuid) -> reqwest::Response {
+    app.http_client
+        .get(format!("{}/todos", app.address))
+        .bearer_auth(access_token)
+        .header("X-Organization-Id", organization_id.to_string())
+        .send()
+        .await
+        .expect("Failed to send list todos request.")
+}
+
+#[tokio::test]
+pub async fn switching_organizations_switches_todos() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, access_token) = login(&app, &app.test_user).await;
+    let work = create_organization(&app, &access_token, "Work").await;
+    let home = create_organization(&app, &access_token, "Home").await;
+    create_todo(&app, &access_token, work, "Ship release").await;
+    create_todo(&app, &access_token, home, "Buy milk").await;
+
+    // act
+    let work_todos = get_todos(&app, &access_token, work).await;
+    let home_todos = get_todos(&app, &access_token, home).await;
+
+    // assert
+    let names = |todos: Vec<TodoResponse>| todos.into_iter().map(|t| t.name).collect::<Vec<_>>();
+    assert_eq!(vec!["Ship release"], names(work_todos.json().await.unwrap()));
+    assert_eq!(vec!["Buy milk"], names(home_todos.json().await.unwrap()));
+}
+
+#[tokio::test]
+pub async fn todos_of_another_organization_cannot_be_changed() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, access_token) = login(&app, &app.test_user).await;
+    let work = create_organization(&app, &access_token, "Work").await;
+    let home = create_organization(&app, &access_token, "Home").await;
+    let todo = create_todo(&app, &access_token, work, "Ship release")
+        .await
+        .json::<TodoResponse>()
+        .await
+        .unwrap();
+    let update_in = |organization_id: Uuid| {
+        app.http_client
+            .patch(format!("{}/todos/{}", app.address, todo.id))
+            .bearer_auth(&access_token)
+            .header("X-Organization-Id", organization_id.to_string())
+            .json(&json!({ "status": "done" }))
+            .send()
+    };
+
+    // act
+    let wrong_tenant = update_in(home).await.unwrap();
+    let right_tenant = update_in(work).await.unwrap();
+
+    // assert
+    assert_eq!(404, wrong_tenant.status().as_u16());
+    assert_eq!(200, right_tenant.status().as_u16());
+    let updated = right_tenant.json::<TodoResponse>().await.unwrap();
+    assert_eq!(TodoStatus::Done, updated.status);
+    assert_eq!("Ship release", updated.name);
+}
+
+#[tokio::test]
+pub async fn non_members_cannot_see_todos() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let (_, access_token) = login(&app, &app.test_user).await;
+    let organization_id = create_organization(&app, &access_token, "Acme").await;
+    create_todo(&app, &access_token, organization_id, "Secret plan").await;
+    let outsider = TestUser::generate();
+    outsider.store_user(&app).await;
+    let (_, outsider_token) = login(&app, &outsider).await;
+
+    // act
+    let res = get_todos(&app, &outsider_token, organization_id).await;
+
+    // assert
+    assert_eq!(403, res.status().as_u16());
+}
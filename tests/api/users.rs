@@ -0,0 +1,292 @@
+use serde_json::json;
+use test_rs::features::{auth::controller::AuthResponse, users::controller::UserProfileResponse};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_refresh_token, spawn_app, spawn_app_with, TestApp};
+
+const NEW_PASSWORD: &str = "violet kettle marching under rain";
+
+#[tokio::test]
+pub async fn a_user_can_read_and_edit_their_profile() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    // act
+    let res = app
+        .http_client
+        .patch(format!("{}/users/me", app.address))
+        .bearer_auth(&auth.access_token)
+        .json(&json!({ "display_name": "  Ada Lovelace " }))
+        .send()
+        .await
+        .expect("Failed to send update profile request.");
+
+    // assert
+    assert_eq!(200, res.status().as_u16());
+    let profile = app
+        .http_client
+        .get(format!("{}/users/me", app.address))
+        .bearer_auth(&auth.access_token)
+        .send()
+        .await
+        .expect("Failed to send profile request.")
+        .json::<UserProfileResponse>()
+        .await
+        .unwrap();
+    assert_eq!(auth.id, profile.id);
+    assert_eq!(app.test_user.username, profile.username);
+    assert_eq!(Some("Ada Lovelace".to_string()), profile.display_name);
+}
+
+#[tokio::test]
+pub async fn changing_the_password_requires_the_current_password() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app
+        .change_password(
+            &auth.access_token,
+            &rt,
+            &json!({ "current_password": "not the password", "new_password": NEW_PASSWORD }),
+        )
+        .await;
+
+    // assert
+    assert_eq!(401, res.status().as_u16());
+    assert_eq!(200, app.login_user(&json!(app.test_user)).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn changing_the_password_revokes_other_sessions() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let current = app.login_user(&json!(app.test_user)).await;
+    let current_rt = get_refresh_token(&current);
+    let auth = current.json::<AuthResponse>().await.unwrap();
+    let other_rt = get_refresh_token(&app.login_user(&json!(app.test_user)).await);
+
+    // act
+    let res = app
+        .change_password(
+            &auth.access_token,
+            &current_rt,
+            &json!({ "current_password": app.test_user.password, "new_password": NEW_PASSWORD }),
+        )
+        .await;
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    assert_eq!(200, app.refresh_token(&current_rt).await.status().as_u16());
+    assert_eq!(401, app.refresh_token(&other_rt).await.status().as_u16());
+    assert_eq!(401, app.login_user(&json!(app.test_user)).await.status().as_u16());
+    let res = app
+        .login_user(&json!({ "username": app.test_user.username, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(200, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn a_new_password_must_satisfy_the_policy() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let res = app.login_user(&json!(app.test_user)).await;
+    let rt = get_refresh_token(&res);
+    let auth = res.json::<AuthResponse>().await.unwrap();
+
+    // act
+    let res = app
+        .change_password(
+            &auth.access_token,
+            &rt,
+            &json!({ "current_password": app.test_user.password, "new_password": "short" }),
+        )
+        .await;
+
+    // assert
+    assert_eq!(400, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn deleting_the_account_requires_the_password() {
+    // arrange
+    let app = spawn_app().await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    // act
+    let rejected = app
+        .delete_account(&auth.access_token, &json!({ "password": "not the password" }))
+        .await;
+    let accepted = app
+        .delete_account(&auth.access_token, &json!({ "password": app.test_user.password }))
+        .await;
+
+    // assert
+    assert_eq!(401, rejected.status().as_u16());
+    assert_eq!(204, accepted.status().as_u16());
+    let users: i64 = sqlx::query_scalar("SELECT count(*) FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count users.");
+    assert_eq!(0, users);
+    assert_eq!(401, app.login_user(&json!(app.test_user)).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn guessing_the_current_password_locks_the_account() {
+    // arrange
+    let app = spawn_app_with(|c| c.login_throttle.max_attempts = 2).await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        let res = app
+            .delete_account(&auth.access_token, &json!({ "password": "not the password" }))
+            .await;
+        assert_eq!(401, res.status().as_u16());
+    }
+
+    // act
+    let res = app
+        .delete_account(&auth.access_token, &json!({ "password": app.test_user.password }))
+        .await;
+
+    // assert
+    assert_eq!(423, res.status().as_u16());
+    assert_eq!(423, app.login_user(&json!(app.test_user)).await.status().as_u16());
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+pub async fn an_email_change_takes_effect_once_confirmed() {
+    // arrange
+    let app = spawn_app().await;
+    mount_email_server(&app).await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    let res = app
+        .change_email(
+            &auth.access_token,
+            &json!({ "email": "Ada@Example.com", "password": app.test_user.password }),
+        )
+        .await;
+    assert_eq!(202, res.status().as_u16());
+    let login_by_email = json!({ "email": "ada@example.com", "password": app.test_user.password });
+    assert_eq!(401, app.login_user(&login_by_email).await.status().as_u16());
+
+    // act
+    let token = app.get_email_token().await;
+    let res = app.confirm_email(&token).await;
+
+    // assert
+    assert_eq!(204, res.status().as_u16());
+    let requests = app.email_server.received_requests().await.unwrap();
+    let email: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!("Ada@Example.com", email["To"]);
+    assert_eq!(200, app.login_user(&login_by_email).await.status().as_u16());
+    assert_eq!(401, app.confirm_email(&token).await.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_email_taken_before_confirmation_returns_409() {
+    // arrange
+    let app = spawn_app().await;
+    mount_email_server(&app).await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+    app.change_email(
+        &auth.access_token,
+        &json!({ "email": "ada@example.com", "password": app.test_user.password }),
+    )
+    .await;
+    let token = app.get_email_token().await;
+    app.register_confirmed_user(&json!({
+        "username": "ada",
+        "email": "ADA@example.com",
+        "password": NEW_PASSWORD
+    }))
+    .await;
+
+    // act
+    let res = app.confirm_email(&token).await;
+
+    // assert
+    assert_eq!(409, res.status().as_u16());
+}
+
+#[tokio::test]
+pub async fn an_email_change_requires_a_valid_address_and_the_password() {
+    // arrange
+    let app = spawn_app().await;
+    mount_email_server(&app).await;
+    app.test_user.store_user(&app).await;
+    let auth = app
+        .login_user(&json!(app.test_user))
+        .await
+        .json::<AuthResponse>()
+        .await
+        .unwrap();
+
+    // act
+    let invalid = app
+        .change_email(
+            &auth.access_token,
+            &json!({ "email": "not-an-email", "password": app.test_user.password }),
+        )
+        .await;
+    let unauthorized = app
+        .change_email(
+            &auth.access_token,
+            &json!({ "email": "ada@example.com", "password": "not the password" }),
+        )
+        .await;
+
+    // assert
+    assert_eq!(400, invalid.status().as_u16());
+    assert_eq!(401, unauthorized.status().as_u16());
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}